@@ -1,4 +1,5 @@
 use actix_web::{web, App, HttpServer};
+use ussdframework::gateway::africastalking;
 use ussdframework::prelude::*;
 
 mod config;
@@ -19,7 +20,7 @@ async fn main() -> std::io::Result<()> {
         let session_store = InMemorySessionStore::new();
 
         // Create a new instance of UssdApp
-        let app = UssdApp::new(false, Some(Box::new(session_store)));
+        let mut app = UssdApp::new(false, Some(Box::new(session_store)));
 
         // Register functions
         app.register_functions(functions::get_functions());
@@ -34,6 +35,10 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(menus))
             .service(health_check)
             .route("/ussd", web::post().to(handle_ussd))
+            .route(
+                "/ussd/africastalking",
+                web::post().to(africastalking::handle_ussd),
+            )
     })
     .bind("127.0.0.1:3000")?
     .run()
@@ -12,7 +12,7 @@ pub async fn handle_ussd(
     menus: web::Data<USSDMenu>,
 ) -> HttpResponse {
     let request = req.into_inner();
-    let response = app.run(request, menus.get_ref().clone());
+    let response = app.run(request, menus.get_ref());
     print!("Response: {:?}", response);
     HttpResponse::Ok().body(response.message)
 }
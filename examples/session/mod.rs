@@ -30,4 +30,10 @@ impl SessionCache for InMemorySessionStore {
             None => Ok(None),
         }
     }
+
+    fn delete_session(&self, session_id: &str) -> Result<(), String> {
+        let mut data = self.data.lock().unwrap();
+        data.remove(session_id);
+        Ok(())
+    }
 }
@@ -31,6 +31,8 @@ const Screen = () => {
         { value: 'router', label: 'Router' },
         { value: 'menu', label: 'Menu' },
         { value: 'input', label: 'Input' },
+        { value: 'confirm', label: 'Confirm' },
+        { value: 'info', label: 'Info' },
       ],
     },
     {
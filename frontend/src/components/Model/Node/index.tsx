@@ -54,6 +54,8 @@ export default function MenuNode() {
 		  [ScreenType.FUNCTION]: 4,
 		  [ScreenType.ROUTER]: 5,
 		  [ScreenType.QUIT]: 6,
+		  [ScreenType.CONFIRM]: 7,
+		  [ScreenType.INFO]: 8,
 		};
 	  
 		return screens.sort((a, b) => {
@@ -29,6 +29,8 @@ const ScreenForm = () => {
         { value: 'router', label: 'Router' },
         { value: 'menu', label: 'Menu' },
         { value: 'input', label: 'Input' },
+        { value: 'confirm', label: 'Confirm' },
+        { value: 'info', label: 'Info' },
       ],
     },
     {
@@ -3,8 +3,10 @@ export interface MenuItem {
 	screen_name: string;
 	name: string;
 	option: string;
+	// A single text or a JSON object of translations by language
 	display_name: string;
 	next_screen: string;
+	language?: string;
 }
 
 export interface RouterOption {
@@ -17,6 +19,7 @@ export interface RouterOption {
 declare interface Screen {
 	id?: number;
 	name: string;
+	// A single text or a JSON object of translations by language
 	text: string;
 	screen_type: ScreenType;
 	default_next_screen: string;
@@ -26,6 +29,14 @@ declare interface Screen {
 	router_options?: RouterOption[];
 	input_identifier?: string;
 	input_type?: string;
+	input_validation?: string;
+	// The dynamic menu items of a menu screen, as JSON
+	dynamic_menu?: string;
+	// The screens a function screen moves to when its service fails or times out
+	on_error_screen?: string;
+	on_timeout_screen?: string;
+	// The accept and reject choices of a confirm screen, as JSON
+	confirm?: string;
 	// Additional fields based on screen type
 }
 
@@ -36,6 +47,8 @@ export enum ScreenType {
 	FUNCTION = "Function",
 	ROUTER = "Router",
 	QUIT = "Quit",
+	CONFIRM = "Confirm",
+	INFO = "Info",
 }
 
 export default Screen;
@@ -5,6 +5,12 @@ declare interface Service {
 	function_url?: string;
 	data_key: string;
 	service_code?: string;
+	kind?: string;
+	// Milliseconds a call may take, unlimited when unset
+	timeout_ms?: number;
+	retries?: number;
+	// Milliseconds before the first retry, doubled before each following retry
+	retry_backoff_ms?: number;
 }
 
 export default Service;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Add the kind of service, either a registered function or an http service
+ALTER TABLE services ADD COLUMN kind TEXT NOT NULL DEFAULT 'Function';
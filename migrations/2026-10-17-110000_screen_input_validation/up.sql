@@ -0,0 +1,2 @@
+-- Add the validation rules of input screens, stored as JSON
+ALTER TABLE screens ADD COLUMN input_validation TEXT;
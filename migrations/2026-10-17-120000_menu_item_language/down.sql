@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE menu_items DROP COLUMN language;
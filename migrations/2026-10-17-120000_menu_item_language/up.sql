@@ -0,0 +1,2 @@
+-- Add the language a menu item switches the session to
+ALTER TABLE menu_items ADD COLUMN language TEXT;
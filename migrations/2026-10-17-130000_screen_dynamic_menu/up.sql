@@ -0,0 +1,2 @@
+-- Add the dynamic menu items of menu screens, stored as JSON
+ALTER TABLE screens ADD COLUMN dynamic_menu TEXT;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE services DROP COLUMN retry_backoff_ms;
+ALTER TABLE services DROP COLUMN retries;
+ALTER TABLE services DROP COLUMN timeout_ms;
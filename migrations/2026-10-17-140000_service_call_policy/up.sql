@@ -0,0 +1,4 @@
+-- Add the timeout, retry count and retry backoff of service calls
+ALTER TABLE services ADD COLUMN timeout_ms BIGINT;
+ALTER TABLE services ADD COLUMN retries INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE services ADD COLUMN retry_backoff_ms BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE screens DROP COLUMN on_timeout_screen;
+ALTER TABLE screens DROP COLUMN on_error_screen;
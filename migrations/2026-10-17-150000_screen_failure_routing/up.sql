@@ -0,0 +1,3 @@
+-- Add the screens function screens move to when their service fails or times out
+ALTER TABLE screens ADD COLUMN on_error_screen TEXT;
+ALTER TABLE screens ADD COLUMN on_timeout_screen TEXT;
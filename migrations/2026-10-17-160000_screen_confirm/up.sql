@@ -0,0 +1,2 @@
+-- Add the accept and reject choices of confirm screens, stored as JSON
+ALTER TABLE screens ADD COLUMN confirm TEXT;
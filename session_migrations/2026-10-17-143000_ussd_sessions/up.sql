@@ -0,0 +1,11 @@
+-- Sessions stored by SqliteSessionStore, the session is stored as JSON
+CREATE TABLE ussd_sessions (
+    session_id TEXT PRIMARY KEY NOT NULL,
+    msisdn TEXT NOT NULL,
+    session TEXT NOT NULL,
+    expires_at BIGINT,
+    updated_at BIGINT NOT NULL
+);
+
+CREATE INDEX ussd_sessions_msisdn ON ussd_sessions (msisdn);
+CREATE INDEX ussd_sessions_expires_at ON ussd_sessions (expires_at);
@@ -0,0 +1,399 @@
+//! Journeys of sessions through the menu, and the drop-off and funnel analytics built from them.
+//!
+//! When a session ends on a `Quit` screen, or expires, the engine builds a `SessionJourney` and
+//! passes it to the hook set with `UssdApp::on_journey`. Journeys can be
+//! shipped to a data warehouse from the hook, or aggregated in process with `JourneyAnalytics`:
+//!
+//! ```rust
+//! use std::sync::Arc;
+//! use ussdframework::analytics::{Funnel, JourneyAnalytics};
+//! use ussdframework::prelude::*;
+//!
+//! let analytics = Arc::new(JourneyAnalytics::new(vec![Funnel::new(
+//!     "airtime",
+//!     &["MainScreen", "AirtimeScreen", "SuccessScreen"],
+//! )]));
+//!
+//! let mut app = UssdApp::new(true, None);
+//! let recorder = analytics.clone();
+//! app.on_journey(move |journey: &SessionJourney| recorder.record(journey));
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Mutex,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::USSDSession;
+
+/// The path of a session through the menu, emitted when the session ends or expires.
+///
+/// # Fields
+///
+/// * `session_id`: The session the journey belongs to.
+/// * `msisdn`: The phone number of the user.
+/// * `screens`: The screens displayed to the user, in order.
+/// * `last_screen`: The screen the session ended or expired on.
+/// * `duration`: The time from the start of the session to its last interaction.
+/// * `completed`: Whether the session ended on a `Quit` screen. Expired sessions are not completed.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SessionJourney {
+    pub session_id: String,
+    pub msisdn: String,
+    pub screens: Vec<String>,
+    pub last_screen: String,
+    pub duration: Duration,
+    pub completed: bool,
+}
+
+impl SessionJourney {
+    /// Builds the journey of `session`, which ended on a `Quit` screen if `completed`.
+    pub fn from_session(session: &USSDSession, completed: bool) -> Self {
+        let duration = session
+            .last_interaction_time
+            .duration_since(session.started_at)
+            .unwrap_or_default();
+
+        SessionJourney {
+            session_id: session.session_id.clone(),
+            msisdn: session.msisdn.clone(),
+            screens: session.displayed_screens.clone(),
+            last_screen: session.current_screen.clone(),
+            duration,
+            completed,
+        }
+    }
+
+    /// Returns whether the journey displayed `steps` in order, with any screens in between.
+    pub fn passes_through(&self, steps: &[String]) -> bool {
+        self.reached(steps) == steps.len()
+    }
+
+    /// Returns how many of the `steps` the journey displayed in order.
+    fn reached(&self, steps: &[String]) -> usize {
+        let mut reached = 0;
+        for screen in &self.screens {
+            if steps.get(reached) == Some(screen) {
+                reached += 1;
+            }
+        }
+        reached
+    }
+}
+
+/// A named sequence of screens, such as the screens of an airtime purchase.
+///
+/// A journey converts to a step of the funnel when it displays every screen up to that step in
+/// order.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Funnel {
+    pub name: String,
+    pub steps: Vec<String>,
+}
+
+impl Funnel {
+    pub fn new(name: &str, steps: &[&str]) -> Self {
+        Funnel {
+            name: name.to_string(),
+            steps: steps.iter().map(|step| step.to_string()).collect(),
+        }
+    }
+}
+
+/// How many journeys displayed a screen, and how many were abandoned on it.
+///
+/// * `views`: The journeys that displayed the screen.
+/// * `drop_offs`: The journeys that expired on the screen.
+/// * `completions`: The journeys that ended on the screen.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScreenDropOff {
+    pub screen: String,
+    pub views: u64,
+    pub drop_offs: u64,
+    pub completions: u64,
+}
+
+impl ScreenDropOff {
+    /// Returns the share of the journeys displaying the screen that were abandoned on it.
+    pub fn drop_off_rate(&self) -> f64 {
+        rate(self.drop_offs, self.views)
+    }
+}
+
+/// How many journeys reached a step of a funnel.
+///
+/// * `conversion`: The share of the journeys entering the funnel that reached the step.
+/// * `step_conversion`: The share of the journeys reaching the previous step that reached this one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FunnelStep {
+    pub screen: String,
+    pub reached: u64,
+    pub conversion: f64,
+    pub step_conversion: f64,
+}
+
+/// The conversion of the journeys through a funnel, step by step.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FunnelReport {
+    pub name: String,
+    pub steps: Vec<FunnelStep>,
+}
+
+#[derive(Debug, Default)]
+struct Totals {
+    journeys: u64,
+    screens: BTreeMap<String, ScreenDropOff>,
+    funnels: Vec<Vec<u64>>,
+}
+
+/// Aggregates journeys into per-screen drop-off and funnel conversion.
+///
+/// Only the counts are kept, so the aggregate stays small however many journeys are recorded.
+#[derive(Debug, Default)]
+pub struct JourneyAnalytics {
+    funnels: Vec<Funnel>,
+    totals: Mutex<Totals>,
+}
+
+impl JourneyAnalytics {
+    /// Creates an aggregate that tracks the conversion through the given funnels.
+    pub fn new(funnels: Vec<Funnel>) -> Self {
+        let totals = Totals {
+            funnels: funnels
+                .iter()
+                .map(|funnel| vec![0; funnel.steps.len()])
+                .collect(),
+            ..Default::default()
+        };
+
+        JourneyAnalytics {
+            funnels,
+            totals: Mutex::new(totals),
+        }
+    }
+
+    /// Adds a journey to the aggregate.
+    pub fn record(&self, journey: &SessionJourney) {
+        let mut totals = self.totals.lock().unwrap();
+        totals.journeys += 1;
+
+        let displayed: HashSet<&String> = journey.screens.iter().collect();
+        for screen in displayed {
+            totals.screens.entry(screen.clone()).or_default().views += 1;
+        }
+
+        let last_screen = totals
+            .screens
+            .entry(journey.last_screen.clone())
+            .or_default();
+        if journey.completed {
+            last_screen.completions += 1;
+        } else {
+            last_screen.drop_offs += 1;
+        }
+
+        for (funnel, counts) in self.funnels.iter().zip(totals.funnels.iter_mut()) {
+            let reached = journey.reached(&funnel.steps);
+            for count in counts.iter_mut().take(reached) {
+                *count += 1;
+            }
+        }
+    }
+
+    /// Returns the number of journeys recorded.
+    pub fn journeys(&self) -> u64 {
+        self.totals.lock().unwrap().journeys
+    }
+
+    /// Returns the drop-off of every screen displayed by a journey, highest drop-off rate first.
+    pub fn screen_drop_offs(&self) -> Vec<ScreenDropOff> {
+        let totals = self.totals.lock().unwrap();
+        let mut screens: Vec<ScreenDropOff> = totals
+            .screens
+            .iter()
+            .map(|(screen, drop_off)| ScreenDropOff {
+                screen: screen.clone(),
+                ..drop_off.clone()
+            })
+            .collect();
+
+        screens.sort_by(|a, b| b.drop_off_rate().total_cmp(&a.drop_off_rate()));
+        screens
+    }
+
+    /// Returns the conversion through each funnel.
+    pub fn funnel_reports(&self) -> Vec<FunnelReport> {
+        let totals = self.totals.lock().unwrap();
+
+        self.funnels
+            .iter()
+            .zip(totals.funnels.iter())
+            .map(|(funnel, counts)| {
+                let entered = counts.first().copied().unwrap_or_default();
+                let steps = funnel
+                    .steps
+                    .iter()
+                    .zip(counts.iter())
+                    .enumerate()
+                    .map(|(index, (screen, reached))| {
+                        let previous = if index == 0 {
+                            entered
+                        } else {
+                            counts[index - 1]
+                        };
+                        FunnelStep {
+                            screen: screen.clone(),
+                            reached: *reached,
+                            conversion: rate(*reached, entered),
+                            step_conversion: rate(*reached, previous),
+                        }
+                    })
+                    .collect();
+
+                FunnelReport {
+                    name: funnel.name.clone(),
+                    steps,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns `part / whole`, or 0 when `whole` is 0.
+fn rate(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{example_menu, ConversationTest};
+
+    fn journey(screens: &[&str], completed: bool) -> SessionJourney {
+        SessionJourney {
+            session_id: "session".to_string(),
+            msisdn: "1234567890".to_string(),
+            screens: screens.iter().map(|screen| screen.to_string()).collect(),
+            last_screen: screens.last().unwrap().to_string(),
+            duration: Duration::from_secs(30),
+            completed,
+        }
+    }
+
+    #[test]
+    fn test_screen_drop_offs() {
+        let analytics = JourneyAnalytics::new(Vec::new());
+        analytics.record(&journey(&["Main", "Airtime", "Amount", "Success"], true));
+        analytics.record(&journey(&["Main", "Airtime", "Amount"], false));
+        analytics.record(&journey(&["Main", "Airtime"], false));
+        analytics.record(&journey(&["Main", "Transfer", "Main", "Airtime"], false));
+
+        let drop_offs = analytics.screen_drop_offs();
+        assert_eq!(analytics.journeys(), 4);
+
+        let airtime = drop_offs.iter().find(|s| s.screen == "Airtime").unwrap();
+        assert_eq!((airtime.views, airtime.drop_offs), (4, 2));
+        assert_eq!(airtime.drop_off_rate(), 0.5);
+
+        let main = drop_offs.iter().find(|s| s.screen == "Main").unwrap();
+        assert_eq!((main.views, main.drop_offs), (4, 0));
+
+        let success = drop_offs.iter().find(|s| s.screen == "Success").unwrap();
+        assert_eq!(success.completions, 1);
+
+        // Highest drop-off rate first
+        assert_eq!(drop_offs[0].screen, "Airtime");
+    }
+
+    #[test]
+    fn test_funnel_reports() {
+        let analytics = JourneyAnalytics::new(vec![Funnel::new(
+            "airtime",
+            &["Main", "Airtime", "Amount", "Success"],
+        )]);
+        analytics.record(&journey(&["Main", "Airtime", "Amount", "Success"], true));
+        analytics.record(&journey(&["Main", "Airtime", "Amount"], false));
+        analytics.record(&journey(&["Main", "Transfer", "Main", "Airtime"], false));
+        analytics.record(&journey(&["Main", "Transfer"], false));
+
+        let report = &analytics.funnel_reports()[0];
+        assert_eq!(report.name, "airtime");
+
+        let reached: Vec<u64> = report.steps.iter().map(|step| step.reached).collect();
+        assert_eq!(reached, vec![4, 3, 2, 1]);
+        assert_eq!(report.steps[3].conversion, 0.25);
+        assert_eq!(report.steps[3].step_conversion, 0.5);
+
+        assert!(journey(&["Main", "Airtime", "Main", "Success"], true)
+            .passes_through(&["Airtime".to_string(), "Success".to_string()]));
+    }
+
+    #[test]
+    fn test_conversation_journeys() {
+        let journeys = Arc::new(Mutex::new(Vec::new()));
+        let recorded = journeys.clone();
+
+        let mut test = ConversationTest::new(example_menu()).mock_data(
+            "buy_airtime",
+            json!({"status": "success", "message": "Airtime sent"}),
+        );
+        test.app_mut().on_journey(move |journey: &SessionJourney| {
+            recorded.lock().unwrap().push(journey.clone());
+        });
+
+        // A session that reaches a Quit screen is completed
+        test.dial().input("3").input("1").input("50");
+        test.expect_screen("SuccessScreen");
+        {
+            let journeys = journeys.lock().unwrap();
+            assert_eq!(journeys.len(), 1);
+            assert!(journeys[0].completed);
+            assert_eq!(journeys[0].last_screen, "SuccessScreen");
+            assert_eq!(journeys[0].screens[..2], ["MainScreen", "AirtimeScreen"]);
+        }
+
+        // A session left idle is reported as abandoned by the next request
+        test.dial().input("3");
+        let cache = test.app().session_cache();
+        let mut session = cache.retrieve_session("test-session").unwrap().unwrap();
+        session.last_interaction_time -= Duration::from_secs(600);
+        cache.store_session(&session).unwrap();
+        test.input("1");
+
+        {
+            let journeys = journeys.lock().unwrap();
+            assert_eq!(journeys.len(), 2);
+            assert!(!journeys[1].completed);
+            assert_eq!(journeys[1].last_screen, "AirtimeScreen");
+            assert_eq!(journeys[1].screens, ["MainScreen", "AirtimeScreen"]);
+        }
+
+        // Screens the user went back from are still part of the journey
+        test.dial().input("3").input("0");
+        test.expect_screen("MainScreen");
+        let cache = test.app().session_cache();
+        let mut session = cache.retrieve_session("test-session").unwrap().unwrap();
+        session.last_interaction_time -= Duration::from_secs(600);
+        cache.store_session(&session).unwrap();
+        test.input("1");
+
+        let journeys = journeys.lock().unwrap();
+        assert_eq!(journeys.len(), 3);
+        assert_eq!(journeys[2].last_screen, "MainScreen");
+        assert_eq!(
+            journeys[2].screens,
+            ["MainScreen", "AirtimeScreen", "MainScreen"]
+        );
+    }
+}
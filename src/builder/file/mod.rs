@@ -4,7 +4,7 @@ use std::collections::HashMap;
 
 pub use json::{from_json, to_json};
 
-use crate::core::USSDMenu;
+use crate::{core::USSDMenu, error};
 
 use super::{Database, DatabaseManager, ScreenModel, ServiceModel};
 
@@ -13,6 +13,7 @@ use super::{Database, DatabaseManager, ScreenModel, ServiceModel};
 /// This function initializes a new `DatabaseManager` and uses it to retrieve screen and service
 /// data. The retrieved data is then converted into USSD-compatible formats and stored in
 /// hash maps. These hash maps are used to construct a `USSDMenu` object which is then returned.
+/// Screen texts and expressions that do not parse are logged as errors.
 ///
 /// # Panics
 ///
@@ -46,5 +47,16 @@ pub fn build() -> USSDMenu {
         services.insert(s.name.clone(), s.to_ussd_service());
     }
 
-    USSDMenu { menus, services }
+    let menu = USSDMenu {
+        menus,
+        services,
+        ..Default::default()
+    };
+
+    // Report expressions that do not parse now, rather than when a session reaches them
+    for e in menu.expression_errors() {
+        error!("Invalid menu: {}", e);
+    }
+
+    menu
 }
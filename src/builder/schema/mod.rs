@@ -3,6 +3,6 @@ pub mod screens;
 pub mod services;
 
 pub use screens::menu_items::MenuItem;
-pub use screens::router_option::RouterOption;
 pub use screens::model_screen::Screen;
-pub use services::model_service::Service;
\ No newline at end of file
+pub use screens::router_option::RouterOption;
+pub use services::model_service::Service;
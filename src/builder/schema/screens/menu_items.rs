@@ -11,6 +11,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::builder::{Database, DatabaseManager, QueryEnum};
 use crate::core::ussd_screens::USSDMenuItems;
+use crate::core::LocalizedText;
 
 // Define structure for a menu item
 #[derive(Debug, Clone, Deserialize, Serialize, Insertable, Queryable, AsChangeset)]
@@ -20,14 +21,16 @@ pub struct MenuItem {
     pub option: String,
     pub display_name: String,
     pub next_screen: String,
+    pub language: Option<String>,
 }
 
 impl MenuItem {
     pub fn to_ussd_menu_item(&self) -> (String, USSDMenuItems) {
         let menu_item = USSDMenuItems {
             option: self.option.clone(),
-            display_name: self.display_name.clone(),
+            display_name: LocalizedText::from_string(&self.display_name),
             next_screen: self.next_screen.clone(),
+            language: self.language.clone(),
         };
 
         (self.name.clone(), menu_item)
@@ -42,8 +45,9 @@ impl MenuItem {
             screen_name,
             name,
             option: menu_item.option,
-            display_name: menu_item.display_name,
+            display_name: menu_item.display_name.to_string(),
             next_screen: menu_item.next_screen,
+            language: menu_item.language,
         }
     }
 }
@@ -58,6 +62,7 @@ impl FromSql<Text, Sqlite> for MenuItem {
             option: parts[2].to_string(),
             display_name: parts[3].to_string(),
             next_screen: parts[4].to_string(),
+            language: parts.get(5).map(|part| part.to_string()),
         })
     }
 }
@@ -70,6 +75,7 @@ table! {
         option -> Text,
         display_name -> Text,
         next_screen -> Text,
+        language -> Nullable<Text>,
     }
 }
 
@@ -82,11 +88,12 @@ impl
             diesel::sql_types::Text,
             diesel::sql_types::Text,
             diesel::sql_types::Text,
+            diesel::sql_types::Nullable<diesel::sql_types::Text>,
         ),
         Sqlite,
     > for MenuItem
 {
-    type Row = (i32, String, String, String, String, String);
+    type Row = (i32, String, String, String, String, String, Option<String>);
 
     fn build(row: Self::Row) -> Result<MenuItem, Box<(dyn StdError + Send + Sync + 'static)>> {
         Ok(MenuItem {
@@ -95,6 +102,7 @@ impl
             option: row.3,
             display_name: row.4,
             next_screen: row.5,
+            language: row.6,
         })
     }
 }
@@ -10,7 +10,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::builder::{Database, DatabaseManager, QueryEnum};
 use crate::core::ussd_screens::USSDScreen;
-use crate::core::ScreenType;
+use crate::core::{LocalizedText, ScreenType};
 
 use super::menu_items::MenuItem;
 use super::router_option::RouterOption;
@@ -26,6 +26,11 @@ pub struct Screen {
     pub function: Option<String>,
     pub input_identifier: Option<String>,
     pub input_type: Option<String>,
+    pub input_validation: Option<String>,
+    pub dynamic_menu: Option<String>,
+    pub on_error_screen: Option<String>,
+    pub on_timeout_screen: Option<String>,
+    pub confirm: Option<String>,
 }
 
 impl Screen {
@@ -59,28 +64,57 @@ impl Screen {
         }
 
         USSDScreen {
-            text: self.text.clone(),
+            text: LocalizedText::from_string(&self.text),
             screen_type: ScreenType::from_string(&self.screen_type),
             default_next_screen: self.default_next_screen.clone(),
             service_code: self.service_code.clone(),
             function: self.function.clone(),
             input_identifier: self.input_identifier.clone(),
             input_type: self.input_type.clone(),
+            input_validation: self
+                .input_validation
+                .as_ref()
+                .and_then(|validation| serde_json::from_str(validation).ok()),
+            dynamic_menu: self
+                .dynamic_menu
+                .as_ref()
+                .and_then(|dynamic_menu| serde_json::from_str(dynamic_menu).ok()),
+            on_error_screen: self.on_error_screen.clone(),
+            on_timeout_screen: self.on_timeout_screen.clone(),
+            confirm: self
+                .confirm
+                .as_ref()
+                .and_then(|confirm| serde_json::from_str(confirm).ok()),
             menu_items: Some(menu_items_map),
             router_options: Some(router_options_vec),
+            ..Default::default()
         }
     }
 
     pub fn from_ussd_menu(name: String, screen: USSDScreen) -> Self {
         Screen {
             name,
-            text: screen.text.clone(),
+            text: screen.text.to_string(),
             screen_type: screen.screen_type.to_string(),
             default_next_screen: screen.default_next_screen.clone(),
             service_code: screen.service_code.clone(),
             function: screen.function.clone(),
             input_identifier: screen.input_identifier.clone(),
             input_type: screen.input_type.clone(),
+            input_validation: screen
+                .input_validation
+                .as_ref()
+                .and_then(|validation| serde_json::to_string(validation).ok()),
+            dynamic_menu: screen
+                .dynamic_menu
+                .as_ref()
+                .and_then(|dynamic_menu| serde_json::to_string(dynamic_menu).ok()),
+            on_error_screen: screen.on_error_screen.clone(),
+            on_timeout_screen: screen.on_timeout_screen.clone(),
+            confirm: screen
+                .confirm
+                .as_ref()
+                .and_then(|confirm| serde_json::to_string(confirm).ok()),
         }
     }
 }
@@ -96,6 +130,11 @@ table! {
         function -> Nullable<Text>,
         input_identifier -> Nullable<Text>,
         input_type -> Nullable<Text>,
+        input_validation -> Nullable<Text>,
+        dynamic_menu -> Nullable<Text>,
+        on_error_screen -> Nullable<Text>,
+        on_timeout_screen -> Nullable<Text>,
+        confirm -> Nullable<Text>,
     }
 }
 
@@ -111,6 +150,11 @@ impl
             diesel::sql_types::Nullable<Text>,
             diesel::sql_types::Nullable<Text>,
             diesel::sql_types::Nullable<Text>,
+            diesel::sql_types::Nullable<Text>,
+            diesel::sql_types::Nullable<Text>,
+            diesel::sql_types::Nullable<Text>,
+            diesel::sql_types::Nullable<Text>,
+            diesel::sql_types::Nullable<Text>,
         ),
         Sqlite,
     > for Screen
@@ -125,6 +169,11 @@ impl
         Option<String>,
         Option<String>,
         Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
     );
 
     fn build(row: Self::Row) -> Result<Screen, Box<(dyn StdError + Send + Sync + 'static)>> {
@@ -137,6 +186,11 @@ impl
             function: row.6,
             input_identifier: row.7,
             input_type: row.8,
+            input_validation: row.9,
+            dynamic_menu: row.10,
+            on_error_screen: row.11,
+            on_timeout_screen: row.12,
+            confirm: row.13,
         })
     }
 }
@@ -156,6 +210,11 @@ impl diesel::deserialize::FromSql<Text, Sqlite> for Screen {
             function: Some(parts[5].to_string()),
             input_identifier: Some(parts[6].to_string()),
             input_type: Some(parts[7].to_string()),
+            input_validation: parts.get(8).map(|part| part.to_string()),
+            dynamic_menu: parts.get(9).map(|part| part.to_string()),
+            on_error_screen: parts.get(10).map(|part| part.to_string()),
+            on_timeout_screen: parts.get(11).map(|part| part.to_string()),
+            confirm: parts.get(12).map(|part| part.to_string()),
         })
     }
 }
@@ -1 +1 @@
-pub mod model_service;
\ No newline at end of file
+pub mod model_service;
@@ -5,7 +5,7 @@ use serde::ser::StdError;
 use serde::{Deserialize, Serialize};
 
 use crate::builder::{Database, DatabaseManager, QueryEnum};
-use crate::core::USSDService;
+use crate::core::{ServiceKind, USSDService};
 
 #[derive(Debug, Clone, Deserialize, Serialize, Insertable, QueryableByName, AsChangeset)]
 pub struct Service {
@@ -14,6 +14,14 @@ pub struct Service {
     pub function_url: Option<String>,
     pub data_key: String,
     pub service_code: Option<String>,
+    #[serde(default)]
+    pub kind: String,
+    #[serde(default)]
+    pub timeout_ms: Option<i64>,
+    #[serde(default)]
+    pub retries: i32,
+    #[serde(default)]
+    pub retry_backoff_ms: i64,
 }
 
 table! {
@@ -24,6 +32,10 @@ table! {
         function_url -> Nullable<Text>,
         data_key -> Text,
         service_code -> Nullable<Text>,
+        kind -> Text,
+        timeout_ms -> Nullable<BigInt>,
+        retries -> Integer,
+        retry_backoff_ms -> BigInt,
     }
 }
 
@@ -35,6 +47,10 @@ impl Service {
             function_url: service.function_url,
             data_key: service.data_key,
             service_code: service.service_code,
+            kind: service.kind.to_string(),
+            timeout_ms: service.timeout_ms.map(|timeout| timeout as i64),
+            retries: service.retries as i32,
+            retry_backoff_ms: service.retry_backoff_ms as i64,
         }
     }
 
@@ -44,6 +60,10 @@ impl Service {
             function_url: self.function_url.clone(),
             data_key: self.data_key.clone(),
             service_code: self.service_code.clone(),
+            kind: ServiceKind::from_string(&self.kind),
+            timeout_ms: self.timeout_ms.map(|timeout| timeout.max(0) as u64),
+            retries: self.retries.max(0) as u32,
+            retry_backoff_ms: self.retry_backoff_ms.max(0) as u64,
         }
     }
 }
@@ -57,11 +77,26 @@ impl
             diesel::sql_types::Nullable<diesel::sql_types::Text>,
             diesel::sql_types::Text,
             diesel::sql_types::Nullable<diesel::sql_types::Text>,
+            diesel::sql_types::Text,
+            diesel::sql_types::Nullable<diesel::sql_types::BigInt>,
+            diesel::sql_types::Integer,
+            diesel::sql_types::BigInt,
         ),
         diesel::sqlite::Sqlite,
     > for Service
 {
-    type Row = (i32, String, String, Option<String>, String, Option<String>);
+    type Row = (
+        i32,
+        String,
+        String,
+        Option<String>,
+        String,
+        Option<String>,
+        String,
+        Option<i64>,
+        i32,
+        i64,
+    );
 
     fn build(row: Self::Row) -> Result<Service, Box<(dyn StdError + Send + Sync + 'static)>> {
         Ok(Service {
@@ -70,6 +105,10 @@ impl
             function_url: row.3,
             data_key: row.4,
             service_code: row.5,
+            kind: row.6,
+            timeout_ms: row.7,
+            retries: row.8,
+            retry_backoff_ms: row.9,
         })
     }
 }
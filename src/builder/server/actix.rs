@@ -42,7 +42,8 @@ pub async fn start_server(port: u16) -> std::io::Result<()> {
                     .route(web::delete().to(screens::delete)),
             )
             .service(
-                web::resource("/api/screens/multiple/").route(web::post().to(screens::get_multiple)),
+                web::resource("/api/screens/multiple/")
+                    .route(web::post().to(screens::get_multiple)),
             )
             // MenuItems
             .service(
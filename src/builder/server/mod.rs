@@ -1 +1 @@
-pub mod actix;
\ No newline at end of file
+pub mod actix;
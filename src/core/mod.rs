@@ -1,15 +1,37 @@
 pub mod process;
+pub mod ussd_confirm;
+pub mod ussd_dial;
+pub mod ussd_dynamic_menu;
+pub mod ussd_error;
+pub mod ussd_input;
+pub mod ussd_language;
 pub mod ussd_menu;
+pub mod ussd_pagination;
 pub mod ussd_request;
 pub mod ussd_response;
 pub mod ussd_screens;
 pub mod ussd_service;
 pub mod ussd_session;
+pub mod ussd_session_sqlite;
+pub mod validation;
 
-pub use process::process_request;
+pub(crate) use process::{error_response, record_evicted_session};
+pub use process::{process_request, process_request_async};
+pub use ussd_confirm::{ConfirmChoice, USSDConfirm};
+pub use ussd_dial::{ShortcutMatch, USSDShortcut};
+pub use ussd_dynamic_menu::USSDDynamicMenu;
+pub use ussd_error::{ErrorClass, ErrorResponse, ErrorSettings, UssdError};
+pub use ussd_input::{InputType, InputValidation, InputValidationError};
+pub use ussd_language::LocalizedText;
 pub use ussd_menu::USSDMenu;
+pub use ussd_pagination::PaginationSettings;
 pub use ussd_request::USSDRequest;
 pub use ussd_response::USSDResponse;
 pub use ussd_screens::{ScreenType, USSDAction};
-pub use ussd_service::USSDService;
-pub use ussd_session::{InMemorySessionStore, SessionCache, USSDSession};
+pub use ussd_service::{ServiceErrorKind, ServiceKind, ServiceOutcome, USSDService};
+pub use ussd_session::{
+    AsyncSessionCache, InMemorySessionStore, SessionCache, SessionError, SessionExpiry,
+    SessionSettings, SyncSessionCache, USSDSession,
+};
+pub use ussd_session_sqlite::SqliteSessionStore;
+pub use validation::MenuValidationError;
@@ -1,102 +1,821 @@
-use crate::{debug, error, info, USSDMenu};
+use std::{sync::RwLock, time::Instant};
 
-use super::{ScreenType, SessionCache, USSDAction, USSDRequest, USSDResponse, USSDSession};
+use futures_util::FutureExt;
+
+use crate::{
+    analytics::SessionJourney,
+    debug, error, info,
+    metrics::{metrics, ScreenExit},
+    types::JourneyHook,
+    warning, USSDMenu, UssdApp,
+};
+
+use super::{
+    ussd_screens::{call_function, call_function_async, USSDScreen},
+    AsyncSessionCache, ScreenType, SessionError, SessionSettings, ShortcutMatch, SyncSessionCache,
+    USSDAction, USSDRequest, USSDResponse, USSDSession, UssdError,
+};
 
 /// Entry point for processing USSD requests.
 ///
+/// Dialling an extended dial string such as `*123*3*1*500#` starts a session for its service code,
+/// then replays the inputs that follow the code one by one. The replay stops at the first input
+/// that does not move the session on, so the user is only asked for what is missing. Dial strings
+/// that match one of the menu's `shortcuts` start at the shortcut's screen instead.
+///
+/// The request runs in a `ussd_request` tracing span with its session id, msisdn and service code.
+///
 /// # Arguments
 ///
 /// * `request` - The USSD request.
-/// * `functions_path` - The path to the functions used by the USSD application.
-/// * `session_cache` - The session cache implementation used by the USSD application.
+/// * `app` - The USSD application, providing the session cache and session settings.
 /// * `screens` - The USSD menu screens.
 ///
 /// # Returns
 ///
-/// The USSD response.
+/// The USSD response, or the `UssdError` that stopped the request. The session is left as it was
+/// stored by the last successful request.
+#[tracing::instrument(
+    name = "ussd_request",
+    skip_all,
+    fields(
+        session_id = %request.session_id,
+        msisdn = %request.msisdn,
+        service_code = %request.service_code
+    )
+)]
 pub fn process_request(
     request: &USSDRequest,
-    session_cache: &Box<dyn SessionCache>,
+    app: &UssdApp,
     screens: &USSDMenu,
-) -> USSDResponse {
-    // Get the initial screen
-    let (initial_screen, _) = screens.get_initial_screen();
+) -> Result<USSDResponse, UssdError> {
+    if app.async_session_cache.is_some() {
+        warning!("The async session cache is only used by run_async, using the session cache");
+    }
 
-    // Generate or retrieve the session
-    let mut session = USSDSession::get_or_create_session(request, &initial_screen, session_cache);
+    // The session cache and the functions are called without waiting, so the request completes
+    // the first time it is polled
+    let cache = SyncSessionCache(app.session_cache.as_ref());
+    process_dial(request, app, &cache, screens, CallMode::Blocking)
+        .now_or_never()
+        .expect("blocking requests complete without waiting")
+}
 
-    // Create a response object
-    let mut response: USSDResponse = USSDResponse {
-        msisdn: request.msisdn.clone(),
-        session_id: request.session_id.clone(),
-        end_session: session.end_session,
-        message: "Something went wrong, please try again later".to_string(),
+/// Entry point for processing USSD requests without blocking the worker.
+///
+/// This behaves like `process_request`, except that `Function` screens await their service.
+/// This allows async functions and http services to be called. Sessions are stored in the app's
+/// async session cache if it has one, and in its session cache otherwise.
+///
+/// # Arguments
+///
+/// * `request` - The USSD request.
+/// * `app` - The USSD application, providing the session cache and session settings.
+/// * `screens` - The USSD menu screens.
+///
+/// # Returns
+///
+/// The USSD response, or the `UssdError` that stopped the request.
+#[tracing::instrument(
+    name = "ussd_request",
+    skip_all,
+    fields(
+        session_id = %request.session_id,
+        msisdn = %request.msisdn,
+        service_code = %request.service_code
+    )
+)]
+pub async fn process_request_async(
+    request: &USSDRequest,
+    app: &UssdApp,
+    screens: &USSDMenu,
+) -> Result<USSDResponse, UssdError> {
+    let sync_cache = SyncSessionCache(app.session_cache.as_ref());
+    let cache: &dyn AsyncSessionCache = match &app.async_session_cache {
+        Some(cache) => cache.as_ref(),
+        None => &sync_cache,
     };
 
-    // Display screen history
-    session.display_screen_history();
+    process_dial(request, app, cache, screens, CallMode::Async).await
+}
+
+/// The most screens a single request runs through before a message must be displayed.
+const MAX_TRANSITIONS: usize = 100;
+
+/// How `Function` screens call their service.
+///
+/// * `Blocking`: The service is called on the worker, as by `UssdApp::run`.
+/// * `Async`: The service is awaited, as by `UssdApp::run_async`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CallMode {
+    Blocking,
+    Async,
+}
+
+/// Processes the request of a dial string and records it in the metrics.
+async fn process_dial(
+    request: &USSDRequest,
+    app: &UssdApp,
+    cache: &dyn AsyncSessionCache,
+    screens: &USSDMenu,
+    mode: CallMode,
+) -> Result<USSDResponse, UssdError> {
+    let started = Instant::now();
+
+    let request = with_session_service_code(request, cache).await;
+    let mut dial = expand_dial_string(&request, screens);
+    if dial.is_extended() && session_position(&dial.request, cache).await.is_some() {
+        dial.continue_session();
+    }
+
+    let response = replay_dial(&dial, app, cache, screens, mode).await;
+
+    record_request(&dial.request, screens, started);
+
+    response
+}
+
+/// Processes the request of a dial string, then replays the inputs dialled after its service code.
+async fn replay_dial(
+    dial: &Dial,
+    app: &UssdApp,
+    cache: &dyn AsyncSessionCache,
+    screens: &USSDMenu,
+    mode: CallMode,
+) -> Result<USSDResponse, UssdError> {
+    let mut response = process_screens(
+        &dial.request,
+        app,
+        cache,
+        screens,
+        dial.shortcut.as_ref(),
+        mode,
+    )
+    .await?;
+
+    for input in dial.inputs.iter() {
+        let position = session_position(&dial.request, cache).await;
+        if response.end_session || position.is_none() {
+            break;
+        }
+
+        debug!("Replaying dial string input: {}", input);
+        let request = USSDRequest {
+            input: input.clone(),
+            ..dial.request.clone()
+        };
+        response = process_screens(&request, app, cache, screens, None, mode).await?;
+
+        if session_position(&dial.request, cache).await == position {
+            break;
+        }
+    }
+
+    Ok(response)
+}
+
+/// Processes a single request, running screens until a message is displayed.
+async fn process_screens(
+    request: &USSDRequest,
+    app: &UssdApp,
+    cache: &dyn AsyncSessionCache,
+    screens: &USSDMenu,
+    shortcut: Option<&ShortcutMatch>,
+    mode: CallMode,
+) -> Result<USSDResponse, UssdError> {
+    // Dispatch on the service code
+    let initial_screen = match screens.initial_screen_for(&request.service_code) {
+        Some((initial_screen, _)) => initial_screen,
+        None => return Ok(unknown_service_code(request, screens)),
+    };
+
+    let (mut session, mut response) = start_request(request, &initial_screen, app, cache).await?;
+
+    let settings = &app.session_settings;
+    if begin_request(
+        &mut session,
+        &mut response,
+        request,
+        app,
+        &initial_screen,
+        shortcut,
+    ) {
+        cache
+            .store_session(&session, settings.storage_ttl())
+            .await?;
+        return Ok(response);
+    }
 
     let mut current_screen = session.current_screen.clone();
+    let mut transitions = 0;
 
     loop {
-        if let Some(screen) = screens.menus.get(&current_screen) {
-            info!(
-                "\nRunning for {}\nScreen Type: {:?}\nRequest : {:?}\n",
-                current_screen, screen.screen_type, request
-            );
-
-            // Execute the screen action for Function, Router, and Initial screen types
-            // They contain no display message
-            // They are used to execute a function, route to another screen, or set the initial screen
-            // The next screen is set based on the action
-            match screen.screen_type {
-                ScreenType::Function | ScreenType::Router | ScreenType::Initial => {
-                    screen.execute(&mut session, request, &screens.services);
+        // Stop menus that route in a circle without displaying a message from holding the worker
+        transitions += 1;
+        if transitions > MAX_TRANSITIONS {
+            return Err(UssdError::TooManyTransitions {
+                screen: current_screen,
+                limit: MAX_TRANSITIONS,
+            });
+        }
+
+        let screen = find_screen(screens, &current_screen)?;
+
+        if screen.screen_type == ScreenType::Function {
+            debug!(screen = %current_screen, screen_type = ?screen.screen_type, "Running screen");
+            call_service(screen, &mut session, screens, app, mode).await?;
+        } else if run_screen(
+            screen,
+            &current_screen,
+            &mut session,
+            &mut response,
+            request,
+            app,
+            screens,
+        )? {
+            session
+                .update_session_async(cache, settings.storage_ttl())
+                .await?;
+            break;
+        }
+
+        current_screen = next_screen(&mut session, &current_screen);
+    }
+
+    if end_session(&session, &response, app) {
+        if let Err(e) = cache.delete_session(&session.session_id).await {
+            error!("Failed to delete session {}: {}", session.session_id, e);
+        }
+    }
+
+    Ok(response)
+}
+
+/// Calls the service of a `Function` screen and moves the session to the screen for its outcome.
+///
+/// This is the only step that differs between `process_request` and `process_request_async`.
+async fn call_service(
+    screen: &USSDScreen,
+    session: &mut USSDSession,
+    screens: &USSDMenu,
+    app: &UssdApp,
+    mode: CallMode,
+) -> Result<(), UssdError> {
+    session.current_screen = match &screen.function {
+        Some(function_name) => {
+            let outcome = match mode {
+                CallMode::Blocking => {
+                    call_function(session, &screens.services, function_name, &app.functions)?
+                }
+                CallMode::Async => {
+                    call_function_async(session, &screens.services, function_name, &app.functions)
+                        .await?
                 }
+            };
+            screen.next_screen_after(&outcome)
+        }
+        None => screen.default_next_screen.clone(),
+    };
 
-                // Display the screen message and execute the screen action for Menu and Input screen types
-                // They contain a display message
-                // The next screen is set based on the action
-                // It checks if the current screen has been displayed
-                // If not, it displays the message and sets the current screen as displayed and also routes back to the current screen
-                _ => {
-                    let current_screen_displayed = session
-                        .displayed
-                        .entry(current_screen.clone())
-                        .or_insert(false);
+    Ok(())
+}
 
-                    if !*current_screen_displayed {
-                        debug!("Displaying message for screen: {}", current_screen);
+/// A request with the inputs of its dial string split off.
+struct Dial {
+    request: USSDRequest,
+    inputs: Vec<String>,
+    shortcut: Option<ShortcutMatch>,
+}
 
-                        response.message = screen.display(&mut session).unwrap_or_else(|| {
-                            error!("Failed to display message for screen: {} please ensure the screen has a message", current_screen);
-                            "Something went wrong, please stop".to_string()
-                        });
+impl Dial {
+    /// Returns whether the dial string has inputs to replay or matches a shortcut.
+    fn is_extended(&self) -> bool {
+        !self.inputs.is_empty() || self.shortcut.is_some()
+    }
 
-                        response.end_session = session.end_session;
+    /// Drops the inputs and shortcut of the dial string.
+    ///
+    /// The inputs and shortcuts only apply when the dial string starts a session, since some
+    /// gateways send the same dial string with every request of the session.
+    fn continue_session(&mut self) {
+        self.inputs.clear();
+        self.shortcut = None;
+    }
+}
 
-                        session.displayed.insert(current_screen.clone(), true);
-                        session.current_screen = current_screen.clone();
-                        session.update_session(session_cache);
+/// Records a processed request in the metrics, by the service code without the dialled inputs.
+fn record_request(request: &USSDRequest, screens: &USSDMenu, started: Instant) {
+    let service_code = match screens.initial_screen_for(&request.service_code) {
+        Some(_) => request.service_code.as_str(),
+        None => "unknown",
+    };
 
-                        break;
-                    } else {
-                        debug!("Executing action for screen: {}", current_screen);
+    metrics().record_request(service_code, started.elapsed());
+}
 
-                        screen.execute(&mut session, request, &screens.services);
+/// Splits the service code of the request into the service code and the inputs dialled after it.
+fn expand_dial_string(request: &USSDRequest, screens: &USSDMenu) -> Dial {
+    let (service_code, inputs) = screens.split_dial_string(&request.service_code);
+    let shortcut = screens.match_shortcut(&request.service_code);
 
-                        // remove from displayed
-                        session.displayed.remove(&current_screen);
-                    }
-                }
-            }
-            // request.session_data = session.data.clone();
-            current_screen = session.current_screen.clone();
-            continue;
+    Dial {
+        request: USSDRequest {
+            service_code,
+            ..request.clone()
+        },
+        inputs: if shortcut.is_none() {
+            inputs
         } else {
-            break;
+            Vec::new()
+        },
+        shortcut,
+    }
+}
+
+/// Fills in the service code of a request that has none with the code its session started with.
+///
+/// Some gateways only send the dialled code with the first request of a session, and the code is
+/// needed to restart an expired session at the right `Initial` screen.
+async fn with_session_service_code(
+    request: &USSDRequest,
+    cache: &dyn AsyncSessionCache,
+) -> USSDRequest {
+    let mut request = request.clone();
+
+    if request.service_code.trim().is_empty() {
+        if let Ok(Some(session)) = cache.retrieve_session(&request.session_id).await {
+            request.service_code = session.service_code;
         }
     }
 
-    return response;
+    request
+}
+
+/// Returns the screen and menu page the session of the request is on, if it is still open.
+async fn session_position(
+    request: &USSDRequest,
+    cache: &dyn AsyncSessionCache,
+) -> Option<(String, usize)> {
+    match cache.retrieve_session(&request.session_id).await {
+        Ok(Some(session)) => Some((session.current_screen, session.menu_page)),
+        _ => None,
+    }
+}
+
+/// Retrieves or creates the session for the request and builds the default response.
+///
+/// New and restarted sessions start at `initial_screen`, the initial screen of the service code.
+async fn start_request(
+    request: &USSDRequest,
+    initial_screen: &str,
+    app: &UssdApp,
+    cache: &dyn AsyncSessionCache,
+) -> Result<(USSDSession, USSDResponse), SessionError> {
+    // Generate or retrieve the session
+    let session = USSDSession::get_or_create_session_async(
+        request,
+        initial_screen,
+        cache,
+        &app.session_settings,
+    )
+    .await?;
+
+    let response = default_response(request, &session);
+
+    Ok((session, response))
+}
+
+/// Runs the steps between retrieving the session and running its screens.
+///
+/// The journey of an expired session is recorded, the user is asked whether to continue an
+/// expired session, and new sessions apply the shortcut that was dialled and call the session
+/// start hook.
+///
+/// # Returns
+///
+/// `true` if the question to continue was displayed and the request is complete. The session must
+/// then be stored.
+fn begin_request(
+    session: &mut USSDSession,
+    response: &mut USSDResponse,
+    request: &USSDRequest,
+    app: &UssdApp,
+    initial_screen: &str,
+    shortcut: Option<&ShortcutMatch>,
+) -> bool {
+    if let Some(journey) = session.expired_journey.take() {
+        record_journey(&journey, &app.journey_hook);
+    }
+
+    if resume_expired_session(
+        session,
+        response,
+        request,
+        &app.session_settings,
+        initial_screen,
+    ) {
+        return true;
+    }
+
+    if session.started {
+        metrics().record_session_start();
+
+        if let Some(shortcut) = shortcut {
+            shortcut.apply(session);
+        }
+
+        if let Some(hook) = &app.session_start_hook {
+            hook(session);
+        }
+    }
+
+    false
+}
+
+/// Builds the response to the request, whose message is set by the screen that displays one.
+fn default_response(request: &USSDRequest, session: &USSDSession) -> USSDResponse {
+    debug!(visited_screens = ?session.visited_screens, "Screen history");
+
+    USSDResponse {
+        msisdn: request.msisdn.clone(),
+        session_id: request.session_id.clone(),
+        end_session: session.end_session,
+        message: String::new(),
+    }
+}
+
+/// Builds the response for a request that failed with `error`.
+///
+/// The response ends the session with the menu's error message for the class of the error, or
+/// the text of the error screen configured for it.
+pub(crate) fn error_response(
+    request: &USSDRequest,
+    screens: &USSDMenu,
+    error: &UssdError,
+) -> USSDResponse {
+    error!(class = ?error.class(), "Failed to process request: {}", error);
+
+    let settings = screens.errors.for_class(error.class());
+    let text = settings
+        .screen
+        .as_ref()
+        .and_then(|screen| screens.menus.get(screen))
+        .map_or(&settings.message, |screen| &screen.text);
+
+    USSDResponse {
+        msisdn: request.msisdn.clone(),
+        session_id: request.session_id.clone(),
+        end_session: true,
+        message: text
+            .get(&request.language, &screens.default_language)
+            .to_string(),
+    }
+}
+
+/// Returns the screen the session moved to.
+fn find_screen<'a>(screens: &'a USSDMenu, screen: &str) -> Result<&'a USSDScreen, UssdError> {
+    screens
+        .menus
+        .get(screen)
+        .ok_or_else(|| UssdError::UnknownScreen(screen.to_string()))
+}
+
+/// Builds the response for a service code that no `Initial` screen serves.
+///
+/// No session is created, and the response ends the session with the menu's
+/// `unknown_service_code` message.
+fn unknown_service_code(request: &USSDRequest, screens: &USSDMenu) -> USSDResponse {
+    warning!(
+        "No initial screen for service code: {}",
+        request.service_code
+    );
+
+    USSDResponse {
+        msisdn: request.msisdn.clone(),
+        session_id: request.session_id.clone(),
+        end_session: true,
+        message: screens
+            .unknown_service_code
+            .get(&request.language, &screens.default_language)
+            .to_string(),
+    }
+}
+
+/// Closes the session if the response ends it.
+///
+/// The session end hook is called with the final session, which must then be removed from the
+/// session cache so the next request with the same session id starts a new session.
+///
+/// # Returns
+///
+/// `true` if the session ended.
+fn end_session(session: &USSDSession, response: &USSDResponse, app: &UssdApp) -> bool {
+    if !response.end_session {
+        return false;
+    }
+
+    debug!("Ending session: {}", session.session_id);
+    metrics().record_session_exit(&session.current_screen, ScreenExit::Ended);
+
+    if let Some(hook) = &app.session_end_hook {
+        hook(session);
+    }
+
+    record_journey(
+        &SessionJourney::from_session(session, true),
+        &app.journey_hook,
+    );
+
+    true
+}
+
+/// Passes the journey of a session that ended or expired to the app's journey hook.
+fn record_journey(journey: &SessionJourney, journey_hook: &RwLock<Option<JourneyHook>>) {
+    debug!(
+        last_screen = %journey.last_screen,
+        completed = journey.completed,
+        "Session journey"
+    );
+
+    if let Some(hook) = journey_hook.read().unwrap().as_ref() {
+        hook(journey);
+    }
+}
+
+/// Records a session that its session cache removed after it expired, without another request
+/// for it, as abandoned on its current screen.
+pub(crate) fn record_evicted_session(
+    session: &USSDSession,
+    journey_hook: &RwLock<Option<JourneyHook>>,
+) {
+    info!("Session {} expired", session.session_id);
+    metrics().record_session_exit(&session.current_screen, ScreenExit::Abandoned);
+    record_journey(&SessionJourney::from_session(session, false), journey_hook);
+}
+
+/// Asks the user whether to continue an expired session, and handles their answer.
+///
+/// Choosing to continue displays the screen the user left off at again, any other answer restarts
+/// the session at the initial screen. The answer is not passed on to the screen.
+///
+/// # Returns
+///
+/// `true` if the question was displayed and the request is complete. The session must then be
+/// stored.
+fn resume_expired_session(
+    session: &mut USSDSession,
+    response: &mut USSDResponse,
+    request: &USSDRequest,
+    settings: &SessionSettings,
+    initial_screen: &str,
+) -> bool {
+    if session.timed_out {
+        debug!("Asking to resume session: {}", session.session_id);
+
+        response.message = settings.resume_message();
+        response.end_session = false;
+
+        session.awaiting_resume = true;
+
+        return true;
+    }
+
+    if session.awaiting_resume {
+        session.awaiting_resume = false;
+
+        if request.input.trim() == settings.resume_option {
+            // Display the screen the user left off at again
+            let current_screen = session.current_screen.clone();
+            session.displayed.remove(&current_screen);
+        } else {
+            session.restart(initial_screen);
+        }
+    }
+
+    false
+}
+
+/// Runs a single screen, either executing its action or displaying its message.
+///
+/// # Returns
+///
+/// `true` if a message was displayed and the request is complete. The session must then be
+/// updated in the session cache.
+///
+/// # Errors
+///
+/// The `UssdError` raised by the screen's action or message.
+fn run_screen(
+    screen: &USSDScreen,
+    current_screen: &str,
+    session: &mut USSDSession,
+    response: &mut USSDResponse,
+    request: &USSDRequest,
+    app: &UssdApp,
+    screens: &USSDMenu,
+) -> Result<bool, UssdError> {
+    debug!(screen = %current_screen, screen_type = ?screen.screen_type, "Running screen");
+
+    // Execute the screen action for Function, Router, and Initial screen types
+    // They contain no display message
+    // They are used to execute a function, route to another screen, or set the initial screen
+    // The next screen is set based on the action
+    match screen.screen_type {
+        ScreenType::Function | ScreenType::Router | ScreenType::Initial => {
+            screen.execute(session, request, screens, &app.functions)?;
+        }
+
+        // Display the screen message and execute the screen action for Menu, Input, Confirm and
+        // Info screen types
+        // They contain a display message
+        // The next screen is set based on the action
+        // It checks if the current screen has been displayed
+        // If not, it displays the message and sets the current screen as displayed and also routes back to the current screen
+        _ => {
+            let current_screen_displayed = session
+                .displayed
+                .entry(current_screen.to_string())
+                .or_insert(false);
+
+            if !*current_screen_displayed {
+                debug!("Displaying message for screen: {}", current_screen);
+                metrics().record_screen_view(current_screen);
+                session.displayed_screens.push(current_screen.to_string());
+
+                response.message = screen
+                    .display(session, screens)?
+                    .ok_or_else(|| UssdError::MissingMessage(current_screen.to_string()))?;
+
+                response.end_session = session.end_session;
+
+                session.displayed.insert(current_screen.to_string(), true);
+                session.current_screen = current_screen.to_string();
+
+                return Ok(true);
+            } else {
+                debug!("Executing action for screen: {}", current_screen);
+
+                screen.execute(session, request, screens, &app.functions)?;
+
+                // remove from displayed
+                session.displayed.remove(current_screen);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+/// Returns the screen to run next, resetting the menu page when moving to a different screen.
+fn next_screen(session: &mut USSDSession, current_screen: &str) -> String {
+    // Start from the first page whenever a different screen is shown
+    if session.current_screen != current_screen {
+        session.menu_page = 0;
+    }
+
+    session.current_screen.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{example_menu, ConversationTest};
+
+    fn request() -> USSDRequest {
+        USSDRequest {
+            session_id: "process-session".to_string(),
+            msisdn: "1234567890".to_string(),
+            input: String::new(),
+            service_code: "*123#".to_string(),
+            language: "en".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_routing_cycle_stops() {
+        let menu: USSDMenu = serde_json::from_value(json!({
+            "menus": {
+                "Start": {
+                    "text": "",
+                    "screen_type": "Initial",
+                    "default_next_screen": "Check"
+                },
+                "Check": {
+                    "text": "",
+                    "screen_type": "Router",
+                    "default_next_screen": "Check"
+                }
+            },
+            "services": {}
+        }))
+        .unwrap();
+
+        let app = UssdApp::new(true, None);
+        assert_eq!(
+            app.try_run(request(), menu.clone()),
+            Err(UssdError::TooManyTransitions {
+                screen: "Check".to_string(),
+                limit: MAX_TRANSITIONS,
+            })
+        );
+
+        let response = app.run(request(), menu);
+        assert!(response.end_session);
+        assert_eq!(
+            response.message,
+            "Something went wrong, please try again later"
+        );
+    }
+
+    #[test]
+    fn test_continuation_without_service_code() {
+        let menu: USSDMenu = serde_json::from_value(json!({
+            "menus": {
+                "BankStart": {
+                    "text": "",
+                    "screen_type": "Initial",
+                    "default_next_screen": "Bank",
+                    "service_code": "*123#"
+                },
+                "Bank": {
+                    "text": "Bank",
+                    "screen_type": "Input",
+                    "default_next_screen": "Bank",
+                    "service_code": "*123#"
+                },
+                "AirtimeStart": {
+                    "text": "",
+                    "screen_type": "Initial",
+                    "default_next_screen": "Airtime",
+                    "service_code": "*456#"
+                },
+                "Airtime": {
+                    "text": "Airtime",
+                    "screen_type": "Input",
+                    "default_next_screen": "Bought",
+                    "service_code": "*456#"
+                },
+                "Bought": {
+                    "text": "Airtime bought",
+                    "screen_type": "Quit",
+                    "default_next_screen": "",
+                    "service_code": "*456#"
+                }
+            },
+            "services": {}
+        }))
+        .unwrap();
+        let continuation = |input: &str| USSDRequest {
+            input: input.to_string(),
+            service_code: String::new(),
+            ..request()
+        };
+
+        let app = UssdApp::new(true, None);
+        let first = USSDRequest {
+            service_code: "*456#".to_string(),
+            ..request()
+        };
+        assert_eq!(app.run(first.clone(), menu.clone()).message, "Airtime");
+        assert_eq!(
+            app.run(continuation("10"), menu.clone()).message,
+            "Airtime bought"
+        );
+
+        // An expired session restarts at the initial screen of the code it started with
+        app.run(first, menu.clone());
+        let mut session = app
+            .session_cache
+            .retrieve_session("process-session")
+            .unwrap()
+            .unwrap();
+        session.last_interaction_time -= app.session_settings.timeout * 2;
+        app.session_cache.store_session(&session).unwrap();
+        assert_eq!(app.run(continuation("10"), menu).message, "Airtime");
+    }
+
+    #[test]
+    fn test_dispatch_on_service_code() {
+        let mut menu = example_menu();
+        let mut airtime_start = menu.menus["InitialScreen"].clone();
+        airtime_start.service_code = Some("*456#".to_string());
+        airtime_start.default_next_screen = "AirtimeScreen".to_string();
+        menu.menus.insert("AirtimeStart".to_string(), airtime_start);
+
+        let mut test = ConversationTest::new(menu.clone()).service_code("*456#");
+        test.dial().expect_screen("AirtimeScreen");
+
+        let mut test = ConversationTest::new(menu.clone()).service_code("*123#");
+        test.dial().expect_screen("MainScreen");
+
+        // Without a shared initial screen, other codes are rejected
+        menu.menus.get_mut("InitialScreen").unwrap().service_code = Some("*123#".to_string());
+        let mut test = ConversationTest::new(menu).service_code("*789#");
+        test.dial()
+            .expect_message("Unknown service code")
+            .expect_end_session(true);
+        assert!(test.session().is_none());
+    }
 }
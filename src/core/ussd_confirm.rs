@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+
+use super::LocalizedText;
+
+/// The choices of a `Confirm` screen, displayed below its text as options `1` and `2`.
+///
+/// # Fields
+///
+/// * `accept_label`: The label of the accept option, "Yes" by default.
+/// * `accept_screen`: The screen to go to when the user accepts, `default_next_screen` when unset.
+/// * `reject_label`: The label of the reject option, "No" by default.
+/// * `reject_screen`: The screen to go to when the user rejects, the previous screen when unset.
+///
+/// # Example
+///
+/// ```json
+/// "confirm": {
+///     "accept_label": { "en": "Send", "sw": "Tuma" },
+///     "reject_label": { "en": "Cancel", "sw": "Ghairi" },
+///     "reject_screen": "MainScreen"
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct USSDConfirm {
+    #[serde(default = "default_accept_label")]
+    pub accept_label: LocalizedText,
+    #[serde(default)]
+    pub accept_screen: Option<String>,
+    #[serde(default = "default_reject_label")]
+    pub reject_label: LocalizedText,
+    #[serde(default)]
+    pub reject_screen: Option<String>,
+}
+
+/// A choice made on a `Confirm` screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmChoice {
+    Accept,
+    Reject,
+}
+
+fn default_accept_label() -> LocalizedText {
+    LocalizedText::from("Yes")
+}
+
+fn default_reject_label() -> LocalizedText {
+    LocalizedText::from("No")
+}
+
+impl Default for USSDConfirm {
+    fn default() -> Self {
+        USSDConfirm {
+            accept_label: default_accept_label(),
+            accept_screen: None,
+            reject_label: default_reject_label(),
+            reject_screen: None,
+        }
+    }
+}
+
+impl USSDConfirm {
+    /// The option that accepts.
+    pub const ACCEPT_OPTION: &'static str = "1";
+    /// The option that rejects.
+    pub const REJECT_OPTION: &'static str = "2";
+
+    /// Returns the option lines displayed below the screen text.
+    pub fn lines(&self, language: &str, default_language: &str) -> Vec<String> {
+        vec![
+            format!(
+                "{}. {}",
+                Self::ACCEPT_OPTION,
+                self.accept_label.get(language, default_language)
+            ),
+            format!(
+                "{}. {}",
+                Self::REJECT_OPTION,
+                self.reject_label.get(language, default_language)
+            ),
+        ]
+    }
+
+    /// Returns the choice made by the input, or nothing if the input is not an option.
+    pub fn choice(&self, input: &str) -> Option<ConfirmChoice> {
+        match input {
+            Self::ACCEPT_OPTION => Some(ConfirmChoice::Accept),
+            Self::REJECT_OPTION => Some(ConfirmChoice::Reject),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        core::USSDMenu,
+        testing::{example_menu, ConversationTest},
+    };
+
+    #[test]
+    fn test_confirm_defaults() {
+        let confirm: USSDConfirm =
+            serde_json::from_str(r#"{"reject_label": {"en": "Cancel", "sw": "Ghairi"}}"#).unwrap();
+
+        assert_eq!(confirm.lines("sw", "en"), vec!["1. Yes", "2. Ghairi"]);
+        assert_eq!(confirm.accept_screen, None);
+        assert_eq!(confirm.choice("1"), Some(ConfirmChoice::Accept));
+        assert_eq!(confirm.choice("2"), Some(ConfirmChoice::Reject));
+        assert_eq!(confirm.choice("3"), None);
+    }
+
+    #[test]
+    fn test_confirm_and_info_screens() {
+        let mut menu = example_menu();
+        menu.menus
+            .get_mut("OwnNumberAmountScreen")
+            .unwrap()
+            .default_next_screen = "ConfirmAirtimeScreen".to_string();
+        menu.menus.insert(
+            "ConfirmAirtimeScreen".to_string(),
+            serde_json::from_value(json!({
+                "text": "Buy {{amount}} airtime?",
+                "screen_type": "Confirm",
+                "default_next_screen": "OwnNumberFunctionScreen",
+                "confirm": {"accept_screen": "ReceivedScreen", "reject_label": "Cancel"}
+            }))
+            .unwrap(),
+        );
+        menu.menus.insert(
+            "ReceivedScreen".to_string(),
+            serde_json::from_value(json!({
+                "text": "Your request has been received, press any key",
+                "screen_type": "Info",
+                "default_next_screen": "OwnNumberFunctionScreen"
+            }))
+            .unwrap(),
+        );
+
+        let mut test = ConversationTest::new(menu).mock_data(
+            "buy_airtime",
+            json!({"status": "success", "message": "Airtime sent"}),
+        );
+        test.dial().input("3").input("1").input("50");
+        test.expect_message("Buy 50 airtime?\n1. Yes\n2. Cancel");
+
+        // Other inputs are rejected, and rejecting goes back to the previous screen
+        test.input("3")
+            .expect_message("Invalid menu option\n\nBuy 50 airtime?\n1. Yes\n2. Cancel");
+        test.input("2").expect_screen("OwnNumberAmountScreen");
+
+        // Accepting moves to the accept screen, which moves on with any input
+        test.input("60").input("1");
+        test.expect_message("Your request has been received, press any key")
+            .expect_end_session(false);
+        test.input("0");
+        test.expect_message("Transaction Successful Airtime sent")
+            .expect_data("amount", json!("60"));
+    }
+
+    fn confirm_menu(confirm: serde_json::Value) -> USSDMenu {
+        serde_json::from_value(json!({
+            "menus": {
+                "Start": {
+                    "text": "",
+                    "screen_type": "Initial",
+                    "default_next_screen": "Amount"
+                },
+                "Amount": {
+                    "text": "Amount",
+                    "screen_type": "Input",
+                    "default_next_screen": "Confirm",
+                    "input_identifier": "amount"
+                },
+                "Confirm": {
+                    "text": "Send {{amount}}?",
+                    "screen_type": "Confirm",
+                    "default_next_screen": "Sent",
+                    "confirm": confirm
+                },
+                "Sent": {
+                    "text": "Sent {{amount}}",
+                    "screen_type": "Info",
+                    "default_next_screen": "Bye"
+                },
+                "Cancelled": {
+                    "text": "Cancelled",
+                    "screen_type": "Quit",
+                    "default_next_screen": ""
+                },
+                "Bye": {
+                    "text": "Bye",
+                    "screen_type": "Quit",
+                    "default_next_screen": ""
+                }
+            },
+            "services": {}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_reject_without_reject_screen_goes_back() {
+        let mut test = ConversationTest::new(confirm_menu(json!({})));
+
+        test.dial()
+            .input("50")
+            .expect_message("Send 50?\n1. Yes\n2. No");
+        test.input("2")
+            .expect_screen("Amount")
+            .expect_message("Amount");
+
+        // The previous screen takes its input again
+        test.input("60")
+            .expect_message("Send 60?\n1. Yes\n2. No")
+            .expect_data("amount", json!("60"));
+    }
+
+    #[test]
+    fn test_reject_screen() {
+        let mut test = ConversationTest::new(confirm_menu(json!({"reject_screen": "Cancelled"})));
+
+        test.dial().input("50").input("2");
+        test.expect_message("Cancelled").expect_end_session(true);
+    }
+
+    #[test]
+    fn test_info_screen_moves_on_with_any_input() {
+        for input in ["0", "00", "hello"] {
+            let mut test = ConversationTest::new(confirm_menu(json!({})));
+
+            test.dial().input("50").input("1");
+            test.expect_message("Sent 50").expect_end_session(false);
+
+            // Back and home do not apply on an Info screen
+            test.input(input)
+                .expect_message("Bye")
+                .expect_end_session(true);
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::USSDData;
+
+use super::{ScreenType, USSDMenu, USSDSession};
+
+/// A named shortcut that jumps from an extended dial string straight to a screen.
+///
+/// The `pattern` is a dial string in which segments written as `{name}` match any input, which is
+/// stored in the session data under `name`. Literal segments must match exactly. A session dialled
+/// with a matching dial string starts at `next_screen`, with the captured inputs and `data`
+/// pre-filled.
+///
+/// # Fields
+///
+/// * `name`: The name of the shortcut.
+/// * `pattern`: The dial string matched, such as `*123*3*{amount}#`.
+/// * `next_screen`: The screen the session starts at.
+/// * `data`: Fixed values stored in the session data.
+///
+/// # Example
+///
+/// ```json
+/// "shortcuts": [
+///     {
+///         "name": "OwnAirtime",
+///         "pattern": "*123*3*{amount}#",
+///         "next_screen": "OwnNumberFunctionScreen",
+///         "data": { "recipient": "self" }
+///     }
+/// ]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct USSDShortcut {
+    #[serde(default)]
+    pub name: String,
+    pub pattern: String,
+    pub next_screen: String,
+    #[serde(default)]
+    pub data: HashMap<String, String>,
+}
+
+/// A shortcut matched by a dial string, with the session data it pre-fills.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShortcutMatch {
+    pub next_screen: String,
+    pub data: HashMap<String, USSDData>,
+}
+
+impl ShortcutMatch {
+    /// Moves a new session to the shortcut's screen and pre-fills its data.
+    pub fn apply(&self, session: &mut USSDSession) {
+        session.current_screen = self.next_screen.clone();
+        for (key, value) in self.data.iter() {
+            session.data.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+impl USSDShortcut {
+    /// Matches the shortcut against a dial string.
+    ///
+    /// # Returns
+    ///
+    /// The screen to start at and the session data to pre-fill, if the dial string matches.
+    pub fn matches(&self, dial_string: &str) -> Option<ShortcutMatch> {
+        let pattern = dial_segments(&self.pattern)?;
+        let segments = dial_segments(dial_string)?;
+
+        if pattern.len() != segments.len() {
+            return None;
+        }
+
+        let mut data: HashMap<String, USSDData> = self
+            .data
+            .iter()
+            .map(|(key, value)| (key.clone(), USSDData::Str(value.clone())))
+            .collect();
+
+        for (expected, segment) in pattern.iter().zip(segments.iter()) {
+            match expected
+                .strip_prefix('{')
+                .and_then(|name| name.strip_suffix('}'))
+            {
+                Some(name) if !segment.is_empty() => {
+                    data.insert(name.to_string(), USSDData::Str(segment.to_string()));
+                }
+                Some(_) => return None,
+                None if expected == segment => {}
+                None => return None,
+            }
+        }
+
+        Some(ShortcutMatch {
+            next_screen: self.next_screen.clone(),
+            data,
+        })
+    }
+}
+
+impl USSDMenu {
+    /// Splits an extended dial string into the service code and the inputs that follow it.
+    ///
+    /// The service code is the longest `service_code` of an `Initial` screen that the dial string
+    /// starts with, or the first segment when no screen has a matching code. Strings that are not
+    /// dial strings, such as codes without the leading `*` or trailing `#`, are returned as they are.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ussdframework::prelude::*;
+    ///
+    /// let menu = USSDMenu::new();
+    ///
+    /// let (service_code, inputs) = menu.split_dial_string("*123*3*1*500#");
+    /// assert_eq!(service_code, "*123#");
+    /// assert_eq!(inputs, vec!["3", "1", "500"]);
+    /// ```
+    pub fn split_dial_string(&self, dial_string: &str) -> (String, Vec<String>) {
+        let segments = match dial_segments(dial_string) {
+            Some(segments) if !segments.is_empty() => segments,
+            _ => return (dial_string.trim().to_string(), Vec::new()),
+        };
+
+        let code_length = self
+            .menus
+            .values()
+            .filter(|screen| screen.screen_type == ScreenType::Initial)
+            .filter_map(|screen| screen.service_code.as_deref().and_then(dial_segments))
+            .filter(|code| !code.is_empty() && segments.starts_with(code))
+            .map(|code| code.len())
+            .max()
+            .unwrap_or(1);
+
+        let service_code = format!("*{}#", segments[..code_length].join("*"));
+        let inputs = segments[code_length..]
+            .iter()
+            .filter(|input| !input.is_empty())
+            .map(|input| input.to_string())
+            .collect();
+
+        (service_code, inputs)
+    }
+
+    /// Returns the first shortcut matching the dial string.
+    pub fn match_shortcut(&self, dial_string: &str) -> Option<ShortcutMatch> {
+        self.shortcuts
+            .iter()
+            .find_map(|shortcut| shortcut.matches(dial_string))
+    }
+}
+
+/// Returns the `*`-separated segments of a dial string such as `*123*3*1#`.
+fn dial_segments(dial_string: &str) -> Option<Vec<&str>> {
+    dial_string
+        .trim()
+        .strip_prefix('*')
+        .and_then(|dial_string| dial_string.strip_suffix('#'))
+        .map(|dial_string| dial_string.split('*').collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        core::ussd_screens::USSDScreen,
+        testing::{example_menu, ConversationTest},
+    };
+
+    #[test]
+    fn test_split_dial_string() {
+        let mut menu = USSDMenu::new();
+        assert_eq!(
+            menu.split_dial_string("*123*3*1*500#"),
+            (
+                "*123#".to_string(),
+                vec!["3".to_string(), "1".to_string(), "500".to_string()]
+            )
+        );
+        assert_eq!(
+            menu.split_dial_string("*123#"),
+            ("*123#".to_string(), vec![])
+        );
+        assert_eq!(menu.split_dial_string("123"), ("123".to_string(), vec![]));
+
+        // Service codes with several segments are matched against the menu
+        menu.menus.insert(
+            "Start".to_string(),
+            USSDScreen {
+                screen_type: ScreenType::Initial,
+                service_code: Some("*384*123#".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            menu.split_dial_string("*384*123*2#"),
+            ("*384*123#".to_string(), vec!["2".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_shortcut_matches() {
+        let shortcut = USSDShortcut {
+            name: "OwnAirtime".to_string(),
+            pattern: "*123*3*{amount}#".to_string(),
+            next_screen: "OwnNumberFunctionScreen".to_string(),
+            data: HashMap::from([("recipient".to_string(), "self".to_string())]),
+        };
+
+        let matched = shortcut.matches("*123*3*500#").unwrap();
+        assert_eq!(matched.next_screen, "OwnNumberFunctionScreen");
+        assert_eq!(matched.data["amount"], USSDData::Str("500".to_string()));
+        assert_eq!(matched.data["recipient"], USSDData::Str("self".to_string()));
+
+        assert!(shortcut.matches("*123*2*500#").is_none());
+        assert!(shortcut.matches("*123*3#").is_none());
+        assert!(shortcut.matches("*123*3*#").is_none());
+    }
+
+    #[test]
+    fn test_extended_dial_string() {
+        let airtime = json!({"status": "success", "message": "Airtime sent"});
+
+        let mut test = ConversationTest::new(example_menu())
+            .service_code("*123*3*1*50#")
+            .mock_data("buy_airtime", airtime);
+        test.dial()
+            .expect_message("Transaction Successful Airtime sent")
+            .expect_end_session(true)
+            .expect_data("amount", json!("50"));
+
+        // The replay stops where an input is missing
+        let mut test = ConversationTest::new(example_menu()).service_code("*123*3*1#");
+        test.dial()
+            .expect_message("Enter amount")
+            .expect_screen("OwnNumberAmountScreen");
+
+        // And at the first input the screen rejects
+        let mut test = ConversationTest::new(example_menu()).service_code("*123*9*1#");
+        test.dial().expect_screen("MainScreen");
+    }
+
+    #[test]
+    fn test_dial_shortcut() {
+        let mut menu = example_menu();
+        menu.shortcuts.push(USSDShortcut {
+            name: "OwnAirtime".to_string(),
+            pattern: "*123*50*{amount}#".to_string(),
+            next_screen: "OwnNumberFunctionScreen".to_string(),
+            data: HashMap::from([("recipient".to_string(), "self".to_string())]),
+        });
+
+        let mut test = ConversationTest::new(menu)
+            .service_code("*123*50*20#")
+            .mock_data(
+                "buy_airtime",
+                json!({"status": "success", "message": "Airtime sent"}),
+            );
+        test.dial()
+            .expect_screen("SuccessScreen")
+            .expect_data("amount", json!("20"))
+            .expect_data("recipient", json!("self"));
+    }
+}
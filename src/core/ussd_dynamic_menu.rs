@@ -0,0 +1,313 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error,
+    types::USSDData,
+    utils::{
+        template::{evaluate, item_variables},
+        Expression, ExpressionValue, Parsed, Template,
+    },
+};
+
+use super::{LocalizedText, USSDSession};
+
+/// Menu items built from a list in the session data, such as the accounts returned by a service.
+///
+/// Each element of the `items` list becomes an option, numbered from 1 and displayed with the
+/// `display` template. In the template, `this` is the element, `@index` and `@number` its position
+/// from 0 and from 1, and the fields of dictionary elements can be used by name. When the user
+/// picks an option, the `value` of the element is stored in the session data under `selection_key`
+/// and the session moves to `next_screen`.
+///
+/// # Fields
+///
+/// * `items`: The expression of the list, such as `accounts` or `customer.beneficiaries`.
+/// * `display`: The template each element is displayed with.
+/// * `value`: The expression stored when an element is picked, the whole element by default.
+/// * `selection_key`: The session data key the picked value is stored under.
+/// * `next_screen`: The screen to go to once an element is picked.
+///
+/// # Example
+///
+/// ```json
+/// "dynamic_menu": {
+///     "items": "accounts",
+///     "display": "{{name}} ({{balance | currency: 'GHS'}})",
+///     "value": "id",
+///     "selection_key": "account_id",
+///     "next_screen": "AmountScreen"
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct USSDDynamicMenu {
+    pub items: String,
+    pub display: LocalizedText,
+    #[serde(default)]
+    pub value: Option<String>,
+    pub selection_key: String,
+    pub next_screen: String,
+    /// The parsed display templates.
+    #[serde(skip)]
+    pub(crate) templates: Parsed<Template>,
+    /// The parsed items and value expressions.
+    #[serde(skip)]
+    pub(crate) expressions: Parsed<Expression>,
+}
+
+impl USSDDynamicMenu {
+    /// Returns the elements of the list, or nothing if the list is not set.
+    ///
+    /// A value that is not a list is treated as a list of one element. The elements are evaluated
+    /// once for a screen and passed to `lines`, `options` and `select`.
+    pub fn elements(&self, session: &USSDSession) -> Vec<ExpressionValue> {
+        match self
+            .expressions
+            .get_or_parse(&self.items, Expression::parse)
+        {
+            Ok(expression) => match expression.evaluate(session) {
+                ExpressionValue::List(items) => items,
+                ExpressionValue::Null => Vec::new(),
+                value => vec![value],
+            },
+            Err(e) => {
+                error!("Invalid dynamic menu items {}: {}", self.items, e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Returns the menu lines of the elements, such as `1. Savings (GHS 2,500.00)`.
+    pub fn lines(
+        &self,
+        session: &USSDSession,
+        elements: Vec<ExpressionValue>,
+        language: &str,
+        default_language: &str,
+    ) -> Vec<String> {
+        let text = self.display.get(language, default_language);
+        let template = match self.templates.get_or_parse(text, Template::parse) {
+            Ok(template) => template,
+            Err(e) => {
+                error!("Invalid dynamic menu display {}: {}", text, e);
+                return Vec::new();
+            }
+        };
+
+        elements
+            .into_iter()
+            .enumerate()
+            .map(|(index, item)| {
+                format!(
+                    "{}. {}",
+                    index + 1,
+                    template.render_with(session, item_variables(item, index))
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the options of the elements, from `1` to the number of elements.
+    pub fn options(&self, elements: &[ExpressionValue]) -> Vec<String> {
+        (1..=elements.len())
+            .map(|option| option.to_string())
+            .collect()
+    }
+
+    /// Stores the value of the element picked with `input` in the session data.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the input picks an element, `false` if it is not one of the options.
+    pub fn select(
+        &self,
+        session: &mut USSDSession,
+        mut elements: Vec<ExpressionValue>,
+        input: &str,
+    ) -> bool {
+        let index = match input.parse::<usize>() {
+            Ok(option) if option > 0 && option <= elements.len() => option - 1,
+            _ => return false,
+        };
+        let element = elements.swap_remove(index);
+
+        let value = match &self.value {
+            Some(value) => match self.expressions.get_or_parse(value, Expression::parse) {
+                Ok(expression) => evaluate(&expression, session, &[item_variables(element, index)]),
+                Err(e) => {
+                    error!("Invalid dynamic menu value {}: {}", value, e);
+                    return false;
+                }
+            },
+            None => element,
+        };
+
+        session
+            .data
+            .insert(self.selection_key.clone(), USSDData::from(value));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{core::USSDMenu, testing::ConversationTest};
+
+    fn session() -> USSDSession {
+        let mut session = USSDSession::new(
+            "dynamic-menu-session".to_string(),
+            "AccountScreen".to_string(),
+            "en".to_string(),
+            "1234567890".to_string(),
+        );
+        session.data.insert(
+            "accounts".to_string(),
+            USSDData::List(vec![
+                USSDData::new_dict(HashMap::from([
+                    ("id".to_string(), USSDData::Int(101)),
+                    ("name".to_string(), USSDData::Str("Savings".to_string())),
+                    ("balance".to_string(), USSDData::Int(2500)),
+                ])),
+                USSDData::new_dict(HashMap::from([
+                    ("id".to_string(), USSDData::Int(102)),
+                    ("name".to_string(), USSDData::Str("Current".to_string())),
+                    ("balance".to_string(), USSDData::Float(10.5)),
+                ])),
+            ]),
+        );
+        session
+    }
+
+    fn accounts_menu() -> USSDDynamicMenu {
+        USSDDynamicMenu {
+            items: "accounts".to_string(),
+            display: "{{name}} ({{balance | currency: 'GHS'}})".into(),
+            value: Some("id".to_string()),
+            selection_key: "account_id".to_string(),
+            next_screen: "AmountScreen".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dynamic_menu_lines() {
+        let session = session();
+        let menu = accounts_menu();
+        let elements = menu.elements(&session);
+
+        assert_eq!(menu.options(&elements), vec!["1", "2"]);
+        assert_eq!(
+            menu.lines(&session, elements, "en", "en"),
+            vec!["1. Savings (GHS 2,500.00)", "2. Current (GHS 10.50)"]
+        );
+
+        let empty = USSDDynamicMenu {
+            items: "beneficiaries".to_string(),
+            ..accounts_menu()
+        };
+        let elements = empty.elements(&session);
+        assert!(empty.lines(&session, elements, "en", "en").is_empty());
+    }
+
+    #[test]
+    fn test_dynamic_menu_select() {
+        let mut session = session();
+        let mut menu = accounts_menu();
+        let elements = menu.elements(&session);
+
+        assert!(menu.select(&mut session, elements.clone(), "2"));
+        assert_eq!(session.data["account_id"], USSDData::Int(102));
+
+        assert!(!menu.select(&mut session, elements.clone(), "3"));
+        assert!(!menu.select(&mut session, elements.clone(), "0"));
+        assert!(!menu.select(&mut session, elements.clone(), "abc"));
+
+        // Without a value the whole element is stored
+        menu.value = None;
+        assert!(menu.select(&mut session, elements, "1"));
+        assert_eq!(
+            session.data["account_id"].as_hash_str_any().unwrap()["name"],
+            USSDData::Str("Savings".to_string())
+        );
+    }
+
+    #[test]
+    fn test_dynamic_menu_conversation() {
+        let menu: USSDMenu = serde_json::from_value(json!({
+            "menus": {
+                "Start": {
+                    "text": "",
+                    "screen_type": "Initial",
+                    "default_next_screen": "FetchAccounts"
+                },
+                "FetchAccounts": {
+                    "text": "",
+                    "screen_type": "Function",
+                    "function": "get_accounts",
+                    "default_next_screen": "Accounts"
+                },
+                "Accounts": {
+                    "text": "Pick an account",
+                    "screen_type": "Menu",
+                    "default_next_screen": "Start",
+                    "dynamic_menu": {
+                        "items": "accounts.list",
+                        "display": "{{name}} {{balance | currency}}",
+                        "value": "id",
+                        "selection_key": "account_id",
+                        "next_screen": "Amount"
+                    }
+                },
+                "Amount": {
+                    "text": "Amount to send from {{account_id}}",
+                    "screen_type": "Input",
+                    "default_next_screen": "Start"
+                }
+            },
+            "services": {
+                "get_accounts": {
+                    "function_name": "get_accounts",
+                    "function_url": null,
+                    "data_key": "accounts",
+                    "service_code": null
+                }
+            },
+            "pagination": { "max_message_length": 80 }
+        }))
+        .unwrap();
+
+        let accounts: Vec<Value> = (1..=5)
+            .map(|n| json!({"id": 100 + n, "name": format!("Account {}", n), "balance": n * 100}))
+            .collect();
+        let mut test =
+            ConversationTest::new(menu).mock_data("get_accounts", json!({ "list": accounts }));
+
+        test.dial()
+            .expect_screen("Accounts")
+            .expect_contains("1. Account 1 100.00")
+            .expect_contains("98. More");
+        assert!(!test.response().message.contains("5. Account 5"));
+
+        test.input("98").expect_contains("3. Account 3 300.00");
+        test.input("98").expect_contains("5. Account 5 500.00");
+        test.input("0");
+        test.input("0").expect_contains("1. Account 1");
+        test.input("9")
+            .expect_screen("Accounts")
+            .expect_contains("Invalid menu option");
+
+        // Options of other pages can be picked from any page
+        test.input("4")
+            .expect_screen("Amount")
+            .expect_message("Amount to send from 104")
+            .expect_data("account_id", json!(104));
+
+        // Going back shows the list again
+        test.input("0").expect_screen("Accounts");
+        test.input("2").expect_data("account_id", json!(102));
+    }
+}
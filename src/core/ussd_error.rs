@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt};
+
+use super::{LocalizedText, SessionError};
+
+/// An error that stopped a request from being processed.
+///
+/// `UssdApp::try_run` returns these errors, while `UssdApp::run` answers them with the menu's
+/// error message for the error's `ErrorClass`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UssdError {
+    /// The menu has no `Initial` screen.
+    NoInitialScreen,
+    /// The session moved to a screen that does not exist in the menu.
+    UnknownScreen(String),
+    /// A `Function` screen calls a service that is not defined in `services`.
+    UnknownService { screen: String, service: String },
+    /// A menu item option is not a positive number.
+    InvalidMenuOption { screen: String, option: String },
+    /// A screen that displays a message produced none.
+    MissingMessage(String),
+    /// The session moved through `limit` screens without displaying a message, as when a
+    /// `Router` routes back to itself.
+    TooManyTransitions { screen: String, limit: usize },
+    /// The session could not be retrieved from or stored in the session cache.
+    Session(SessionError),
+}
+
+/// The class of a `UssdError`, which decides what the user is shown.
+///
+/// * `Menu`: The menu is broken, for example a screen points at a screen that does not exist.
+/// * `Session`: The session cache failed, for example the session store is unreachable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum ErrorClass {
+    Menu,
+    Session,
+}
+
+impl UssdError {
+    /// Returns the class of the error.
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            UssdError::Session(_) => ErrorClass::Session,
+            _ => ErrorClass::Menu,
+        }
+    }
+}
+
+impl fmt::Display for UssdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UssdError::NoInitialScreen => write!(f, "no Initial screen found"),
+            UssdError::UnknownScreen(screen) => write!(f, "unknown screen '{}'", screen),
+            UssdError::UnknownService { screen, service } => write!(
+                f,
+                "Function screen '{}' calls unknown service '{}'",
+                screen, service
+            ),
+            UssdError::InvalidMenuOption { screen, option } => write!(
+                f,
+                "screen '{}' has non-numeric menu option '{}'",
+                screen, option
+            ),
+            UssdError::MissingMessage(screen) => {
+                write!(f, "screen '{}' has no message to display", screen)
+            }
+            UssdError::TooManyTransitions { screen, limit } => write!(
+                f,
+                "stopped at screen '{}' after {} screens without a message to display",
+                screen, limit
+            ),
+            UssdError::Session(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for UssdError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            UssdError::Session(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<SessionError> for UssdError {
+    fn from(error: SessionError) -> Self {
+        UssdError::Session(error)
+    }
+}
+
+/// What the user is shown when a request fails, by `ErrorClass`.
+///
+/// # Example
+///
+/// ```json
+/// "errors": {
+///     "menu": { "screen": "ServiceUnavailableScreen" },
+///     "session": { "message": { "en": "Please try again in a few minutes", "sw": "Tafadhali jaribu tena baadaye" } }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ErrorSettings {
+    #[serde(default)]
+    pub menu: ErrorResponse,
+    #[serde(default)]
+    pub session: ErrorResponse,
+}
+
+impl ErrorSettings {
+    /// Returns the response for errors of `class`.
+    pub fn for_class(&self, class: ErrorClass) -> &ErrorResponse {
+        match class {
+            ErrorClass::Menu => &self.menu,
+            ErrorClass::Session => &self.session,
+        }
+    }
+}
+
+/// The message displayed for a class of errors. The response always ends the session.
+///
+/// # Fields
+///
+/// * `message`: The message displayed.
+/// * `screen`: A screen whose text is displayed instead of `message`. Templates in its text are
+///   not evaluated, since the session may not be available.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ErrorResponse {
+    #[serde(default = "default_error_message")]
+    pub message: LocalizedText,
+    #[serde(default)]
+    pub screen: Option<String>,
+}
+
+fn default_error_message() -> LocalizedText {
+    LocalizedText::from("Something went wrong, please try again later")
+}
+
+impl Default for ErrorResponse {
+    fn default() -> Self {
+        ErrorResponse {
+            message: default_error_message(),
+            screen: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::{ussd_screens::USSDScreen, LocalizedText, ScreenType, USSDRequest},
+        testing::{example_menu, ConversationTest, UnreachableCache},
+    };
+
+    #[test]
+    fn test_error_classes() {
+        let error = UssdError::from(SessionError::Storage("connection refused".to_string()));
+        assert_eq!(error.class(), ErrorClass::Session);
+        assert_eq!(
+            error.to_string(),
+            "session storage error: connection refused"
+        );
+        assert!(error.source().is_some());
+
+        let error = UssdError::UnknownScreen("Missing".to_string());
+        assert_eq!(error.class(), ErrorClass::Menu);
+        assert_eq!(error.to_string(), "unknown screen 'Missing'");
+    }
+
+    #[test]
+    fn test_error_settings() {
+        let settings: ErrorSettings =
+            serde_json::from_str(r#"{"session": {"message": "Try again later"}}"#).unwrap();
+
+        assert_eq!(
+            settings.for_class(ErrorClass::Session).message,
+            LocalizedText::from("Try again later")
+        );
+        assert_eq!(
+            settings.for_class(ErrorClass::Menu),
+            &ErrorResponse::default()
+        );
+    }
+
+    #[test]
+    fn test_errors_end_the_session() {
+        let mut broken = example_menu();
+        broken.menus.remove("AirtimeScreen");
+        broken.errors.menu.message = LocalizedText::from("Service unavailable");
+
+        let mut test = ConversationTest::new(broken.clone());
+        test.dial().input("3");
+        test.expect_message("Service unavailable")
+            .expect_end_session(true);
+
+        // The session stays where the last successful request left it
+        let request = USSDRequest {
+            session_id: "test-session".to_string(),
+            msisdn: "1234567890".to_string(),
+            input: "3".to_string(),
+            service_code: String::new(),
+            language: String::new(),
+        };
+        assert_eq!(
+            test.app().try_run(request, &broken),
+            Err(UssdError::UnknownScreen("AirtimeScreen".to_string()))
+        );
+
+        // Session errors display the text of their error screen
+        let mut menu = example_menu();
+        menu.menus.insert(
+            "SessionErrorScreen".to_string(),
+            USSDScreen {
+                text: LocalizedText::from("Please try again in a few minutes"),
+                screen_type: ScreenType::Quit,
+                ..Default::default()
+            },
+        );
+        menu.errors.session.screen = Some("SessionErrorScreen".to_string());
+
+        let mut test = ConversationTest::new(menu);
+        test.app_mut().use_session_cache(UnreachableCache);
+        test.dial();
+        assert_eq!(test.response().message, "Please try again in a few minutes");
+        assert!(test.response().end_session);
+    }
+}
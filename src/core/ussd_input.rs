@@ -0,0 +1,314 @@
+use std::fmt::Display;
+use std::sync::Arc;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{error, utils::Parsed};
+
+lazy_static::lazy_static! {
+    // A positive number with at most two decimal places
+    static ref AMOUNT_PATTERN: Regex = Regex::new(r"^\d+(\.\d{1,2})?$").unwrap();
+    // The compiled `pattern` of each input validation
+    static ref PATTERNS: Parsed<Regex> = Parsed::default();
+}
+
+/// The kind of value an `Input` screen accepts, read from the screen's `input_type`.
+///
+/// * `Text`: Any input, the default when no `input_type` is set.
+/// * `Numeric`: Digits only.
+/// * `Amount`: A positive number with at most two decimal places.
+/// * `Msisdn`: A phone number of 7 to 15 digits, optionally starting with `+`.
+/// * `Pin`: A numeric PIN, 4 digits long unless `min_length` or `max_length` are set.
+/// * `Regex`: Input matching the `pattern` of the screen's `input_validation`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub enum InputType {
+    #[default]
+    Text,
+    Numeric,
+    Amount,
+    Msisdn,
+    Pin,
+    Regex,
+}
+
+impl Display for InputType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputType::Text => write!(f, "Text"),
+            InputType::Numeric => write!(f, "Numeric"),
+            InputType::Amount => write!(f, "Amount"),
+            InputType::Msisdn => write!(f, "Msisdn"),
+            InputType::Pin => write!(f, "Pin"),
+            InputType::Regex => write!(f, "Regex"),
+        }
+    }
+}
+
+impl InputType {
+    pub fn from_string(input_type: &str) -> InputType {
+        match input_type.to_lowercase().as_str() {
+            "text" => InputType::Text,
+            "numeric" | "number" => InputType::Numeric,
+            "amount" => InputType::Amount,
+            "msisdn" | "phone" => InputType::Msisdn,
+            "pin" => InputType::Pin,
+            "regex" => InputType::Regex,
+            _ => {
+                error!("Invalid input type: {}", input_type);
+                InputType::Text
+            }
+        }
+    }
+}
+
+/// Additional rules applied to the input of an `Input` screen.
+///
+/// # Fields
+///
+/// * `min_length`: The minimum number of characters.
+/// * `max_length`: The maximum number of characters.
+/// * `min_value`: The minimum value of `Numeric` and `Amount` inputs.
+/// * `max_value`: The maximum value of `Numeric` and `Amount` inputs.
+/// * `pattern`: The regular expression the input must match, required by `Regex` inputs. A
+///   pattern that does not compile rejects every input, and is reported by `USSDMenu::validate`.
+/// * `error_message`: Replaces the default error text displayed when the input is rejected.
+///
+/// # Example
+///
+/// ```json
+/// "input_type": "Amount",
+/// "input_validation": {
+///     "min_value": 50,
+///     "max_value": 10000,
+///     "error_message": "Enter an amount between 50 and 10000"
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct InputValidation {
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub min_value: Option<f64>,
+    pub max_value: Option<f64>,
+    pub pattern: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// The reason an input was rejected.
+///
+/// The `Display` implementation gives the default error text shown to the user.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputValidationError {
+    NotNumeric,
+    InvalidAmount,
+    InvalidMsisdn,
+    InvalidPin,
+    PatternMismatch,
+    TooShort(usize),
+    TooLong(usize),
+    BelowMinimum(f64),
+    AboveMaximum(f64),
+}
+
+impl Display for InputValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputValidationError::NotNumeric => write!(f, "Please enter numbers only"),
+            InputValidationError::InvalidAmount => write!(f, "Please enter a valid amount"),
+            InputValidationError::InvalidMsisdn => {
+                write!(f, "Please enter a valid phone number")
+            }
+            InputValidationError::InvalidPin => write!(f, "Please enter a valid PIN"),
+            InputValidationError::PatternMismatch => write!(f, "Invalid input, please try again"),
+            InputValidationError::TooShort(min) => {
+                write!(f, "Input must be at least {} characters", min)
+            }
+            InputValidationError::TooLong(max) => {
+                write!(f, "Input must be at most {} characters", max)
+            }
+            InputValidationError::BelowMinimum(min) => {
+                write!(f, "Value must be at least {}", min)
+            }
+            InputValidationError::AboveMaximum(max) => {
+                write!(f, "Value must be at most {}", max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InputValidationError {}
+
+impl InputValidation {
+    /// Checks the input against the input type and the validation rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `input_type` - The kind of value expected.
+    /// * `input` - The input entered by the user.
+    ///
+    /// # Returns
+    ///
+    /// The first rule the input breaks, if any.
+    pub fn validate(
+        &self,
+        input_type: &InputType,
+        input: &str,
+    ) -> Result<(), InputValidationError> {
+        let is_digits = !input.is_empty() && input.chars().all(|c| c.is_ascii_digit());
+
+        match input_type {
+            InputType::Text => {}
+            InputType::Numeric => {
+                if !is_digits {
+                    return Err(InputValidationError::NotNumeric);
+                }
+            }
+            InputType::Amount => {
+                if !AMOUNT_PATTERN.is_match(input) || input.parse::<f64>().unwrap_or(0.0) <= 0.0 {
+                    return Err(InputValidationError::InvalidAmount);
+                }
+            }
+            InputType::Msisdn => {
+                let digits = input.strip_prefix('+').unwrap_or(input);
+                let is_msisdn =
+                    digits.chars().all(|c| c.is_ascii_digit()) && (7..=15).contains(&digits.len());
+                if !is_msisdn {
+                    return Err(InputValidationError::InvalidMsisdn);
+                }
+            }
+            InputType::Pin => {
+                let is_pin = match (self.min_length, self.max_length) {
+                    (None, None) => input.len() == 4,
+                    _ => true,
+                };
+                if !is_digits || !is_pin {
+                    return Err(InputValidationError::InvalidPin);
+                }
+            }
+            InputType::Regex => {}
+        }
+
+        if let Some(pattern) = &self.pattern {
+            let matches = match compile_pattern(pattern) {
+                Ok(regex) => regex.is_match(input),
+                Err(e) => {
+                    error!("Invalid input validation pattern {}: {}", pattern, e);
+                    false
+                }
+            };
+            if !matches {
+                return Err(InputValidationError::PatternMismatch);
+            }
+        } else if *input_type == InputType::Regex {
+            error!("No pattern set for Regex input");
+        }
+
+        let length = input.chars().count();
+        if let Some(min_length) = self.min_length {
+            if length < min_length {
+                return Err(InputValidationError::TooShort(min_length));
+            }
+        }
+        if let Some(max_length) = self.max_length {
+            if length > max_length {
+                return Err(InputValidationError::TooLong(max_length));
+            }
+        }
+
+        if let Ok(value) = input.parse::<f64>() {
+            if let Some(min_value) = self.min_value {
+                if value < min_value {
+                    return Err(InputValidationError::BelowMinimum(min_value));
+                }
+            }
+            if let Some(max_value) = self.max_value {
+                if value > max_value {
+                    return Err(InputValidationError::AboveMaximum(max_value));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the text displayed to the user when the input is rejected.
+    pub fn error_text(&self, error: &InputValidationError) -> String {
+        self.error_message
+            .clone()
+            .unwrap_or_else(|| error.to_string())
+    }
+}
+
+/// Compiles the `pattern` of an input validation, once for each pattern.
+///
+/// Patterns that are not valid regular expressions return the error as text.
+pub(crate) fn compile_pattern(pattern: &str) -> Result<Arc<Regex>, String> {
+    PATTERNS.get_or_parse(pattern, Regex::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_type_checks() {
+        let rules = InputValidation::default();
+
+        assert!(rules.validate(&InputType::Text, "anything").is_ok());
+        assert!(rules.validate(&InputType::Numeric, "12345").is_ok());
+        assert_eq!(
+            rules.validate(&InputType::Numeric, "12a"),
+            Err(InputValidationError::NotNumeric)
+        );
+        assert!(rules.validate(&InputType::Amount, "150.50").is_ok());
+        assert_eq!(
+            rules.validate(&InputType::Amount, "1.234"),
+            Err(InputValidationError::InvalidAmount)
+        );
+        assert!(rules.validate(&InputType::Msisdn, "+233201234567").is_ok());
+        assert_eq!(
+            rules.validate(&InputType::Msisdn, "12345"),
+            Err(InputValidationError::InvalidMsisdn)
+        );
+        assert!(rules.validate(&InputType::Pin, "1234").is_ok());
+        assert_eq!(
+            rules.validate(&InputType::Pin, "12345"),
+            Err(InputValidationError::InvalidPin)
+        );
+    }
+
+    #[test]
+    fn test_input_validation_rules() {
+        let rules = InputValidation {
+            min_length: Some(2),
+            max_length: Some(4),
+            min_value: Some(10.0),
+            max_value: Some(500.0),
+            ..Default::default()
+        };
+
+        assert!(rules.validate(&InputType::Numeric, "100").is_ok());
+        assert_eq!(
+            rules.validate(&InputType::Numeric, "1"),
+            Err(InputValidationError::TooShort(2))
+        );
+        assert_eq!(
+            rules.validate(&InputType::Numeric, "10000"),
+            Err(InputValidationError::TooLong(4))
+        );
+        assert_eq!(
+            rules.validate(&InputType::Numeric, "501"),
+            Err(InputValidationError::AboveMaximum(500.0))
+        );
+
+        let pattern = InputValidation {
+            pattern: Some(r"^[A-Z]{3}$".to_string()),
+            error_message: Some("Enter a 3 letter code".to_string()),
+            ..Default::default()
+        };
+        let error = pattern.validate(&InputType::Regex, "abc").unwrap_err();
+        assert_eq!(error, InputValidationError::PatternMismatch);
+        assert_eq!(pattern.error_text(&error), "Enter a 3 letter code");
+    }
+}
@@ -0,0 +1,131 @@
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+/// Text displayed to the user, either a single string or a translation per language.
+///
+/// Screen `text` and menu item `display_name` accept both forms, so existing menus keep working
+/// and translations can be added one screen at a time.
+///
+/// # Example
+///
+/// ```json
+/// "text": "Welcome",
+/// "text": {
+///     "en": "Welcome",
+///     "fr": "Bienvenue",
+///     "ha": "Barka da zuwa",
+///     "sw": "Karibu"
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum LocalizedText {
+    Text(String),
+    Translations(HashMap<String, String>),
+}
+
+impl Default for LocalizedText {
+    fn default() -> Self {
+        LocalizedText::Text(String::new())
+    }
+}
+
+impl From<&str> for LocalizedText {
+    fn from(text: &str) -> Self {
+        LocalizedText::Text(text.to_string())
+    }
+}
+
+impl From<String> for LocalizedText {
+    fn from(text: String) -> Self {
+        LocalizedText::Text(text)
+    }
+}
+
+/// Formats the text as it is stored in the builder database.
+///
+/// A single string is stored as it is, translations are stored as a JSON object.
+impl Display for LocalizedText {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocalizedText::Text(text) => write!(f, "{}", text),
+            LocalizedText::Translations(translations) => write!(
+                f,
+                "{}",
+                serde_json::to_string(translations).unwrap_or_default()
+            ),
+        }
+    }
+}
+
+impl LocalizedText {
+    /// Reads text stored in the builder database, the reverse of `to_string`.
+    pub fn from_string(text: &str) -> LocalizedText {
+        if text.trim_start().starts_with('{') {
+            if let Ok(translations) = serde_json::from_str(text) {
+                return LocalizedText::Translations(translations);
+            }
+        }
+        LocalizedText::Text(text.to_string())
+    }
+
+    /// Returns the text for the language.
+    ///
+    /// When there is no translation for `language`, the `fallback` language is used, then the
+    /// first language in alphabetical order so that something is always displayed.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - The language of the session.
+    /// * `fallback` - The default language of the menu.
+    pub fn get(&self, language: &str, fallback: &str) -> &str {
+        match self {
+            LocalizedText::Text(text) => text,
+            LocalizedText::Translations(translations) => translations
+                .get(language)
+                .or_else(|| translations.get(fallback))
+                .or_else(|| {
+                    translations
+                        .iter()
+                        .min_by_key(|(language, _)| language.as_str())
+                        .map(|(_, text)| text)
+                })
+                .map(|text| text.as_str())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localized_text_get() {
+        let text: LocalizedText =
+            serde_json::from_str(r#"{"en": "Welcome", "fr": "Bienvenue"}"#).unwrap();
+
+        assert_eq!(text.get("fr", "en"), "Bienvenue");
+        assert_eq!(text.get("sw", "en"), "Welcome");
+        assert_eq!(text.get("sw", "ha"), "Welcome");
+
+        let single: LocalizedText = serde_json::from_str(r#""Karibu""#).unwrap();
+        assert_eq!(single.get("fr", "en"), "Karibu");
+    }
+
+    #[test]
+    fn test_localized_text_string_round_trip() {
+        let single = LocalizedText::from("Welcome");
+        assert_eq!(LocalizedText::from_string(&single.to_string()), single);
+
+        let mut translations = HashMap::new();
+        translations.insert("en".to_string(), "Welcome".to_string());
+        translations.insert("ha".to_string(), "Barka da zuwa".to_string());
+        let translated = LocalizedText::Translations(translations);
+        assert_eq!(
+            LocalizedText::from_string(&translated.to_string()),
+            translated
+        );
+    }
+}
@@ -6,6 +6,10 @@ use std::fs::File;
 use std::io::prelude::*;
 
 use crate::core::{
+    ussd_dial::USSDShortcut,
+    ussd_error::{ErrorSettings, UssdError},
+    ussd_language::LocalizedText,
+    ussd_pagination::PaginationSettings,
     ussd_screens::{ScreenType, USSDScreen},
     ussd_service::USSDService,
 };
@@ -22,16 +26,41 @@ use crate::core::{
 ///
 /// * `menus`: A `HashMap<String, Screen>` representing the menus available in the USSD menu structure.
 /// * `services`: A `HashMap<String, USSDService>` representing the services associated with the menu structure.
+/// * `pagination`: The `PaginationSettings` used to split long menu screens into pages.
+/// * `default_language`: The language used when a text has no translation for the session language.
+/// * `unknown_service_code`: The message displayed when no `Initial` screen serves the dialled service code.
+/// * `shortcuts`: Extended dial strings that jump straight to a screen.
+/// * `errors`: The messages displayed when a request fails, by class of error.
 ///
 /// # Derives
 ///
 /// The `USSDMenu` struct derives `Debug`, `Clone`, `Deserialize`, and `Serialize` traits
 /// to enable debugging, cloning, and serialization/deserialization of menu instances.
 ///
-#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct USSDMenu {
     pub menus: HashMap<String, USSDScreen>,
     pub services: HashMap<String, USSDService>,
+    #[serde(default)]
+    pub pagination: PaginationSettings,
+    #[serde(default)]
+    pub default_language: String,
+    #[serde(default = "default_unknown_service_code")]
+    pub unknown_service_code: LocalizedText,
+    #[serde(default)]
+    pub shortcuts: Vec<USSDShortcut>,
+    #[serde(default)]
+    pub errors: ErrorSettings,
+}
+
+fn default_unknown_service_code() -> LocalizedText {
+    LocalizedText::from("Unknown service code")
+}
+
+impl Default for USSDMenu {
+    fn default() -> Self {
+        USSDMenu::new()
+    }
 }
 
 impl USSDMenu {
@@ -45,6 +74,11 @@ impl USSDMenu {
         USSDMenu {
             menus: HashMap::new(),
             services: HashMap::new(),
+            pagination: PaginationSettings::default(),
+            default_language: String::new(),
+            unknown_service_code: default_unknown_service_code(),
+            shortcuts: Vec::new(),
+            errors: ErrorSettings::default(),
         }
     }
 
@@ -69,7 +103,7 @@ impl USSDMenu {
         file.read_to_string(&mut contents)?;
         // let contents = include_str!("../data/menu.json");
         let menu: USSDMenu = serde_json::from_str(&contents)?;
-        Ok(menu)
+        menu.parse_expressions()
     }
 
     // Save menu structure to JSON file
@@ -83,7 +117,20 @@ impl USSDMenu {
     // load menu from config
     pub fn _load_from_config(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
         let menu: USSDMenu = config.get("menu")?;
-        Ok(menu)
+        menu.parse_expressions()
+    }
+
+    /// Parses the expressions of a menu that has just loaded.
+    ///
+    /// Expressions that do not parse are reported now, rather than when a session reaches them.
+    fn parse_expressions(self) -> Result<Self, Box<dyn std::error::Error>> {
+        let errors = self.expression_errors();
+        if !errors.is_empty() {
+            let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            return Err(errors.join("\n").into());
+        }
+
+        Ok(self)
     }
 
     /// Retrieves the initial screen from the USSD menu.
@@ -94,22 +141,69 @@ impl USSDMenu {
     ///
     /// A tuple containing the name of the initial screen and a reference to the initial `Screen`.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// This method panics if no initial screen is found in the menu.
+    /// `UssdError::NoInitialScreen` if no initial screen is found in the menu.
     ///
-    pub fn get_initial_screen(&self) -> (String, &USSDScreen) {
+    pub fn get_initial_screen(&self) -> Result<(String, &USSDScreen), UssdError> {
         for (screen_name, screen) in self.menus.iter() {
             if let ScreenType::Initial = screen.screen_type {
-                return (screen_name.clone(), screen);
+                return Ok((screen_name.clone(), screen));
             }
         }
-        panic!("No initial screen found!");
+        Err(UssdError::NoInitialScreen)
+    }
+
+    /// Retrieves the initial screen for the dialled service code.
+    ///
+    /// An `Initial` screen whose `service_code` matches is preferred, then an `Initial` screen
+    /// without a service code, which serves any code. Requests without a service code start at
+    /// the `Initial` screen when the menu has a single one, for gateways that do not send one.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_code`: The service code dialled by the user.
+    ///
+    /// # Returns
+    ///
+    /// The name of the initial screen and a reference to it, or `None` if no `Initial` screen
+    /// serves the service code.
+    ///
+    pub fn initial_screen_for(&self, service_code: &str) -> Option<(String, &USSDScreen)> {
+        let service_code = service_code.trim();
+
+        let mut shared: Option<(&String, &USSDScreen)> = None;
+        let mut only: Option<(&String, &USSDScreen)> = None;
+        let mut count = 0;
+
+        for (name, screen) in self.menus.iter() {
+            if screen.screen_type != ScreenType::Initial {
+                continue;
+            }
+            count += 1;
+            only = Some((name, screen));
+
+            match screen.service_code.as_deref() {
+                Some(code) if !service_code.is_empty() && code == service_code => {
+                    return Some((name.clone(), screen));
+                }
+                // Pick the first shared screen by name so the same screen is chosen between runs
+                None if shared.is_none_or(|(shared_name, _)| name < shared_name) => {
+                    shared = Some((name, screen));
+                }
+                _ => {}
+            }
+        }
+
+        shared
+            .or(only.filter(|_| count == 1 && service_code.is_empty()))
+            .map(|(name, screen)| (name.clone(), screen))
     }
 
     /// Filters and retrieves screens and services belonging to a specific service code.
     ///
     /// This method filters the menu's screens and services to retrieve those associated with the specified `service_code`.
+    /// Screens and services without a service code are shared by every service code, and are always included.
     ///
     /// # Arguments
     ///
@@ -128,7 +222,9 @@ impl USSDMenu {
                 Some(ref code) if code == service_code => {
                     menus.insert(name.clone(), screen.clone());
                 }
-                None => {}
+                None => {
+                    menus.insert(name.clone(), screen.clone());
+                }
                 _ => {}
             }
         }
@@ -138,15 +234,106 @@ impl USSDMenu {
                 Some(ref code) if code == service_code => {
                     services.insert(name.clone(), service.clone());
                 }
-                None => {}
+                None => {
+                    services.insert(name.clone(), service.clone());
+                }
                 _ => {}
             }
         }
 
-        USSDMenu { menus, services }
+        USSDMenu {
+            menus,
+            services,
+            pagination: self.pagination.clone(),
+            default_language: self.default_language.clone(),
+            unknown_service_code: self.unknown_service_code.clone(),
+            shortcuts: self
+                .shortcuts
+                .iter()
+                .filter(|shortcut| self.split_dial_string(&shortcut.pattern).0 == service_code)
+                .cloned()
+                .collect(),
+            errors: self.errors.clone(),
+        }
     }
 
     // pub fn builder(service_code: &str, connection: DbConnection) -> MenuBuilder {
     //     MenuBuilder::new(service_code, connection)
     // }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn initial_screen(service_code: Option<&str>) -> USSDScreen {
+        USSDScreen {
+            text: "Welcome".into(),
+            screen_type: ScreenType::Initial,
+            default_next_screen: "Main".to_string(),
+            service_code: service_code.map(|code| code.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_initial_screen_for_service_code() {
+        let mut menu = USSDMenu::new();
+        menu.menus
+            .insert("BankStart".to_string(), initial_screen(Some("*123#")));
+        menu.menus
+            .insert("AirtimeStart".to_string(), initial_screen(Some("*456#")));
+
+        let initial_screen_name =
+            |menu: &USSDMenu, code: &str| menu.initial_screen_for(code).map(|(name, _)| name);
+
+        assert_eq!(
+            initial_screen_name(&menu, "*123#"),
+            Some("BankStart".to_string())
+        );
+        assert_eq!(
+            initial_screen_name(&menu, " *456# "),
+            Some("AirtimeStart".to_string())
+        );
+        assert_eq!(initial_screen_name(&menu, "*789#"), None);
+        assert_eq!(initial_screen_name(&menu, ""), None);
+
+        // A single initial screen serves requests without a service code
+        let mut single = USSDMenu::new();
+        single
+            .menus
+            .insert("BankStart".to_string(), initial_screen(Some("*123#")));
+        assert_eq!(
+            initial_screen_name(&single, ""),
+            Some("BankStart".to_string())
+        );
+        assert_eq!(initial_screen_name(&single, "*789#"), None);
+
+        // An initial screen without a service code serves any other code
+        menu.menus.insert("Start".to_string(), initial_screen(None));
+        assert_eq!(
+            initial_screen_name(&menu, "*789#"),
+            Some("Start".to_string())
+        );
+        assert_eq!(
+            initial_screen_name(&menu, "*123#"),
+            Some("BankStart".to_string())
+        );
+        assert_eq!(initial_screen_name(&menu, ""), Some("Start".to_string()));
+    }
+
+    #[test]
+    fn test_belonging_to_includes_shared_screens() {
+        let mut menu = USSDMenu::new();
+        menu.menus
+            .insert("BankStart".to_string(), initial_screen(Some("*123#")));
+        menu.menus
+            .insert("AirtimeStart".to_string(), initial_screen(Some("*456#")));
+        menu.menus.insert("Main".to_string(), initial_screen(None));
+
+        let bank = menu.belonging_to("*123#");
+        assert!(bank.menus.contains_key("BankStart"));
+        assert!(bank.menus.contains_key("Main"));
+        assert!(!bank.menus.contains_key("AirtimeStart"));
+    }
+}
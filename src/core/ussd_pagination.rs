@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+
+/// Controls how long `Menu` screens are split into pages.
+///
+/// Gateways truncate or reject messages longer than roughly 160 to 182 characters. When the text
+/// of a menu screen and its items do not fit in `max_message_length`, the items are spread over
+/// several pages. Each page shows a "More" option leading to the next page, and every page but
+/// the first shows a "Back" option leading to the previous one.
+///
+/// # Fields
+///
+/// * `max_message_length`: The maximum number of characters in a single message.
+/// * `more_option`: The option the user enters to go to the next page.
+/// * `more_label`: The label displayed next to the `more_option`.
+/// * `back_option`: The option the user enters to go to the previous page.
+/// * `back_label`: The label displayed next to the `back_option`.
+///
+/// # Example
+///
+/// ```json
+/// "pagination": {
+///     "max_message_length": 160,
+///     "more_option": "98",
+///     "more_label": "More",
+///     "back_option": "0",
+///     "back_label": "Back"
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PaginationSettings {
+    pub max_message_length: usize,
+    pub more_option: String,
+    pub more_label: String,
+    pub back_option: String,
+    pub back_label: String,
+}
+
+impl Default for PaginationSettings {
+    fn default() -> Self {
+        PaginationSettings {
+            max_message_length: 182,
+            more_option: "98".to_string(),
+            more_label: "More".to_string(),
+            back_option: "0".to_string(),
+            back_label: "Back".to_string(),
+        }
+    }
+}
+
+impl PaginationSettings {
+    /// Splits the lines of a menu into pages that fit within `max_message_length`.
+    ///
+    /// The `header` is shown at the top of every page and counts towards the message length, as do
+    /// the navigation lines appended to each page. Every page holds at least one line, so a line
+    /// that is too long on its own still gets a page of its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - The text displayed above the lines on every page.
+    /// * `lines` - The menu lines to split into pages.
+    ///
+    /// # Returns
+    ///
+    /// A list of pages, each holding its menu lines followed by its navigation lines.
+    ///
+    pub fn paginate(&self, header: &str, lines: &[String]) -> Vec<Vec<String>> {
+        let more_line = format!("{}. {}", self.more_option, self.more_label);
+        let back_line = format!("{}. {}", self.back_option, self.back_label);
+
+        let mut pages = Vec::new();
+        let mut start = 0;
+
+        while start < lines.len() || pages.is_empty() {
+            let is_first = pages.is_empty();
+            let mut nav: Vec<String> = Vec::new();
+            if !is_first {
+                nav.push(back_line.clone());
+            }
+
+            // The rest of the lines fit on this page, so it is the last one
+            if message_length(header, &lines[start..], &nav) <= self.max_message_length {
+                let mut page = lines[start..].to_vec();
+                page.extend(nav);
+                pages.push(page);
+                break;
+            }
+
+            nav.insert(0, more_line.clone());
+
+            let mut end = start + 1;
+            while end < lines.len()
+                && message_length(header, &lines[start..end + 1], &nav) <= self.max_message_length
+            {
+                end += 1;
+            }
+
+            let mut page = lines[start..end].to_vec();
+            page.extend(nav);
+            pages.push(page);
+            start = end;
+        }
+
+        pages
+    }
+}
+
+/// Returns the number of characters in a message made of the header, lines and navigation lines,
+/// each on its own line.
+fn message_length(header: &str, lines: &[String], nav: &[String]) -> usize {
+    header.chars().count()
+        + lines
+            .iter()
+            .chain(nav.iter())
+            .map(|line| line.chars().count() + 1)
+            .sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(count: usize) -> Vec<String> {
+        (1..=count)
+            .map(|i| format!("{}. Option number {}", i, i))
+            .collect()
+    }
+
+    #[test]
+    fn test_paginate_fits_on_one_page() {
+        let settings = PaginationSettings::default();
+        let pages = settings.paginate("Main Menu", &lines(3));
+
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0], lines(3));
+    }
+
+    #[test]
+    fn test_paginate_splits_long_menus() {
+        let settings = PaginationSettings {
+            max_message_length: 60,
+            ..Default::default()
+        };
+        let header = "Main Menu";
+        let pages = settings.paginate(header, &lines(8));
+
+        assert!(pages.len() > 1);
+        assert_eq!(pages[0].last().unwrap(), "98. More");
+        assert_eq!(pages.last().unwrap().last().unwrap(), "0. Back");
+
+        for page in pages.iter() {
+            assert!(message_length(header, page, &[]) <= settings.max_message_length);
+        }
+
+        // Every line appears exactly once across the pages
+        let shown: Vec<String> = pages
+            .into_iter()
+            .flatten()
+            .filter(|line| line != "98. More" && line != "0. Back")
+            .collect();
+        assert_eq!(shown, lines(8));
+    }
+
+    #[test]
+    fn test_paginate_long_line_gets_own_page() {
+        let settings = PaginationSettings {
+            max_message_length: 10,
+            ..Default::default()
+        };
+        let pages = settings.paginate("Header", &lines(2));
+
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0][0], "1. Option number 1");
+        assert_eq!(pages[1][0], "2. Option number 2");
+    }
+}
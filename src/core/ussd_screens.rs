@@ -1,13 +1,21 @@
 use crate::{
     error,
     types::USSDData,
-    utils::{evaluate_expression, evaluate_expression_op},
+    utils::{Expression, FunctionRegistry, Parsed, Template},
 };
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use super::{ussd_service::USSDServiceTrait, USSDRequest, USSDService, USSDSession};
+use super::{
+    ussd_confirm::{ConfirmChoice, USSDConfirm},
+    ussd_dynamic_menu::USSDDynamicMenu,
+    ussd_input::{InputType, InputValidation},
+    ussd_language::LocalizedText,
+    ussd_pagination::PaginationSettings,
+    ussd_service::{ServiceOutcome, USSDServiceTrait},
+    USSDMenu, USSDRequest, USSDService, USSDSession, UssdError,
+};
 
 // Define types of screens
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
@@ -19,6 +27,8 @@ pub enum ScreenType {
     Function,
     Router,
     Quit,
+    Confirm,
+    Info,
 }
 
 impl ScreenType {
@@ -30,6 +40,8 @@ impl ScreenType {
             ScreenType::Function => "Function".to_string(),
             ScreenType::Router => "Router".to_string(),
             ScreenType::Quit => "Quit".to_string(),
+            ScreenType::Confirm => "Confirm".to_string(),
+            ScreenType::Info => "Info".to_string(),
         }
     }
 
@@ -41,6 +53,8 @@ impl ScreenType {
             "Function" => ScreenType::Function,
             "Router" => ScreenType::Router,
             "Quit" => ScreenType::Quit,
+            "Confirm" => ScreenType::Confirm,
+            "Info" => ScreenType::Info,
             _ => {
                 error!("Invalid screen type");
                 ScreenType::Initial
@@ -52,13 +66,16 @@ impl ScreenType {
 // Define structure for a screen
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 pub struct USSDScreen {
-    pub text: String,
+    pub text: LocalizedText,
     pub screen_type: ScreenType,
     pub default_next_screen: String,
     #[serde(default)]
     pub service_code: Option<String>,
     #[serde(default)]
     pub menu_items: Option<HashMap<String, USSDMenuItems>>,
+    /// Menu items built from a list in the session data, used instead of `menu_items`.
+    #[serde(default)]
+    pub dynamic_menu: Option<USSDDynamicMenu>,
     #[serde(default)]
     pub function: Option<String>,
     #[serde(default)]
@@ -67,14 +84,34 @@ pub struct USSDScreen {
     pub input_identifier: Option<String>,
     #[serde(default)]
     pub input_type: Option<String>,
+    #[serde(default)]
+    pub input_validation: Option<InputValidation>,
+    /// The accept and reject choices of a `Confirm` screen, "Yes" and "No" when unset.
+    #[serde(default)]
+    pub confirm: Option<USSDConfirm>,
+    /// The screen a `Function` screen moves to when its service fails.
+    #[serde(default)]
+    pub on_error_screen: Option<String>,
+    /// The screen a `Function` screen moves to when its service times out, `on_error_screen`
+    /// when unset.
+    #[serde(default)]
+    pub on_timeout_screen: Option<String>,
+    /// The parsed screen texts.
+    #[serde(skip)]
+    pub(crate) templates: Parsed<Template>,
+    /// The parsed router option expressions.
+    #[serde(skip)]
+    pub(crate) expressions: Parsed<Expression>,
     // Additional fields based on screen type
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 pub struct USSDMenuItems {
     pub option: String,
-    pub display_name: String,
+    pub display_name: LocalizedText,
     pub next_screen: String,
+    #[serde(default)]
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
@@ -84,6 +121,11 @@ pub struct USSDRouterOption {
 }
 
 fn back(session: &mut USSDSession) {
+    // The current screen is visited again each time it is displayed, skip past it
+    while session.visited_screens.last() == Some(&session.current_screen) {
+        session.visited_screens.pop();
+    }
+
     // switch to the previous screen
     if let Some(prev_screen) = session.visited_screens.pop() {
         session.current_screen = prev_screen;
@@ -92,17 +134,52 @@ fn back(session: &mut USSDSession) {
 
 fn home(session: &mut USSDSession) {
     // Switch to the initial screen
-    session.current_screen = session.visited_screens.first().unwrap().clone();
+    if let Some(initial_screen) = session.visited_screens.first() {
+        session.current_screen = initial_screen.clone();
+    }
+}
+
+/// Moves between the pages of a menu screen.
+///
+/// Returns `true` if the input was a page navigation option and the page was changed.
+/// The "More" option only applies when no menu item uses the same option and another page follows
+/// the current one. `page_count` is only called for the "More" option.
+fn change_page(
+    session: &mut USSDSession,
+    input: &str,
+    options: &[String],
+    pagination: &PaginationSettings,
+    page_count: impl FnOnce(&USSDSession) -> usize,
+) -> bool {
+    if input == pagination.more_option
+        && !options.iter().any(|option| option == input)
+        && session.menu_page + 1 < page_count(session)
+    {
+        session.menu_page += 1;
+        return true;
+    }
+
+    if input == pagination.back_option && session.menu_page > 0 {
+        session.menu_page -= 1;
+        return true;
+    }
+
+    false
 }
 
 pub trait USSDAction {
-    fn display(&self, session: &mut USSDSession) -> Option<String>;
+    fn display(
+        &self,
+        session: &mut USSDSession,
+        menu: &USSDMenu,
+    ) -> Result<Option<String>, UssdError>;
     fn execute(
         &self,
         session: &mut USSDSession,
         request: &USSDRequest,
-        services: &HashMap<String, USSDService>,
-    );
+        menu: &USSDMenu,
+        functions: &FunctionRegistry,
+    ) -> Result<(), UssdError>;
 }
 
 impl USSDAction for USSDScreen {
@@ -110,34 +187,53 @@ impl USSDAction for USSDScreen {
     ///
     /// The message construction depends on the type of screen:
     /// - For an initial screen, no message is displayed.
-    /// - For a menu screen, the message concatenates the screen text with the menu items.
+    /// - For a menu screen, the message concatenates the screen text with the menu items, split into
+    ///   pages according to the menu's `PaginationSettings`.
     /// - For an input screen, the message comprises the screen text alone.
+    /// - For a confirm screen, the message concatenates the screen text with the accept and reject
+    ///   options.
+    /// - For an info screen, the message comprises the screen text alone.
     /// - For a function screen, no message is displayed.
     /// - For a router screen, no message is displayed.
-    fn display(&self, session: &mut USSDSession) -> Option<String> {
+    ///
+    /// The text and menu item names are picked by the session language, falling back to the menu's
+    /// `default_language`.
+    ///
+    /// Menu items whose option is not a positive number are reported as
+    /// `UssdError::InvalidMenuOption`.
+    fn display(
+        &self,
+        session: &mut USSDSession,
+        menu: &USSDMenu,
+    ) -> Result<Option<String>, UssdError> {
         let mut message = String::new();
+        let language = session.language.clone();
+        let text = self
+            .templates
+            .render(self.text.get(&language, &menu.default_language), session);
 
         // check if there's an error message in the session if there is then append to message
-        if let Some(error_message) = &session.error_message {
+        // it is only displayed once
+        if let Some(error_message) = session.error_message.take() {
             message.push_str(&error_message);
             message.push_str("\n\n");
         }
 
-        match self.screen_type {
+        let message = match self.screen_type {
             ScreenType::Initial => None,
             ScreenType::Menu => {
-                let text = evaluate_expression(&self.text, session);
                 message.push_str(&text);
 
-                if let Some(menu_items) = &self.menu_items {
-                    let mut sorted_menu_items: Vec<(&String, &USSDMenuItems)> =
-                        menu_items.iter().collect();
-                    // Sort the menu items by their option number
-                    sorted_menu_items
-                        .sort_by_key(|(_, item)| item.option.parse::<usize>().unwrap());
+                let lines = self.menu_lines(session, &language, &menu.default_language)?;
 
-                    for (index, (_, value)) in sorted_menu_items.iter().enumerate() {
-                        message.push_str(&format!("\n{}. {}", index + 1, value.display_name));
+                if let Some(lines) = lines {
+                    let pages = menu.pagination.paginate(&message, &lines);
+
+                    // Keep the page within bounds in case the items changed since the last page
+                    session.menu_page = session.menu_page.min(pages.len() - 1);
+
+                    for line in pages[session.menu_page].iter() {
+                        message.push_str(&format!("\n{}", line));
                     }
                 } else {
                     message.push_str("\nNo menu items found");
@@ -145,20 +241,29 @@ impl USSDAction for USSDScreen {
 
                 Some(message)
             }
-            ScreenType::Input => {
-                let text = evaluate_expression(&self.text, session);
+            ScreenType::Input | ScreenType::Info => {
+                message.push_str(&text);
+                Some(message)
+            }
+            ScreenType::Confirm => {
                 message.push_str(&text);
+
+                let confirm = self.confirm.clone().unwrap_or_default();
+                for line in confirm.lines(&language, &menu.default_language) {
+                    message.push_str(&format!("\n{}", line));
+                }
                 Some(message)
             }
             ScreenType::Function => None,
             ScreenType::Router => None,
             ScreenType::Quit => {
-                let text = evaluate_expression(&self.text, session);
                 message.push_str(&text);
                 session.end_session = true;
                 Some(message)
             }
-        }
+        };
+
+        Ok(message)
     }
 
     /// Executes the specified screen action, determining the next screen based on the action type.
@@ -167,22 +272,80 @@ impl USSDAction for USSDScreen {
     /// - If it's a function, the function is called.
     /// - If it's a router, the next screen is determined based on the router option.
     /// - If it's an initial, quit, or menu screen, the next screen is set based on a default next screen.
-    /// - If it's an input screen, the input is stored in the session data, and the next screen is set based on a default next screen.
+    /// - If it's an info screen, any input moves on to the default next screen.
+    /// - If it's a confirm screen, accepting moves to the `accept_screen` or the default next screen,
+    ///   and rejecting moves to the `reject_screen` or back to the previous screen.
+    /// - If the selected menu item has a `language`, the session language is switched to it.
+    /// - If it's a paginated menu screen and the input is a page navigation option, the page is changed.
+    /// - If it's an input screen, the input is checked against the screen's `input_type` and `input_validation`.
+    ///   Valid input is stored in the session data, and the next screen is set based on a default next screen.
+    ///   Invalid input sets the session error message, and the same screen is displayed again.
+    ///
+    /// A function screen whose service is not defined in the menu is reported as
+    /// `UssdError::UnknownService`.
     fn execute(
         &self,
         session: &mut USSDSession,
         request: &USSDRequest,
-        services: &HashMap<String, USSDService>,
-    ) {
+        menu: &USSDMenu,
+        functions: &FunctionRegistry,
+    ) -> Result<(), UssdError> {
         let input = request.input.trim();
 
+        // The initial screen takes no input and info screens move on with any input, so back and
+        // home do not apply
+        if matches!(self.screen_type, ScreenType::Initial | ScreenType::Info) {
+            session.current_screen = self.default_next_screen.clone();
+            return Ok(());
+        }
+
+        // The elements of a dynamic menu are evaluated once, for both paging and picking an option
+        let elements = match (&self.screen_type, &self.dynamic_menu) {
+            (ScreenType::Menu, Some(dynamic_menu)) => dynamic_menu.elements(session),
+            _ => Vec::new(),
+        };
+
+        if self.screen_type == ScreenType::Menu {
+            let options: Option<Vec<String>> = match (&self.dynamic_menu, &self.menu_items) {
+                (Some(dynamic_menu), _) => Some(dynamic_menu.options(&elements)),
+                (None, Some(menu_items)) => Some(
+                    menu_items
+                        .values()
+                        .map(|item| item.option.clone())
+                        .collect(),
+                ),
+                (None, None) => None,
+            };
+
+            if let Some(options) = options {
+                let page_count = |session: &USSDSession| self.page_count(session, menu);
+                if change_page(session, input, &options, &menu.pagination, page_count) {
+                    return Ok(());
+                }
+            }
+        }
+
+        // Function and router screens run after the input of the screen before them, which is not
+        // meant for back and home
+        let takes_input = !matches!(self.screen_type, ScreenType::Function | ScreenType::Router);
+
         match input {
-            "0" => back(session),
-            "00" => home(session),
+            "0" if takes_input => back(session),
+            "00" if takes_input => home(session),
             _ => {
                 session.current_screen = match self.screen_type {
-                    ScreenType::Initial => self.default_next_screen.clone(),
+                    ScreenType::Initial | ScreenType::Info => self.default_next_screen.clone(),
                     ScreenType::Menu => {
+                        if let Some(dynamic_menu) = &self.dynamic_menu {
+                            if dynamic_menu.select(session, elements, input) {
+                                session.current_screen = dynamic_menu.next_screen.clone();
+                            } else {
+                                error!("Selected menu item not found");
+                                session.error_message = Some("Invalid menu option".to_string());
+                            }
+                            return Ok(());
+                        }
+
                         match input.parse::<usize>() {
                             Ok(selected_option) if selected_option > 0 => {
                                 if let Some(menu_items_ref_unwrapped) = self.menu_items.as_ref() {
@@ -190,14 +353,18 @@ impl USSDAction for USSDScreen {
                                         .values()
                                         .find(|item| item.option == selected_option.to_string())
                                     {
+                                        // Menu items with a language switch the session language
+                                        if let Some(language) = &selected_item.language {
+                                            session.language = language.clone();
+                                        }
                                         session.current_screen = selected_item.next_screen.clone();
-                                        return;
+                                        return Ok(());
                                     } else {
                                         error!("Selected menu item not found");
                                         session.error_message =
                                             Some("Invalid menu option".to_string());
                                         session.current_screen = session.current_screen.clone();
-                                        return;
+                                        return Ok(());
                                     }
                                 }
                             }
@@ -206,6 +373,12 @@ impl USSDAction for USSDScreen {
                         self.default_next_screen.clone()
                     }
                     ScreenType::Input => {
+                        if let Err(message) = self.validate_input(input) {
+                            error!("Invalid input for screen: {}", session.current_screen);
+                            session.error_message = Some(message);
+                            return Ok(());
+                        }
+
                         if let Some(input_identifier) = &self.input_identifier {
                             session.data.insert(
                                 input_identifier.to_string(),
@@ -214,18 +387,20 @@ impl USSDAction for USSDScreen {
                         }
                         self.default_next_screen.clone()
                     }
-                    ScreenType::Function => {
-                        if let Some(function_name) = &self.function {
-                            call_function(session, services, function_name);
+                    ScreenType::Function => match &self.function {
+                        Some(function_name) => {
+                            let outcome =
+                                call_function(session, &menu.services, function_name, functions)?;
+                            self.next_screen_after(&outcome)
                         }
-                        self.default_next_screen.clone()
-                    }
+                        None => self.default_next_screen.clone(),
+                    },
                     ScreenType::Router => {
                         if let Some(router_options) = &self.router_options {
                             for option in router_options {
-                                if evaluate_expression_op(session, &option.router_option) {
+                                if self.expressions.is_true(session, &option.router_option) {
                                     session.current_screen = option.next_screen.clone();
-                                    return;
+                                    return Ok(());
                                 }
                             }
                         }
@@ -235,9 +410,128 @@ impl USSDAction for USSDScreen {
                         session.end_session = true;
                         self.default_next_screen.clone()
                     }
+                    ScreenType::Confirm => {
+                        let confirm = self.confirm.clone().unwrap_or_default();
+                        match confirm.choice(input) {
+                            Some(ConfirmChoice::Accept) => confirm
+                                .accept_screen
+                                .unwrap_or_else(|| self.default_next_screen.clone()),
+                            Some(ConfirmChoice::Reject) => match confirm.reject_screen {
+                                Some(reject_screen) => reject_screen,
+                                None => {
+                                    back(session);
+                                    return Ok(());
+                                }
+                            },
+                            None => {
+                                error!("Invalid confirm option");
+                                session.error_message = Some("Invalid menu option".to_string());
+                                return Ok(());
+                            }
+                        }
+                    }
                 }
             }
         }
+
+        Ok(())
+    }
+}
+
+impl USSDScreen {
+    /// Returns the lines of the items of a `Menu` screen, or `None` if it has no items.
+    ///
+    /// Menu items whose option is not a positive number are reported as
+    /// `UssdError::InvalidMenuOption`.
+    fn menu_lines(
+        &self,
+        session: &USSDSession,
+        language: &str,
+        default_language: &str,
+    ) -> Result<Option<Vec<String>>, UssdError> {
+        let lines = match (&self.dynamic_menu, &self.menu_items) {
+            (Some(dynamic_menu), _) => {
+                let elements = dynamic_menu.elements(session);
+                Some(dynamic_menu.lines(session, elements, language, default_language))
+                    .filter(|lines| !lines.is_empty())
+            }
+            (None, Some(menu_items)) => {
+                let mut sorted_menu_items: Vec<(usize, &USSDMenuItems)> = Vec::new();
+                for item in menu_items.values() {
+                    let option =
+                        item.option
+                            .parse::<usize>()
+                            .map_err(|_| UssdError::InvalidMenuOption {
+                                screen: session.current_screen.clone(),
+                                option: item.option.clone(),
+                            })?;
+                    sorted_menu_items.push((option, item));
+                }
+                // Sort the menu items by their option number
+                sorted_menu_items.sort_by_key(|(option, _)| *option);
+
+                Some(
+                    sorted_menu_items
+                        .iter()
+                        .map(|(_, item)| {
+                            let display_name = item.display_name.get(language, default_language);
+                            format!("{}. {}", item.option, display_name)
+                        })
+                        .collect::<Vec<String>>(),
+                )
+            }
+            (None, None) => None,
+        };
+
+        Ok(lines)
+    }
+
+    /// Returns the number of pages the items of a `Menu` screen are displayed on.
+    fn page_count(&self, session: &USSDSession, menu: &USSDMenu) -> usize {
+        let language = &session.language;
+        let text = self
+            .templates
+            .render(self.text.get(language, &menu.default_language), session);
+
+        match self.menu_lines(session, language, &menu.default_language) {
+            Ok(Some(lines)) => menu.pagination.paginate(&text, &lines).len(),
+            _ => 1,
+        }
+    }
+
+    /// Checks the input of an `Input` screen against its `input_type` and `input_validation`.
+    ///
+    /// # Returns
+    ///
+    /// The error text to display if the input is rejected.
+    pub fn validate_input(&self, input: &str) -> Result<(), String> {
+        let input_type = self
+            .input_type
+            .as_deref()
+            .map(InputType::from_string)
+            .unwrap_or_default();
+        let validation = self.input_validation.clone().unwrap_or_default();
+
+        validation
+            .validate(&input_type, input)
+            .map_err(|e| validation.error_text(&e))
+    }
+
+    /// Returns the screen a `Function` screen moves to after its service call.
+    ///
+    /// Failed calls move to `on_error_screen` and timed out calls to `on_timeout_screen`, falling
+    /// back to `on_error_screen`. Calls without a matching screen move to `default_next_screen`.
+    pub fn next_screen_after(&self, outcome: &ServiceOutcome) -> String {
+        let screen = match outcome {
+            ServiceOutcome::Success => None,
+            ServiceOutcome::Failed => self.on_error_screen.as_ref(),
+            ServiceOutcome::TimedOut => self
+                .on_timeout_screen
+                .as_ref()
+                .or(self.on_error_screen.as_ref()),
+        };
+
+        screen.unwrap_or(&self.default_next_screen).clone()
     }
 }
 
@@ -249,7 +543,7 @@ impl USSDAction for USSDScreen {
 /// * `request` - The USSD request.
 /// * `services` - The USSD services.
 /// * `function_name` - The name of the function to call.
-/// * `functions_path` - The path to the functions used by the USSD application.
+/// * `functions` - The functions registered in the USSD application.
 ///
 /// # Example
 ///
@@ -263,16 +557,118 @@ impl USSDAction for USSDScreen {
 ///
 /// let services = HashMap::new();
 /// let function_name = "function_name".to_string();
-/// let functions_path = "path/to/functions".to_string();
+/// let functions = FunctionRegistry::new();
 ///
-/// call_function(&mut session, &services, &function_name, &functions_path);
+/// call_function(&mut session, &services, &function_name, &functions);
 /// ```
-fn call_function(
+pub(crate) fn call_function(
     session: &mut USSDSession,
     services: &HashMap<String, USSDService>,
     function_name: &str,
-) {
-    let service = services.get(function_name).unwrap();
+    functions: &FunctionRegistry,
+) -> Result<ServiceOutcome, UssdError> {
+    let service = find_service(session, services, function_name)?;
 
-    service.call(session);
+    Ok(service.call(session, functions))
+}
+
+/// Call the function without blocking
+///
+/// Async counterpart of `call_function`, used when the request is processed with
+/// `process_request_async`. It supports async functions and http services.
+///
+/// # Arguments
+///
+/// * `session` - The USSD session.
+/// * `services` - The USSD services.
+/// * `function_name` - The name of the function to call.
+/// * `functions` - The functions registered in the USSD application.
+pub(crate) async fn call_function_async(
+    session: &mut USSDSession,
+    services: &HashMap<String, USSDService>,
+    function_name: &str,
+    functions: &FunctionRegistry,
+) -> Result<ServiceOutcome, UssdError> {
+    let service = find_service(session, services, function_name)?;
+
+    Ok(service.call_async(session, functions).await)
+}
+
+/// Looks up the service called by the function screen the session is on.
+fn find_service<'a>(
+    session: &USSDSession,
+    services: &'a HashMap<String, USSDService>,
+    function_name: &str,
+) -> Result<&'a USSDService, UssdError> {
+    services
+        .get(function_name)
+        .ok_or_else(|| UssdError::UnknownService {
+            screen: session.current_screen.clone(),
+            service: function_name.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{core::USSDMenu, testing::ConversationTest};
+
+    fn menu(max_message_length: usize) -> USSDMenu {
+        serde_json::from_value(json!({
+            "menus": {
+                "Start": {
+                    "text": "",
+                    "screen_type": "Initial",
+                    "default_next_screen": "Main"
+                },
+                "Main": {
+                    "text": "Main",
+                    "screen_type": "Menu",
+                    "default_next_screen": "Main",
+                    "menu_items": {
+                        "Balance": { "option": "1", "display_name": "Balance", "next_screen": "Bye" },
+                        "Airtime": { "option": "2", "display_name": "Airtime", "next_screen": "Bye" },
+                        "Transfer": { "option": "3", "display_name": "Transfer", "next_screen": "Bye" }
+                    }
+                },
+                "Bye": {
+                    "text": "Bye",
+                    "screen_type": "Quit",
+                    "default_next_screen": ""
+                }
+            },
+            "services": {},
+            "pagination": { "max_message_length": max_message_length }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_more_on_single_page_menu() {
+        let mut test = ConversationTest::new(menu(182));
+
+        test.dial().expect_screen("Main");
+        assert!(!test.response().message.contains("98. More"));
+
+        test.input("98")
+            .expect_screen("Main")
+            .expect_contains("Invalid menu option");
+        assert_eq!(test.session().unwrap().menu_page, 0);
+    }
+
+    #[test]
+    fn test_more_on_last_page() {
+        let mut test = ConversationTest::new(menu(30));
+
+        test.dial()
+            .expect_contains("1. Balance")
+            .expect_contains("98. More");
+        test.input("98").expect_contains("2. Airtime");
+        test.input("98").expect_contains("3. Transfer");
+        assert!(!test.response().message.contains("98. More"));
+
+        test.input("98").expect_contains("Invalid menu option");
+        assert_eq!(test.session().unwrap().menu_page, 2);
+    }
 }
@@ -1,67 +1,873 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    future::Future,
+    time::{Duration, Instant},
+};
 
+use awc::{error::SendRequestError, Client};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
-use crate::{error, info, types::USSDData, utils::FUNCTION_MAP};
+use crate::{
+    debug, error, info,
+    metrics::metrics,
+    types::{hash_str_any_to_json, json_to_hash_str_any, FunctionHandler, USSDData},
+    utils::FunctionRegistry,
+    warning,
+};
 
 use super::USSDSession;
 
+/// How long http services without a `timeout_ms` may take to reply.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+thread_local! {
+    // awc clients are bound to the thread they are created on, so each worker holds its own
+    static HTTP_CLIENT: Client = Client::default();
+}
+
+/// The kind of service, which decides how the service produces its result.
+///
+/// * `Function`: Calls the registered function named by `function_name`.
+/// * `Http`: POSTs the session to `function_url` as JSON and stores the reply. Http services are
+///   only called when the request is processed with `UssdApp::run_async`. They use the awc client
+///   of the current thread, so they must run on an actix runtime, such as an actix-web worker.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub enum ServiceKind {
+    #[default]
+    #[serde(alias = "function")]
+    Function,
+    #[serde(alias = "http")]
+    Http,
+}
+
+impl Display for ServiceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServiceKind::Function => write!(f, "Function"),
+            ServiceKind::Http => write!(f, "Http"),
+        }
+    }
+}
+
+impl ServiceKind {
+    pub fn from_string(kind: &str) -> ServiceKind {
+        match kind {
+            "Http" | "http" => ServiceKind::Http,
+            _ => ServiceKind::Function,
+        }
+    }
+}
+
+/// Why a service call failed without producing a result.
+///
+/// * `Timeout`: The call took longer than the service's `timeout_ms`.
+/// * `NotFound`: No function is registered under the service's `function_name`.
+/// * `Unsupported`: The service is an http service or an async function, called with `run`.
+/// * `Http`: The http service could not be reached, replied with a status other than a success or
+///   its reply could not be read.
+/// * `Failed`: The function stopped without returning a result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceErrorKind {
+    Timeout,
+    NotFound,
+    Unsupported,
+    Http,
+    Failed,
+}
+
+impl Display for ServiceErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServiceErrorKind::Timeout => write!(f, "timeout"),
+            ServiceErrorKind::NotFound => write!(f, "not_found"),
+            ServiceErrorKind::Unsupported => write!(f, "unsupported"),
+            ServiceErrorKind::Http => write!(f, "http"),
+            ServiceErrorKind::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// A service call that failed without producing a result.
+#[derive(Debug, Clone, PartialEq)]
+struct ServiceError {
+    kind: ServiceErrorKind,
+    message: String,
+}
+
+impl ServiceError {
+    fn new(kind: ServiceErrorKind, message: &str) -> Self {
+        ServiceError {
+            kind,
+            message: message.to_string(),
+        }
+    }
+
+    fn timeout(timeout: Duration) -> Self {
+        Self::new(
+            ServiceErrorKind::Timeout,
+            &format!("Service timed out after {}ms", timeout.as_millis()),
+        )
+    }
+
+    /// Builds the data stored in the session for the error, after `attempts` calls.
+    fn to_data(&self, attempts: u32) -> USSDData {
+        let error = HashMap::from([
+            ("kind".to_string(), USSDData::Str(self.kind.to_string())),
+            ("message".to_string(), USSDData::Str(self.message.clone())),
+            ("attempts".to_string(), USSDData::Int(attempts as i64)),
+        ]);
+
+        USSDData::Dict(HashMap::from([(
+            "error".to_string(),
+            USSDData::Dict(error),
+        )]))
+    }
+}
+
+/// The outcome of a service call, which decides the screen a `Function` screen moves to.
+///
+/// * `Success`: The service returned a result without an `error` field.
+/// * `Failed`: The service failed, or returned a result with an `error` field.
+/// * `TimedOut`: The last attempt took longer than the service's `timeout_ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceOutcome {
+    Success,
+    Failed,
+    TimedOut,
+}
+
+/// A service called by `Function` screens, whose result is stored in the session data.
+///
+/// # Fields
+///
+/// * `function_name`: The registered function called by `Function` services.
+/// * `function_url`: The url passed to the function, or POSTed to by `Http` services.
+/// * `data_key`: The key of the session data the result is stored under.
+/// * `service_code`: The service code the service belongs to.
+/// * `kind`: Whether the service calls a function or an http service.
+/// * `timeout_ms`: How long a call may take, in milliseconds, before it is abandoned. Unlimited
+///   when unset.
+/// * `retries`: How many times a call that fails or times out is retried.
+/// * `retry_backoff_ms`: The wait before the first retry, in milliseconds, doubled before each
+///   following retry.
+///
+/// When every attempt fails without a result, the session data under `data_key` holds an `error`
+/// with its `kind`, `message` and number of `attempts`, so Router screens can branch on it:
+///
+/// ```json
+/// { "error": { "kind": "timeout", "message": "Service timed out after 3000ms", "attempts": 3 } }
+/// ```
+///
+/// The kinds are listed by `ServiceErrorKind`. Results with an `error` field returned by the
+/// service itself are stored as they are.
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 pub struct USSDService {
     pub function_name: String,
     pub function_url: Option<String>,
     pub data_key: String,
     pub service_code: Option<String>,
+    #[serde(default)]
+    pub kind: ServiceKind,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default)]
+    pub retry_backoff_ms: u64,
 }
 
 pub trait USSDServiceTrait {
-    fn call(&self, session: &mut USSDSession);
-    fn load_function(&self) -> Box<dyn Fn(&USSDSession, &str) -> USSDData>;
+    fn call(&self, session: &mut USSDSession, functions: &FunctionRegistry) -> ServiceOutcome;
 }
 
 impl USSDServiceTrait for USSDService {
-    fn call(&self, session: &mut USSDSession) {
-        // Find and load the function from the functions_path
-        // Logic to load the function from the function_path (You need to implement this logic)
-        let loaded_function = self.load_function();
-
-        // Clone the session to pass to the loaded function
-        let new_session: USSDSession = session.clone();
-
-        // Pass the function_url as an argument to the loaded function
-        let result = match &self.function_url {
-            Some(url) => loaded_function(&new_session, url),
-            None => loaded_function(&new_session, ""),
+    /// Calls the function of the service once and saves the result in the session data under
+    /// `data_key`.
+    ///
+    /// The function runs on the calling thread. `timeout_ms`, `retries` and `retry_backoff_ms`
+    /// are only applied by `call_async`, so a worker is never put to sleep between retries, nor
+    /// does it leave calls that timed out running on threads of their own. Services that set them
+    /// log a warning when called here.
+    #[tracing::instrument(
+        name = "ussd_service",
+        skip_all,
+        fields(service = %self.function_name, kind = %self.kind)
+    )]
+    fn call(&self, session: &mut USSDSession, functions: &FunctionRegistry) -> ServiceOutcome {
+        let started = Instant::now();
+
+        if self.has_call_policy() {
+            warning!(
+                "Service {} is called with run, which ignores its timeout and retries",
+                self.function_name
+            );
+        }
+
+        // Http services and async functions need an async runtime to run
+        let function = if self.kind == ServiceKind::Http || functions.is_async(&self.function_name)
+        {
+            error!(
+                "Service {} can only be called with run_async",
+                self.function_name
+            );
+            Err(ServiceError::new(
+                ServiceErrorKind::Unsupported,
+                "Service can only be called asynchronously",
+            ))
+        } else {
+            self.find_function(functions)
         };
 
-        // Save the returned result in the session data with the data_key
-        session.data.insert(self.data_key.clone(), result.clone());
+        let url = self.function_url.clone().unwrap_or_default();
+        let result = function.map(|function| function(session, &url));
+
+        self.save_result(session, result, 1, started)
     }
+}
 
-    fn load_function(&self) -> Box<dyn Fn(&USSDSession, &str) -> USSDData> {
-        // Load the function from the functions_path
-        let func = FUNCTION_MAP
-            .lock()
-            .unwrap()
-            .get(&self.function_name)
-            .cloned();
+impl USSDService {
+    /// Calls the service without blocking, retrying failed calls, and saves the result in the
+    /// session data under `data_key`.
+    ///
+    /// Http services POST the session to `function_url`, async functions are awaited, and
+    /// synchronous functions are called as they are by `call`, on the runtime's blocking pool when
+    /// the service has a `timeout_ms`. A call that times out is not cancelled: an async function
+    /// or http request is dropped, but a synchronous function keeps running on the pool and its
+    /// result is discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The USSD session.
+    /// * `functions` - The functions registered in the app.
+    #[tracing::instrument(
+        name = "ussd_service",
+        skip_all,
+        fields(service = %self.function_name, kind = %self.kind)
+    )]
+    pub async fn call_async(
+        &self,
+        session: &mut USSDSession,
+        functions: &FunctionRegistry,
+    ) -> ServiceOutcome {
+        let url = self.function_url.clone().unwrap_or_default();
+        let started = Instant::now();
+
+        let mut attempts = 0;
+        let result = loop {
+            attempts += 1;
+
+            let result = match self.kind {
+                ServiceKind::Http => self.with_timeout(self.call_http(session)).await,
+                ServiceKind::Function => match functions.get_async(&self.function_name) {
+                    Some(f) => {
+                        debug!("Async function found: {}", self.function_name);
+                        self.with_timeout(async { Ok(f(session.clone(), url.clone()).await) })
+                            .await
+                    }
+                    None => match self.find_function(functions) {
+                        Ok(f) => self.call_function_blocking(f, session, &url).await,
+                        Err(e) => break Err(e),
+                    },
+                },
+            };
+
+            if !self.should_retry(&result, attempts) {
+                break result;
+            }
+            tokio::time::sleep(self.backoff(attempts)).await;
+        };
+
+        self.save_result(session, result, attempts, started)
+    }
 
-        match func {
+    /// Loads the function of the service from the app's function registry.
+    fn find_function(&self, functions: &FunctionRegistry) -> Result<FunctionHandler, ServiceError> {
+        match functions.get(&self.function_name) {
             Some(f) => {
-                info!("Function found: {}", self.function_name);
-                Box::new(f.clone())
+                debug!("Function found: {}", self.function_name);
+                Ok(f)
             }
             None => {
                 error!("Function not found: {}", self.function_name);
-                Box::new(|_session: &USSDSession, _url: &str| {
-                    let mut result = HashMap::new();
-                    result.insert(
-                        "error".to_string(),
-                        USSDData::Str("Function not found".to_string()),
-                    );
-                    USSDData::Dict(result)
-                })
+                Err(ServiceError::new(
+                    ServiceErrorKind::NotFound,
+                    "Function not found",
+                ))
+            }
+        }
+    }
+
+    /// Calls a synchronous function without blocking the worker if the service has a timeout.
+    ///
+    /// The blocking pool of the runtime is bounded, so functions that never return cannot grow
+    /// the number of threads without limit.
+    async fn call_function_blocking(
+        &self,
+        function: FunctionHandler,
+        session: &USSDSession,
+        url: &str,
+    ) -> Result<USSDData, ServiceError> {
+        if self.timeout().is_none() {
+            return Ok(function(session, url));
+        }
+
+        let session = session.clone();
+        let url = url.to_string();
+        let task = tokio::task::spawn_blocking(move || function(&session, &url));
+
+        match self.with_timeout(async { Ok(task.await) }).await? {
+            Ok(result) => Ok(result),
+            Err(_) => Err(ServiceError::new(
+                ServiceErrorKind::Failed,
+                "Function stopped without a result",
+            )),
+        }
+    }
+
+    /// Awaits `call`, failing with a timeout error if it takes longer than the service's timeout.
+    async fn with_timeout<F, T>(&self, call: F) -> Result<T, ServiceError>
+    where
+        F: Future<Output = Result<T, ServiceError>>,
+    {
+        match self.timeout() {
+            Some(timeout) => tokio::time::timeout(timeout, call)
+                .await
+                .unwrap_or_else(|_| Err(ServiceError::timeout(timeout))),
+            None => call.await,
+        }
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout_ms.map(Duration::from_millis)
+    }
+
+    /// Returns whether the service sets a timeout or retries, which only `call_async` applies.
+    pub(crate) fn has_call_policy(&self) -> bool {
+        self.timeout_ms.is_some() || self.retries > 0
+    }
+
+    /// Returns whether a call that ended with `result` after `attempts` calls is retried.
+    fn should_retry(&self, result: &Result<USSDData, ServiceError>, attempts: u32) -> bool {
+        let failed = match result {
+            Ok(data) => is_failure(data),
+            Err(_) => true,
+        };
+
+        if failed && attempts <= self.retries {
+            warning!(
+                attempt = attempts,
+                "Service {} failed, retrying",
+                self.function_name
+            );
+            return true;
+        }
+        false
+    }
+
+    /// Returns the wait before the retry following `attempts` calls.
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 1u64 << (attempts - 1).min(16);
+        Duration::from_millis(self.retry_backoff_ms.saturating_mul(factor))
+    }
+
+    /// Stores the result of the call in the session data and records the call in the metrics.
+    fn save_result(
+        &self,
+        session: &mut USSDSession,
+        result: Result<USSDData, ServiceError>,
+        attempts: u32,
+        started: Instant,
+    ) -> ServiceOutcome {
+        let (data, outcome) = match result {
+            Ok(data) if is_failure(&data) => (data, ServiceOutcome::Failed),
+            Ok(data) => (data, ServiceOutcome::Success),
+            Err(e) => {
+                error!(
+                    kind = %e.kind,
+                    attempts,
+                    "Service {} failed: {}",
+                    self.function_name,
+                    e.message
+                );
+                let outcome = match e.kind {
+                    ServiceErrorKind::Timeout => ServiceOutcome::TimedOut,
+                    _ => ServiceOutcome::Failed,
+                };
+                (e.to_data(attempts), outcome)
+            }
+        };
+
+        let failed = outcome != ServiceOutcome::Success;
+        metrics().record_service_call(&self.function_name, started.elapsed(), failed);
+
+        // Save the returned result in the session data with the data_key
+        session.data.insert(self.data_key.clone(), data);
+        outcome
+    }
+
+    /// POSTs the session to the service's `function_url` as JSON and converts the reply to `USSDData`.
+    ///
+    /// The request body holds the session id, msisdn, language and session data. A JSON reply is
+    /// converted with `json_to_hash_str_any`, any other reply is stored as a string. Replies with
+    /// a status other than a success are reported as `ServiceErrorKind::Http`.
+    ///
+    /// Calls share the http client of the worker thread, so connections to the service are reused.
+    async fn call_http(&self, session: &USSDSession) -> Result<USSDData, ServiceError> {
+        let url = match &self.function_url {
+            Some(url) => url,
+            None => {
+                error!("No function_url set for http service {}", self.data_key);
+                return Err(ServiceError::new(
+                    ServiceErrorKind::Http,
+                    "No function_url set",
+                ));
+            }
+        };
+
+        let body = json!({
+            "session_id": session.session_id,
+            "msisdn": session.msisdn,
+            "language": session.language,
+            "data": hash_str_any_to_json(USSDData::Dict(session.data.clone())),
+        });
+
+        info!(url = %url, "Calling http service");
+
+        // The service's own timeout applies instead of the client's
+        let timeout = self.timeout().unwrap_or(HTTP_TIMEOUT);
+        let request = HTTP_CLIENT.with(|client| client.post(url).timeout(timeout));
+
+        let mut response = match request.send_json(&body).await {
+            Ok(response) => response,
+            Err(SendRequestError::Timeout) => return Err(ServiceError::timeout(timeout)),
+            Err(e) => {
+                error!("Failed to call http service {}: {}", url, e);
+                return Err(ServiceError::new(ServiceErrorKind::Http, &e.to_string()));
+            }
+        };
+
+        if !response.status().is_success() {
+            error!(
+                "Http service {} responded with status {}",
+                url,
+                response.status()
+            );
+            return Err(ServiceError::new(
+                ServiceErrorKind::Http,
+                &format!("Responded with status {}", response.status()),
+            ));
+        }
+
+        match response.body().await {
+            Ok(bytes) => match serde_json::from_slice(&bytes) {
+                Ok(value) => Ok(json_to_hash_str_any(value)),
+                Err(_) => Ok(USSDData::Str(String::from_utf8_lossy(&bytes).to_string())),
+            },
+            Err(e) => {
+                error!("Failed to read http service response from {}: {}", url, e);
+                Err(ServiceError::new(ServiceErrorKind::Http, &e.to_string()))
+            }
+        }
+    }
+}
+
+/// Returns whether a service result reports a failure, with an `error` field.
+pub(crate) fn is_failure(result: &USSDData) -> bool {
+    matches!(result, USSDData::Dict(data) if data.contains_key("error"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::TcpListener,
+        sync::{Arc, Mutex},
+    };
+
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        core::{ussd_screens::USSDScreen, LocalizedText, ScreenType, USSDRequest, USSDResponse},
+        gateway,
+        testing::example_menu,
+        types::USSDFuture,
+        USSDMenu, UssdApp,
+    };
+
+    /// Routes of the http service called by the tests.
+    fn service_routes(config: &mut web::ServiceConfig) {
+        config
+            .route(
+                "/balance",
+                web::post().to(|body: web::Json<Value>| async move {
+                    HttpResponse::Ok().json(json!({"balance": "100", "msisdn": body["msisdn"]}))
+                }),
+            )
+            .route(
+                "/missing",
+                web::post().to(|| async { HttpResponse::NotFound().finish() }),
+            )
+            .route(
+                "/slow",
+                web::post().to(|| async {
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                    HttpResponse::Ok().json(json!({"balance": "100"}))
+                }),
+            );
+    }
+
+    /// Starts a local server with the service routes and the routes added by `configure`,
+    /// returning its url.
+    fn start_server<F>(configure: F) -> String
+    where
+        F: Fn(&mut web::ServiceConfig) + Clone + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let server = HttpServer::new(move || {
+            App::new()
+                .configure(service_routes)
+                .configure(configure.clone())
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_web::rt::spawn(server);
+
+        url
+    }
+
+    fn session() -> USSDSession {
+        USSDSession::new(
+            "session".to_string(),
+            "Balance".to_string(),
+            "en".to_string(),
+            "1234567890".to_string(),
+        )
+    }
+
+    fn http_service(url: &str) -> USSDService {
+        USSDService {
+            function_name: "balance".to_string(),
+            function_url: Some(url.to_string()),
+            data_key: "account".to_string(),
+            kind: ServiceKind::Http,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the string at `path` in the session data.
+    fn data_field(session: &USSDSession, path: &[&str]) -> Option<String> {
+        let mut data = session.data.get(path[0])?;
+        for key in &path[1..] {
+            data = data.as_hash_str_any()?.get(*key)?;
+        }
+        data.as_str().map(str::to_string)
+    }
+
+    fn greet(session: USSDSession, _url: String) -> USSDFuture {
+        Box::pin(async move {
+            tokio::task::yield_now().await;
+            USSDData::Str(format!("Hello {}", session.msisdn))
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_http_service() {
+        let url = start_server(|_| {});
+        let functions = FunctionRegistry::new();
+
+        let mut session = session();
+        let service = http_service(&format!("{}/balance", url));
+        assert_eq!(
+            service.call_async(&mut session, &functions).await,
+            ServiceOutcome::Success
+        );
+        assert_eq!(
+            data_field(&session, &["account", "msisdn"]),
+            Some("1234567890".to_string())
+        );
+
+        // Calls share the client of the worker
+        assert_eq!(
+            service.call_async(&mut session, &functions).await,
+            ServiceOutcome::Success
+        );
+
+        // Replies that are not a success fail the call
+        let service = http_service(&format!("{}/missing", url));
+        assert_eq!(
+            service.call_async(&mut session, &functions).await,
+            ServiceOutcome::Failed
+        );
+        assert_eq!(
+            data_field(&session, &["account", "error", "kind"]),
+            Some("http".to_string())
+        );
+        assert_eq!(
+            data_field(&session, &["account", "error", "message"]),
+            Some("Responded with status 404 Not Found".to_string())
+        );
+
+        let service = USSDService {
+            timeout_ms: Some(50),
+            ..http_service(&format!("{}/slow", url))
+        };
+        assert_eq!(
+            service.call_async(&mut session, &functions).await,
+            ServiceOutcome::TimedOut
+        );
+        assert_eq!(
+            data_field(&session, &["account", "error", "kind"]),
+            Some("timeout".to_string())
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_async_function() {
+        let mut functions = FunctionRegistry::new();
+        functions.register_async("greet", greet);
+
+        let service = USSDService {
+            function_name: "greet".to_string(),
+            data_key: "greeting".to_string(),
+            ..Default::default()
+        };
+        let mut session = session();
+        assert_eq!(
+            service.call_async(&mut session, &functions).await,
+            ServiceOutcome::Success
+        );
+        assert_eq!(
+            session.data["greeting"],
+            USSDData::Str("Hello 1234567890".to_string())
+        );
+
+        // The future of an async function can be spawned
+        let greeting = tokio::spawn(greet(session.clone(), String::new()))
+            .await
+            .unwrap();
+        assert_eq!(greeting, USSDData::Str("Hello 1234567890".to_string()));
+
+        // Without an async runtime, the call fails instead of blocking
+        assert_eq!(
+            service.call(&mut session, &functions),
+            ServiceOutcome::Failed
+        );
+        assert_eq!(
+            data_field(&session, &["greeting", "error", "kind"]),
+            Some("unsupported".to_string())
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_services_through_gateway() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let menu: USSDMenu = serde_json::from_value(json!({
+            "menus": {
+                "Start": {
+                    "text": "",
+                    "screen_type": "Initial",
+                    "default_next_screen": "FetchBalance"
+                },
+                "FetchBalance": {
+                    "text": "",
+                    "screen_type": "Function",
+                    "function": "balance",
+                    "default_next_screen": "Greet"
+                },
+                "Greet": {
+                    "text": "",
+                    "screen_type": "Function",
+                    "function": "greet",
+                    "default_next_screen": "Done"
+                },
+                "Done": {
+                    "text": "{{greeting}}, your balance is {{account.balance}}",
+                    "screen_type": "Quit",
+                    "default_next_screen": "Start"
+                }
+            },
+            "services": {
+                "balance": {
+                    "function_name": "balance",
+                    "function_url": format!("{}/balance", url),
+                    "data_key": "account",
+                    "kind": "Http"
+                },
+                "greet": {
+                    "function_name": "greet",
+                    "data_key": "greeting"
+                }
             }
+        }))
+        .unwrap();
+
+        let server = HttpServer::new(move || {
+            let mut app = UssdApp::new(true, None);
+            app.functions.register_async("greet", greet);
+
+            App::new()
+                .app_data(web::Data::new(app))
+                .app_data(web::Data::new(menu.clone()))
+                .configure(service_routes)
+                .route("/ussd", gateway::route(gateway::AfricasTalking))
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_web::rt::spawn(server);
+
+        let mut response = awc::Client::default()
+            .post(format!("{}/ussd", url))
+            .send_form(&[
+                ("sessionId", "ATUid_1"),
+                ("serviceCode", "*123#"),
+                ("phoneNumber", "1234567890"),
+                ("text", ""),
+            ])
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(
+            response.body().await.unwrap(),
+            "END Hello 1234567890, your balance is 100"
+        );
+    }
+
+    /// Buys airtime for the user's own number in the example menu, returning the last response
+    /// and the final session.
+    async fn buy_airtime(mut app: UssdApp, menu: &USSDMenu) -> (USSDResponse, USSDSession) {
+        let ended = Arc::new(Mutex::new(None));
+        let hook_session = ended.clone();
+        app.on_session_end(move |session: &USSDSession| {
+            *hook_session.lock().unwrap() = Some(session.clone());
+        });
+
+        let mut response = None;
+        for input in ["", "3", "1", "50"] {
+            let request = USSDRequest {
+                session_id: "session".to_string(),
+                msisdn: "1234567890".to_string(),
+                input: input.to_string(),
+                service_code: String::new(),
+                language: String::new(),
+            };
+            response = Some(app.run_async(request, menu.clone()).await);
         }
+
+        let session = ended
+            .lock()
+            .unwrap()
+            .take()
+            .expect("The session did not end");
+        (response.unwrap(), session)
+    }
+
+    #[test]
+    fn test_is_failure() {
+        let error = HashMap::from([("error".to_string(), USSDData::Str("down".to_string()))]);
+        assert!(is_failure(&USSDData::Dict(error)));
+        assert!(!is_failure(&USSDData::Str("error".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_service_failure_routing() {
+        // A call that does not return in time moves to the timeout screen
+        let mut slow = example_menu();
+        slow.services.get_mut("buy_airtime").unwrap().timeout_ms = Some(20);
+        let screen = slow.menus.get_mut("OwnNumberFunctionScreen").unwrap();
+        screen.on_error_screen = Some("FailureScreen".to_string());
+        screen.on_timeout_screen = Some("TimeoutScreen".to_string());
+        slow.menus.insert(
+            "TimeoutScreen".to_string(),
+            USSDScreen {
+                text: LocalizedText::from("Request timed out after {{airtime.error.attempts}} try"),
+                screen_type: ScreenType::Quit,
+                ..Default::default()
+            },
+        );
+
+        let mut app = UssdApp::new(true, None);
+        app.register_function("buy_airtime", |_session: &USSDSession, _url: &str| {
+            std::thread::sleep(Duration::from_millis(500));
+            json_to_hash_str_any(json!({"status": "success"}))
+        });
+        let (response, session) = buy_airtime(app, &slow).await;
+        assert_eq!(response.message, "Request timed out after 1 try");
+        assert_eq!(session.current_screen, "TimeoutScreen");
+        assert_eq!(
+            hash_str_any_to_json(session.data["airtime"].clone()),
+            json!({"error": {
+                "kind": "timeout",
+                "message": "Service timed out after 20ms",
+                "attempts": 1
+            }})
+        );
+
+        // Failed calls are retried before moving on
+        let calls = Arc::new(Mutex::new(0));
+        let counted = calls.clone();
+        slow.services.get_mut("buy_airtime").unwrap().retries = 2;
+        let mut app = UssdApp::new(true, None);
+        app.register_function("buy_airtime", move |_session: &USSDSession, _url: &str| {
+            let mut calls = counted.lock().unwrap();
+            *calls += 1;
+            match *calls {
+                1 => json_to_hash_str_any(json!({"error": "Busy"})),
+                _ => json_to_hash_str_any(json!({"status": "success", "message": "Sent"})),
+            }
+        });
+        let (_, session) = buy_airtime(app, &slow).await;
+        assert_eq!(session.current_screen, "SuccessScreen");
+        assert_eq!(*calls.lock().unwrap(), 2);
+
+        // Missing functions are not retried and move to the error screen
+        let (_, session) = buy_airtime(UssdApp::new(true, None), &slow).await;
+        assert_eq!(session.current_screen, "FailureScreen");
+        assert_eq!(
+            hash_str_any_to_json(session.data["airtime"].clone()),
+            json!({"error": {"kind": "not_found", "message": "Function not found", "attempts": 1}})
+        );
+    }
+
+    #[test]
+    fn test_call_runs_once() {
+        let calls = Arc::new(Mutex::new(0));
+        let counted = calls.clone();
+        let mut functions = FunctionRegistry::new();
+        functions.register("busy", move |_session: &USSDSession, _url: &str| {
+            *counted.lock().unwrap() += 1;
+            std::thread::sleep(Duration::from_millis(50));
+            json_to_hash_str_any(json!({"error": "Busy"}))
+        });
+
+        // Timeouts and retries only apply to calls made with call_async
+        let service = USSDService {
+            function_name: "busy".to_string(),
+            data_key: "result".to_string(),
+            timeout_ms: Some(10),
+            retries: 2,
+            retry_backoff_ms: 1000,
+            ..Default::default()
+        };
+        let mut session = session();
+        assert_eq!(
+            service.call(&mut session, &functions),
+            ServiceOutcome::Failed
+        );
+        assert_eq!(*calls.lock().unwrap(), 1);
+        assert_eq!(
+            data_field(&session, &["result", "error"]),
+            Some("Busy".to_string())
+        );
     }
 }
@@ -2,11 +2,19 @@ use serde::{Deserialize, Serialize};
 
 use std::{
     collections::HashMap,
-    sync::Mutex,
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex, Weak},
+    thread,
     time::{Duration, SystemTime},
 };
 
-use crate::{info, types::USSDData};
+use crate::{
+    analytics::SessionJourney,
+    debug, error, info,
+    metrics::{metrics, ScreenExit},
+    types::{ExpiryHook, SessionFuture, USSDData},
+};
 
 use super::USSDRequest;
 
@@ -26,11 +34,18 @@ use super::USSDRequest;
 /// * `data`: A `HashMap<String, USSDData>` containing session-specific data associated with keys.
 /// * `current_screen`: A string representing the identifier of the current screen within the session.
 /// * `displayed`: A `HashMap<String, bool>` indicating whether each screen has been displayed.
+/// * `menu_page`: The page of the current menu screen being displayed, starting at 0.
 /// * `visited_screens`: A vector of strings representing the screens visited during the session.
+/// * `displayed_screens`: The screens displayed to the user, in order. Going back does not remove them.
 /// * `last_interaction_time`: A `SystemTime` representing the timestamp of the last interaction with the session.
 /// * `end_session`: A boolean indicating whether the session has ended.
 /// * `language`: A string representing the language preference of the session.
 /// * `msisdn`: A string representing the mobile subscriber ISDN (MSISDN) number associated with the session.
+/// * `awaiting_resume`: A boolean indicating whether the user was asked to continue an expired session.
+/// * `started_at`: A `SystemTime` representing when the session started.
+/// * `timed_out`: A boolean indicating whether the session expired before the current request. It is not stored.
+/// * `started`: A boolean indicating whether the session started with the current request. It is not stored.
+/// * `expired_journey`: The journey of the session if it expired before the current request. It is not stored.
 ///
 /// # Derives
 ///
@@ -44,11 +59,28 @@ pub struct USSDSession {
     pub current_screen: String,
     pub error_message: Option<String>,
     pub displayed: HashMap<String, bool>,
+    #[serde(default)]
+    pub menu_page: usize,
     pub visited_screens: Vec<String>,
+    #[serde(default)]
+    pub displayed_screens: Vec<String>,
     pub last_interaction_time: SystemTime,
     pub end_session: bool,
     pub language: String,
     pub msisdn: String,
+    /// The service code the session was started with, used for requests that do not send one.
+    #[serde(default)]
+    pub service_code: String,
+    #[serde(default)]
+    pub awaiting_resume: bool,
+    #[serde(default = "SystemTime::now")]
+    pub started_at: SystemTime,
+    #[serde(skip)]
+    pub timed_out: bool,
+    #[serde(skip)]
+    pub started: bool,
+    #[serde(skip)]
+    pub expired_journey: Option<SessionJourney>,
 }
 
 impl USSDSession {
@@ -64,11 +96,19 @@ impl USSDSession {
             current_screen,
             error_message: None,
             displayed: HashMap::new(),
+            menu_page: 0,
             visited_screens: Vec::new(),
+            displayed_screens: Vec::new(),
             last_interaction_time: SystemTime::now(),
             end_session: false,
             language,
             msisdn,
+            service_code: String::new(),
+            awaiting_resume: false,
+            started_at: SystemTime::now(),
+            timed_out: false,
+            started: true,
+            expired_journey: None,
         }
     }
 
@@ -86,9 +126,18 @@ impl USSDSession {
     pub fn restart(&mut self, initial_screen: &str) {
         // clear visited screens
         self.visited_screens.clear();
+        self.displayed_screens.clear();
         self.current_screen = initial_screen.to_string();
         self.update_last_interaction_time();
         // Reset any other session-related data as needed
+        self.data.clear();
+        self.displayed.clear();
+        self.error_message = None;
+        self.menu_page = 0;
+        self.end_session = false;
+        self.awaiting_resume = false;
+        self.started_at = SystemTime::now();
+        self.started = true;
     }
 
     // Display screen history with an arrow pointing to the current screen
@@ -118,55 +167,142 @@ impl USSDSession {
     }
 
     /// Get or create a session
+    ///
+    /// A retrieved session that has been idle for longer than the settings' timeout is restarted at the
+    /// initial screen, or marked as `timed_out` so the user can be asked to continue where they left off.
+    /// A retrieved session that has already ended is restarted.
+    ///
+    /// # Errors
+    ///
+    /// `SessionError::Storage` if the session cache fails to retrieve or store the session.
     pub fn get_or_create_session(
         request: &USSDRequest,
         initial_screen: &str,
         cache: &Box<dyn SessionCache>,
-    ) -> Self {
-        let retrieved_session = USSDSession::retrieve_session(&request.session_id, &cache);
+        settings: &SessionSettings,
+    ) -> Result<Self, SessionError> {
+        if let Some(session) = cache.retrieve_session(&request.session_id)? {
+            return Ok(session.continue_session(initial_screen, settings));
+        }
 
-        match retrieved_session {
-            Ok(sesh) => {
-                // Update last interaction time for existing session
-                info!("Retrieved session {:?}", sesh);
+        let new_session = USSDSession::for_request(request, initial_screen);
 
-                let mut session = sesh;
-                session.update_last_interaction_time();
-                session
-            }
-            Err(_) => {
-                // Create new session
-                let new_session = USSDSession {
-                    session_id: request.session_id.clone(),
-                    data: HashMap::new(),
-                    current_screen: initial_screen.to_string(),
-                    error_message: None,
-                    displayed: HashMap::new(),
-                    visited_screens: Vec::new(),
-                    last_interaction_time: SystemTime::now(),
-                    end_session: false,
-                    language: request.language.clone(),
-                    msisdn: request.msisdn.clone(),
-                };
-
-                info!("New session {:?}", new_session);
-
-                new_session.store_session(&cache).unwrap();
-                new_session
+        cache.store_session_with_ttl(&new_session, settings.storage_ttl())?;
+        Ok(new_session)
+    }
+
+    /// Get or create a session without blocking the worker.
+    ///
+    /// This behaves like `get_or_create_session`, with a session cache that is awaited.
+    pub async fn get_or_create_session_async(
+        request: &USSDRequest,
+        initial_screen: &str,
+        cache: &dyn AsyncSessionCache,
+        settings: &SessionSettings,
+    ) -> Result<Self, SessionError> {
+        if let Some(session) = cache.retrieve_session(&request.session_id).await? {
+            return Ok(session.continue_session(initial_screen, settings));
+        }
+
+        let new_session = USSDSession::for_request(request, initial_screen);
+
+        cache
+            .store_session(&new_session, settings.storage_ttl())
+            .await?;
+        Ok(new_session)
+    }
+
+    /// Creates the session of a request that has no session yet.
+    fn for_request(request: &USSDRequest, initial_screen: &str) -> Self {
+        let new_session = USSDSession {
+            session_id: request.session_id.clone(),
+            data: HashMap::new(),
+            current_screen: initial_screen.to_string(),
+            error_message: None,
+            displayed: HashMap::new(),
+            menu_page: 0,
+            visited_screens: Vec::new(),
+            displayed_screens: Vec::new(),
+            last_interaction_time: SystemTime::now(),
+            end_session: false,
+            language: request.language.clone(),
+            msisdn: request.msisdn.clone(),
+            service_code: request.service_code.clone(),
+            awaiting_resume: false,
+            started_at: SystemTime::now(),
+            timed_out: false,
+            started: true,
+            expired_journey: None,
+        };
+
+        info!(screen = %new_session.current_screen, "New session");
+
+        new_session
+    }
+
+    /// Continues a retrieved session, restarting it if it ended or expired.
+    ///
+    /// An expired session is only kept for the user to resume while it has been idle for less than
+    /// the `timeout` and `resume_window` together, caches may keep it for longer.
+    fn continue_session(self, initial_screen: &str, settings: &SessionSettings) -> Self {
+        // Update last interaction time for existing session
+        debug!(screen = %self.current_screen, "Retrieved session");
+
+        let mut session = self;
+
+        if session.end_session {
+            info!("Session {} has ended, restarting", session.session_id);
+            session.restart(initial_screen);
+        } else if session.has_timed_out(settings.timeout) {
+            info!("Session {} has expired", session.session_id);
+            metrics().record_session_exit(&session.current_screen, ScreenExit::Abandoned);
+            session.expired_journey = Some(SessionJourney::from_session(&session, false));
+
+            let resumable = settings.on_expiry == SessionExpiry::Resume
+                && !session.has_timed_out(settings.timeout + settings.resume_window);
+            if resumable {
+                session.timed_out = true;
+            } else {
+                session.restart(initial_screen);
             }
         }
+
+        session.update_last_interaction_time();
+        session
     }
 
     /// Update the session with the current screen and last interaction time
-    pub fn update_session(&mut self, session_cache: &Box<dyn SessionCache>) {
+    pub fn update_session(
+        &mut self,
+        session_cache: &dyn SessionCache,
+        ttl: Duration,
+    ) -> Result<(), SessionError> {
+        self.visit_current_screen();
+
+        // Store the session
+        session_cache.store_session_with_ttl(self, ttl)?;
+        Ok(())
+    }
+
+    /// Update the session with the current screen and last interaction time without blocking the
+    /// worker.
+    pub async fn update_session_async(
+        &mut self,
+        session_cache: &dyn AsyncSessionCache,
+        ttl: Duration,
+    ) -> Result<(), SessionError> {
+        self.visit_current_screen();
+
+        // Store the session
+        session_cache.store_session(self, ttl).await
+    }
+
+    fn visit_current_screen(&mut self) {
         // Store the current screen in the session's visited screens
         self.visited_screens.push(self.current_screen.clone());
 
         // Update the session's last interaction time
         self.update_last_interaction_time();
-
-        // Store the session
-        self.store_session(&session_cache).unwrap();
     }
 
     /// Fetches an item from the session data based on the given key.
@@ -176,6 +312,95 @@ impl USSDSession {
     }
 }
 
+/// What happens to a session that has been idle for longer than the session timeout.
+///
+/// * `Restart`: The session starts again at the initial screen.
+/// * `Resume`: The user is asked whether to continue where they left off.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub enum SessionExpiry {
+    #[default]
+    Restart,
+    Resume,
+}
+
+/// Controls how long sessions live.
+///
+/// # Fields
+///
+/// * `timeout`: How long a session can be idle before it expires.
+/// * `on_expiry`: What happens when a request arrives for an expired session.
+/// * `resume_window`: How long after expiring a session can still be resumed. Only used with `SessionExpiry::Resume`.
+/// * `resume_text`: The question displayed when asking to continue an expired session.
+/// * `resume_option`: The option the user enters to continue.
+/// * `resume_label`: The label displayed next to the `resume_option`.
+/// * `restart_option`: The option the user enters to start again.
+/// * `restart_label`: The label displayed next to the `restart_option`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ussdframework::prelude::*;
+///
+/// let mut app = UssdApp::new(true, None);
+/// app.session_settings = SessionSettings {
+///     timeout: Duration::from_secs(120),
+///     on_expiry: SessionExpiry::Resume,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SessionSettings {
+    pub timeout: Duration,
+    pub on_expiry: SessionExpiry,
+    pub resume_window: Duration,
+    pub resume_text: String,
+    pub resume_option: String,
+    pub resume_label: String,
+    pub restart_option: String,
+    pub restart_label: String,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        SessionSettings {
+            timeout: Duration::from_secs(180),
+            on_expiry: SessionExpiry::Restart,
+            resume_window: Duration::from_secs(600),
+            resume_text: "Continue where you left off?".to_string(),
+            resume_option: "1".to_string(),
+            resume_label: "Yes".to_string(),
+            restart_option: "2".to_string(),
+            restart_label: "No".to_string(),
+        }
+    }
+}
+
+impl SessionSettings {
+    /// Returns how long the session cache should keep a session after its last interaction.
+    ///
+    /// Sessions that can be resumed are kept for the `resume_window` after they expire.
+    pub fn storage_ttl(&self) -> Duration {
+        match self.on_expiry {
+            SessionExpiry::Restart => self.timeout,
+            SessionExpiry::Resume => self.timeout + self.resume_window,
+        }
+    }
+
+    /// Returns the message asking the user to continue an expired session.
+    pub fn resume_message(&self) -> String {
+        format!(
+            "{}\n{}. {}\n{}. {}",
+            self.resume_text,
+            self.resume_option,
+            self.resume_label,
+            self.restart_option,
+            self.restart_label
+        )
+    }
+}
+
 /// Trait for a session cache implementation.
 ///
 /// The `SessionCache` trait defines the interface for storing and retrieving USSD (Unstructured
@@ -222,10 +447,260 @@ pub trait SessionCache: Send + Sync {
     /// or a `String` containing an error message if an error occurs during the retrieval process.
     ///
     fn retrieve_session(&self, session_id: &str) -> Result<Option<USSDSession>, String>;
+
+    /// Removes a USSD session from the cache.
+    ///
+    /// This method removes the session associated with the provided `session_id`. Removing a session
+    /// that does not exist is not an error.
+    ///
+    /// The engine calls it when a session ends. The default implementation keeps the session, which
+    /// is stored as ended, so the next request with the same session id restarts it. Caches should
+    /// override it so ended sessions do not wait for their ttl to be removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id`: A string representing the unique identifier of the session to be removed.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating whether the operation was successful. If an error occurs during the removal
+    /// process, a `String` containing an error message is returned.
+    ///
+    fn delete_session(&self, _session_id: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Stores a USSD session in the cache, to be expired once `ttl` has passed.
+    ///
+    /// The engine stores sessions with this method so that caches with native expiry, such as Redis,
+    /// can evict sessions on their own. The default implementation ignores the `ttl` and calls
+    /// `store_session`.
+    ///
+    /// # Arguments
+    ///
+    /// * `session`: A reference to the `USSDSession` object representing the session to be stored.
+    /// * `ttl`: How long the session should be kept after this call.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating whether the operation was successful.
+    ///
+    fn store_session_with_ttl(&self, session: &USSDSession, _ttl: Duration) -> Result<(), String> {
+        self.store_session(session)
+    }
+
+    /// Sets the hook called with each session the cache removes because it expired.
+    ///
+    /// `UssdApp` sets it so that sessions that are never dialled again are still counted as
+    /// abandoned and passed to its journey hook. Caches that evict expired sessions on their own,
+    /// like the built-in stores, should call the hook with every session they remove that has not
+    /// ended. The default implementation ignores the hook.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook`: The function called with each expired session.
+    ///
+    fn on_expire(&self, _hook: ExpiryHook) {}
+}
+
+/// Error raised when a session cannot be retrieved from or stored in a session cache.
+///
+/// An `AsyncSessionCache` returns it directly. The `String` errors of a `SessionCache` are
+/// converted to `Storage` errors, so both kinds of cache fail the same way.
+///
+/// * `Storage`: The session store could not be reached or failed the operation.
+/// * `Serialization`: A session could not be converted to or from its stored form.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionError {
+    Storage(String),
+    Serialization(String),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Storage(message) => write!(f, "session storage error: {}", message),
+            SessionError::Serialization(message) => {
+                write!(f, "session serialization error: {}", message)
+            }
+        }
+    }
+}
+
+impl Error for SessionError {}
+
+impl From<String> for SessionError {
+    fn from(message: String) -> Self {
+        SessionError::Storage(message)
+    }
+}
+
+impl From<serde_json::Error> for SessionError {
+    fn from(error: serde_json::Error) -> Self {
+        SessionError::Serialization(error.to_string())
+    }
+}
+
+/// Trait for a session cache that is awaited, such as a Redis or database client.
+///
+/// `process_request_async` drives the session cache of the app through this trait, so stores that
+/// make network calls do not block the worker. Methods return a `SessionFuture`, a boxed future,
+/// so the trait can be used as `dyn AsyncSessionCache`.
+///
+/// A synchronous `SessionCache` is driven through this trait by wrapping it in a `SyncSessionCache`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::{collections::HashMap, sync::Mutex, time::Duration};
+/// use ussdframework::prelude::*;
+///
+/// #[derive(Default)]
+/// struct RemoteSessions {
+///     sessions: Mutex<HashMap<String, USSDSession>>,
+/// }
+///
+/// impl AsyncSessionCache for RemoteSessions {
+///     fn store_session<'a>(&'a self, session: &'a USSDSession, _ttl: Duration) -> SessionFuture<'a, ()> {
+///         Box::pin(async move {
+///             let mut sessions = self.sessions.lock().unwrap();
+///             sessions.insert(session.session_id.clone(), session.clone());
+///             Ok(())
+///         })
+///     }
+///
+///     fn retrieve_session<'a>(&'a self, session_id: &'a str) -> SessionFuture<'a, Option<USSDSession>> {
+///         Box::pin(async move { Ok(self.sessions.lock().unwrap().get(session_id).cloned()) })
+///     }
+///
+///     fn delete_session<'a>(&'a self, session_id: &'a str) -> SessionFuture<'a, ()> {
+///         Box::pin(async move {
+///             self.sessions.lock().unwrap().remove(session_id);
+///             Ok(())
+///         })
+///     }
+/// }
+///
+/// let mut app = UssdApp::new(true, None);
+/// app.use_async_session_cache(RemoteSessions::default());
+/// ```
+pub trait AsyncSessionCache: Send + Sync {
+    /// Stores a USSD session, replacing any session with the same session ID, to be expired once
+    /// `ttl` has passed.
+    fn store_session<'a>(
+        &'a self,
+        session: &'a USSDSession,
+        ttl: Duration,
+    ) -> SessionFuture<'a, ()>;
+
+    /// Retrieves the session with the given session ID, or `None` if there is no such session.
+    fn retrieve_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> SessionFuture<'a, Option<USSDSession>>;
+
+    /// Removes the session with the given session ID. Removing a session that does not exist is
+    /// not an error.
+    fn delete_session<'a>(&'a self, session_id: &'a str) -> SessionFuture<'a, ()>;
+
+    /// Sets the hook called with each session the cache removes because it expired.
+    ///
+    /// See `SessionCache::on_expire`. The default implementation ignores the hook.
+    fn on_expire(&self, _hook: ExpiryHook) {}
+
+    /// Returns whether a session with the given session ID is stored.
+    ///
+    /// The default implementation retrieves the session.
+    fn session_exists<'a>(&'a self, session_id: &'a str) -> SessionFuture<'a, bool> {
+        Box::pin(async move { Ok(self.retrieve_session(session_id).await?.is_some()) })
+    }
+
+    /// Extends the expiry of the session with the given session ID to `ttl` from now.
+    ///
+    /// Returns `false` if there is no such session. The default implementation retrieves the
+    /// session and stores it again, stores with native expiry should override it.
+    fn touch_session<'a>(&'a self, session_id: &'a str, ttl: Duration) -> SessionFuture<'a, bool> {
+        Box::pin(async move {
+            match self.retrieve_session(session_id).await? {
+                Some(session) => {
+                    self.store_session(&session, ttl).await?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })
+    }
+}
+
+/// Drives a synchronous session cache as an `AsyncSessionCache`.
+///
+/// The synchronous methods run when the futures are polled, so the cache should not make network
+/// calls. `process_request_async` uses it for apps without an async session cache.
+///
+/// # Example
+///
+/// ```rust
+/// use ussdframework::prelude::*;
+///
+/// let app = UssdApp::new(true, None);
+/// let cache = SyncSessionCache(app.session_cache());
+/// ```
+pub struct SyncSessionCache<'c>(pub &'c dyn SessionCache);
+
+impl AsyncSessionCache for SyncSessionCache<'_> {
+    fn store_session<'a>(
+        &'a self,
+        session: &'a USSDSession,
+        ttl: Duration,
+    ) -> SessionFuture<'a, ()> {
+        Box::pin(async move { Ok(self.0.store_session_with_ttl(session, ttl)?) })
+    }
+
+    fn retrieve_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> SessionFuture<'a, Option<USSDSession>> {
+        Box::pin(async move { Ok(self.0.retrieve_session(session_id)?) })
+    }
+
+    fn delete_session<'a>(&'a self, session_id: &'a str) -> SessionFuture<'a, ()> {
+        Box::pin(async move { Ok(self.0.delete_session(session_id)?) })
+    }
+
+    fn on_expire(&self, hook: ExpiryHook) {
+        self.0.on_expire(hook)
+    }
+}
+
+/// Calls the expiry hook with a stored session that a cache removed after it expired.
+///
+/// Sessions that ended are skipped, as are sessions waiting for the user to resume them, whose
+/// expiry was reported by the request that found them expired.
+pub(crate) fn report_expired(hook: &Mutex<Option<ExpiryHook>>, stored: &str) {
+    let hook = match hook.lock().unwrap().clone() {
+        Some(hook) => hook,
+        None => return,
+    };
+
+    match serde_json::from_str::<USSDSession>(stored) {
+        Ok(session) if !session.end_session && !session.awaiting_resume => hook(&session),
+        Ok(_) => {}
+        Err(e) => error!("Failed to read expired session: {}", e),
+    }
 }
 
+/// A stored session and the time after which it expires.
+type StoredSession = (String, Option<SystemTime>);
+
+/// The default session cache, which keeps sessions in memory.
+///
+/// Sessions stored with a ttl are removed by a background sweeper thread once they expire, so
+/// memory does not grow with every session ever seen. The sweeper stops when the store is dropped.
+/// Expired sessions are passed to the expiry hook when the sweeper removes them, or when a new
+/// session with the same id replaces them.
 pub struct InMemorySessionStore {
-    data: Mutex<HashMap<String, String>>,
+    data: Arc<Mutex<HashMap<String, StoredSession>>>,
+    expiry_hook: Arc<Mutex<Option<ExpiryHook>>>,
 }
 
 // unsafe impl Send for InMemorySessionStore {}
@@ -233,8 +708,61 @@ pub struct InMemorySessionStore {
 
 impl InMemorySessionStore {
     pub fn new() -> Self {
-        Self {
-            data: Mutex::new(HashMap::new()),
+        Self::with_sweep_interval(Duration::from_secs(60))
+    }
+
+    /// Creates a store whose sweeper removes expired sessions every `interval`.
+    pub fn with_sweep_interval(interval: Duration) -> Self {
+        let data = Arc::new(Mutex::new(HashMap::new()));
+        let expiry_hook = Arc::new(Mutex::new(None));
+
+        let weak_data = Arc::downgrade(&data);
+        let sweeper_hook = expiry_hook.clone();
+        thread::spawn(move || sweep(weak_data, sweeper_hook, interval));
+
+        Self { data, expiry_hook }
+    }
+}
+
+/// Returns whether a stored session has expired.
+fn is_expired((_, expires_at): &StoredSession, now: SystemTime) -> bool {
+    matches!(expires_at, Some(expires_at) if *expires_at <= now)
+}
+
+/// Removes expired sessions every `interval` until the store is dropped, passing them to the
+/// expiry hook.
+fn sweep(
+    data: Weak<Mutex<HashMap<String, StoredSession>>>,
+    expiry_hook: Arc<Mutex<Option<ExpiryHook>>>,
+    interval: Duration,
+) {
+    loop {
+        thread::sleep(interval);
+
+        let data = match data.upgrade() {
+            Some(data) => data,
+            None => break,
+        };
+
+        let now = SystemTime::now();
+        let expired: Vec<StoredSession> = {
+            let mut data = data.lock().unwrap();
+            let expired_ids: Vec<String> = data
+                .iter()
+                .filter(|(_, stored)| is_expired(stored, now))
+                .map(|(session_id, _)| session_id.clone())
+                .collect();
+            expired_ids
+                .iter()
+                .filter_map(|session_id| data.remove(session_id))
+                .collect()
+        };
+
+        debug!("Swept {} expired sessions", expired.len());
+
+        // The hook runs without holding the lock, so requests are not blocked by it
+        for (session, _) in &expired {
+            report_expired(&expiry_hook, session);
         }
     }
 }
@@ -244,7 +772,10 @@ impl SessionCache for InMemorySessionStore {
         let mut data = self.data.lock().unwrap();
         data.insert(
             session.session_id.clone(),
-            serde_json::to_string(session).unwrap(),
+            (
+                serde_json::to_string(session).map_err(|e| e.to_string())?,
+                None,
+            ),
         );
         Ok(())
     }
@@ -252,8 +783,384 @@ impl SessionCache for InMemorySessionStore {
     fn retrieve_session(&self, session_id: &str) -> Result<Option<USSDSession>, String> {
         let data = self.data.lock().unwrap();
         match data.get(session_id) {
-            Some(session) => Ok(Some(serde_json::from_str(session).unwrap())),
+            Some((_, Some(expires_at))) if *expires_at <= SystemTime::now() => Ok(None),
+            Some((session, _)) => serde_json::from_str(session)
+                .map(Some)
+                .map_err(|e| e.to_string()),
             None => Ok(None),
         }
     }
+
+    fn delete_session(&self, session_id: &str) -> Result<(), String> {
+        let mut data = self.data.lock().unwrap();
+        data.remove(session_id);
+        Ok(())
+    }
+
+    fn store_session_with_ttl(&self, session: &USSDSession, ttl: Duration) -> Result<(), String> {
+        let stored = (
+            serde_json::to_string(session).map_err(|e| e.to_string())?,
+            Some(SystemTime::now() + ttl),
+        );
+        let replaced = self
+            .data
+            .lock()
+            .unwrap()
+            .insert(session.session_id.clone(), stored);
+
+        // A new session replacing an expired one that was not swept yet
+        if let Some(replaced) = replaced.filter(|replaced| is_expired(replaced, SystemTime::now()))
+        {
+            report_expired(&self.expiry_hook, &replaced.0);
+        }
+        Ok(())
+    }
+
+    fn on_expire(&self, hook: ExpiryHook) {
+        *self.expiry_hook.lock().unwrap() = Some(hook);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{example_menu, UnreachableCache};
+
+    fn request() -> USSDRequest {
+        USSDRequest {
+            msisdn: "1234567890".to_string(),
+            session_id: "session".to_string(),
+            input: "".to_string(),
+            service_code: "*123#".to_string(),
+            language: "en".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_in_memory_store_expiry_and_delete() {
+        let store = InMemorySessionStore::new();
+        let session = USSDSession::new(
+            "session".to_string(),
+            "Main".to_string(),
+            "en".to_string(),
+            "1234567890".to_string(),
+        );
+
+        store
+            .store_session_with_ttl(&session, Duration::from_secs(60))
+            .unwrap();
+        assert!(store.retrieve_session("session").unwrap().is_some());
+
+        store.delete_session("session").unwrap();
+        assert!(store.retrieve_session("session").unwrap().is_none());
+
+        store
+            .store_session_with_ttl(&session, Duration::from_secs(0))
+            .unwrap();
+        assert!(store.retrieve_session("session").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_expired_session_restarts() {
+        let cache: Box<dyn SessionCache> = Box::new(InMemorySessionStore::new());
+        let settings = SessionSettings::default();
+
+        let mut session = USSDSession::new(
+            "session".to_string(),
+            "Balance".to_string(),
+            "en".to_string(),
+            "1234567890".to_string(),
+        );
+        session.visited_screens.push("Main".to_string());
+        session.last_interaction_time = SystemTime::now() - Duration::from_secs(600);
+        cache.store_session(&session).unwrap();
+
+        let session =
+            USSDSession::get_or_create_session(&request(), "Start", &cache, &settings).unwrap();
+        assert_eq!(session.current_screen, "Start");
+        assert!(session.visited_screens.is_empty());
+
+        let resume = SessionSettings {
+            on_expiry: SessionExpiry::Resume,
+            ..Default::default()
+        };
+        let mut expired = session.clone();
+        expired.current_screen = "Balance".to_string();
+        expired.last_interaction_time = SystemTime::now() - Duration::from_secs(600);
+        cache.store_session(&expired).unwrap();
+
+        let session =
+            USSDSession::get_or_create_session(&request(), "Start", &cache, &resume).unwrap();
+        assert_eq!(session.current_screen, "Balance");
+        assert!(session.timed_out);
+
+        // Past the resume window, a cache that still holds the session does not make it resumable
+        expired.last_interaction_time = SystemTime::now() - Duration::from_secs(3600);
+        cache.store_session(&expired).unwrap();
+
+        let session =
+            USSDSession::get_or_create_session(&request(), "Start", &cache, &resume).unwrap();
+        assert_eq!(session.current_screen, "Start");
+        assert!(!session.timed_out);
+    }
+
+    /// A session cache that only stores and retrieves sessions.
+    #[derive(Default)]
+    struct MinimalCache {
+        sessions: Mutex<HashMap<String, USSDSession>>,
+    }
+
+    impl SessionCache for MinimalCache {
+        fn store_session(&self, session: &USSDSession) -> Result<(), String> {
+            let mut sessions = self.sessions.lock().unwrap();
+            sessions.insert(session.session_id.clone(), session.clone());
+            Ok(())
+        }
+
+        fn retrieve_session(&self, session_id: &str) -> Result<Option<USSDSession>, String> {
+            Ok(self.sessions.lock().unwrap().get(session_id).cloned())
+        }
+    }
+
+    #[test]
+    fn test_ended_session_restarts_without_delete() {
+        let app = crate::UssdApp::new(false, Some(Box::new(MinimalCache::default())));
+        let menu: crate::USSDMenu = serde_json::from_value(serde_json::json!({
+            "menus": {
+                "Start": {
+                    "text": "",
+                    "screen_type": "Initial",
+                    "default_next_screen": "Main"
+                },
+                "Main": {
+                    "text": "Main Menu",
+                    "screen_type": "Menu",
+                    "default_next_screen": "Main",
+                    "menu_items": {
+                        "Exit": {"option": "1", "display_name": "Exit", "next_screen": "Bye"}
+                    }
+                },
+                "Bye": {
+                    "text": "Goodbye",
+                    "screen_type": "Quit",
+                    "default_next_screen": "Main"
+                }
+            },
+            "services": {}
+        }))
+        .unwrap();
+
+        let exit = USSDRequest {
+            input: "1".to_string(),
+            ..request()
+        };
+        app.run(request(), menu.clone());
+        assert!(app.run(exit, menu.clone()).end_session);
+
+        // The ended session is still stored, and the next request starts again
+        assert!(
+            app.session_cache
+                .retrieve_session("session")
+                .unwrap()
+                .unwrap()
+                .end_session
+        );
+        let response = app.run(request(), menu);
+        assert!(response.message.contains("Main Menu"));
+        assert!(!response.end_session);
+    }
+
+    #[test]
+    fn test_swept_session_is_abandoned() {
+        // The registry is shared with the other tests, so only increases are checked
+        let abandoned = "ussd_screen_exits_total{screen=\"AirtimeScreen\",reason=\"abandoned\"}";
+        let sample = || -> u64 {
+            metrics()
+                .render()
+                .lines()
+                .find_map(|line| line.strip_prefix(abandoned)?.trim().parse().ok())
+                .unwrap_or(0)
+        };
+        let before = sample();
+
+        let store = InMemorySessionStore::with_sweep_interval(Duration::from_millis(10));
+        let mut app = crate::UssdApp::new(false, Some(Box::new(store)));
+        app.session_settings.timeout = Duration::from_millis(50);
+        let journeys = Arc::new(Mutex::new(Vec::new()));
+        let recorded = journeys.clone();
+        app.on_journey(move |journey: &SessionJourney| {
+            recorded.lock().unwrap().push(journey.clone())
+        });
+
+        let menu = example_menu();
+        app.run(request(), menu.clone());
+        let input = USSDRequest {
+            input: "3".to_string(),
+            ..request()
+        };
+        app.run(input, menu);
+
+        // The session is never dialled again, so only the sweeper sees it expire
+        for _ in 0..100 {
+            if !journeys.lock().unwrap().is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let journeys = journeys.lock().unwrap();
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(journeys[0].last_screen, "AirtimeScreen");
+        assert!(!journeys[0].completed);
+        assert!(sample() > before);
+        assert!(app
+            .session_cache
+            .retrieve_session("session")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_session_cache_errors() {
+        let cache: Box<dyn SessionCache> = Box::new(UnreachableCache);
+
+        let result =
+            USSDSession::get_or_create_session(&request(), "Start", &cache, &Default::default());
+        assert_eq!(
+            result,
+            Err(SessionError::Storage("connection refused".to_string()))
+        );
+    }
+
+    /// An async cache that records the sessions it stores, standing in for a networked store.
+    #[derive(Clone, Default)]
+    struct RecordingCache {
+        sessions: Arc<Mutex<HashMap<String, (USSDSession, Duration)>>>,
+    }
+
+    impl AsyncSessionCache for RecordingCache {
+        fn store_session<'a>(
+            &'a self,
+            session: &'a USSDSession,
+            ttl: Duration,
+        ) -> SessionFuture<'a, ()> {
+            Box::pin(async move {
+                let mut sessions = self.sessions.lock().unwrap();
+                sessions.insert(session.session_id.clone(), (session.clone(), ttl));
+                Ok(())
+            })
+        }
+
+        fn retrieve_session<'a>(
+            &'a self,
+            session_id: &'a str,
+        ) -> SessionFuture<'a, Option<USSDSession>> {
+            Box::pin(async move {
+                let sessions = self.sessions.lock().unwrap();
+                Ok(sessions.get(session_id).map(|(session, _)| session.clone()))
+            })
+        }
+
+        fn delete_session<'a>(&'a self, session_id: &'a str) -> SessionFuture<'a, ()> {
+            Box::pin(async move {
+                self.sessions.lock().unwrap().remove(session_id);
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_session_cache() {
+        let cache = RecordingCache::default();
+        let settings = SessionSettings::default();
+
+        let mut session =
+            USSDSession::get_or_create_session_async(&request(), "Start", &cache, &settings)
+                .await
+                .unwrap();
+        assert!(session.started);
+        assert!(cache.session_exists("session").await.unwrap());
+
+        session.current_screen = "Balance".to_string();
+        session
+            .update_session_async(&cache, Duration::from_secs(30))
+            .await
+            .unwrap();
+
+        let session =
+            USSDSession::get_or_create_session_async(&request(), "Start", &cache, &settings)
+                .await
+                .unwrap();
+        assert_eq!(session.current_screen, "Balance");
+        assert_eq!(session.visited_screens, vec!["Balance"]);
+
+        assert!(cache
+            .touch_session("session", Duration::from_secs(90))
+            .await
+            .unwrap());
+        assert_eq!(
+            cache.sessions.lock().unwrap()["session"].1,
+            Duration::from_secs(90)
+        );
+        assert!(!cache
+            .touch_session("missing", Duration::from_secs(90))
+            .await
+            .unwrap());
+
+        cache.delete_session("session").await.unwrap();
+        assert!(!cache.session_exists("session").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sync_session_cache_adapter() {
+        let store: Box<dyn SessionCache> = Box::new(InMemorySessionStore::new());
+        let cache = SyncSessionCache(store.as_ref());
+
+        let session = USSDSession::get_or_create_session_async(
+            &request(),
+            "Start",
+            &cache,
+            &Default::default(),
+        )
+        .await
+        .unwrap();
+        assert!(store.retrieve_session("session").unwrap().is_some());
+
+        cache.delete_session(&session.session_id).await.unwrap();
+        assert!(store.retrieve_session("session").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_async_with_async_session_cache() {
+        let cache = RecordingCache::default();
+        let mut app = crate::UssdApp::new(true, None);
+        app.use_async_session_cache(cache.clone());
+
+        let menu = example_menu();
+        let response = app.run_async(request(), menu.clone()).await;
+        assert!(response.message.contains("Main Menu"));
+
+        // The session is stored in the async cache, not the app's session cache
+        assert!(cache.session_exists("session").await.unwrap());
+        assert!(app
+            .session_cache
+            .retrieve_session("session")
+            .unwrap()
+            .is_none());
+
+        let response = app
+            .run_async(
+                USSDRequest {
+                    input: "3".to_string(),
+                    ..request()
+                },
+                menu,
+            )
+            .await;
+        assert!(!response.message.contains("Main Menu"));
+        assert_eq!(
+            cache.sessions.lock().unwrap()["session"].0.visited_screens,
+            vec!["MainScreen", "AirtimeScreen"]
+        );
+    }
 }
@@ -0,0 +1,457 @@
+use diesel::{
+    connection::SimpleConnection,
+    prelude::*,
+    r2d2::{ConnectionManager, CustomizeConnection, Pool},
+    table,
+};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+use std::{
+    sync::{Arc, Mutex, Weak},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{debug, error, types::ExpiryHook};
+
+use super::{ussd_session::report_expired, SessionCache, USSDSession};
+
+pub type SqlitePool = Pool<ConnectionManager<SqliteConnection>>;
+
+const SESSION_MIGRATIONS: EmbeddedMigrations = embed_migrations!("./session_migrations");
+
+table! {
+    ussd_sessions (session_id) {
+        session_id -> Text,
+        msisdn -> Text,
+        session -> Text,
+        expires_at -> Nullable<BigInt>,
+        updated_at -> BigInt,
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = ussd_sessions)]
+struct StoredSessionRow<'a> {
+    session_id: &'a str,
+    msisdn: &'a str,
+    session: String,
+    expires_at: Option<i64>,
+    updated_at: i64,
+}
+
+/// Sets up every pooled connection so that several workers can share the database file.
+///
+/// Write-ahead logging lets readers run while a session is written, and the busy timeout makes a
+/// writer wait for the lock instead of failing with `database is locked`.
+#[derive(Debug)]
+struct ConnectionOptions {
+    busy_timeout: Duration,
+}
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for ConnectionOptions {
+    fn on_acquire(&self, connection: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        connection
+            .batch_execute(&format!(
+                "PRAGMA busy_timeout = {}; PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;",
+                self.busy_timeout.as_millis()
+            ))
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+/// A session cache that persists sessions in a SQLite database.
+///
+/// Sessions are stored as JSON in the `ussd_sessions` table, which is created by the store's own
+/// migration, so they survive application restarts. The store is backed by an r2d2 pool and can
+/// be shared by all actix workers. Sessions stored with a TTL are ignored once they expire and are
+/// removed by a background sweeper, which passes them to the expiry hook. An expired session that
+/// a new session with the same id replaces before the sweeper runs is passed to the hook as well.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use ussdframework::prelude::*;
+///
+/// let store = SqliteSessionStore::new("sessions.sqlite3").unwrap();
+/// let app = UssdApp::new(false, Some(Box::new(store)));
+/// ```
+pub struct SqliteSessionStore {
+    pool: Arc<SqlitePool>,
+    expiry_hook: Arc<Mutex<Option<ExpiryHook>>>,
+}
+
+impl SqliteSessionStore {
+    /// Opens the database at `database_url` and creates the sessions table if needed.
+    pub fn new(database_url: &str) -> Result<Self, String> {
+        Self::with_sweep_interval(database_url, Duration::from_secs(60))
+    }
+
+    /// Opens the database at `database_url` with a sweeper that removes expired sessions every
+    /// `interval`.
+    pub fn with_sweep_interval(database_url: &str, interval: Duration) -> Result<Self, String> {
+        let manager = ConnectionManager::<SqliteConnection>::new(database_url);
+        let pool = Pool::builder()
+            .connection_customizer(Box::new(ConnectionOptions {
+                busy_timeout: Duration::from_secs(5),
+            }))
+            .build(manager)
+            .map_err(|e| e.to_string())?;
+
+        Self::with_pool(pool, interval)
+    }
+
+    /// Creates a store from an existing pool, running the sessions migration on it.
+    pub fn with_pool(pool: SqlitePool, interval: Duration) -> Result<Self, String> {
+        let mut connection = pool.get().map_err(|e| e.to_string())?;
+        connection
+            .run_pending_migrations(SESSION_MIGRATIONS)
+            .map_err(|e| e.to_string())?;
+
+        let pool = Arc::new(pool);
+        let expiry_hook = Arc::new(Mutex::new(None));
+
+        let weak_pool = Arc::downgrade(&pool);
+        let sweeper_hook = expiry_hook.clone();
+        thread::spawn(move || sweep(weak_pool, sweeper_hook, interval));
+
+        Ok(Self { pool, expiry_hook })
+    }
+
+    /// Removes the expired sessions, passing them to the expiry hook, and returns how many were
+    /// removed.
+    pub fn remove_expired(&self) -> Result<usize, String> {
+        remove_expired(&self.pool, &self.expiry_hook)
+    }
+
+    /// Returns the sessions of the subscriber `msisdn` that have not expired.
+    pub fn sessions_for_msisdn(&self, msisdn: &str) -> Result<Vec<USSDSession>, String> {
+        let mut connection = self.pool.get().map_err(|e| e.to_string())?;
+        let rows = ussd_sessions::table
+            .filter(ussd_sessions::msisdn.eq(msisdn))
+            .filter(
+                ussd_sessions::expires_at
+                    .is_null()
+                    .or(ussd_sessions::expires_at.gt(now())),
+            )
+            .select(ussd_sessions::session)
+            .load::<String>(&mut connection)
+            .map_err(|e| e.to_string())?;
+
+        rows.iter()
+            .map(|session| serde_json::from_str(session).map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    fn upsert(&self, session: &USSDSession, expires_at: Option<i64>) -> Result<(), String> {
+        let row = StoredSessionRow {
+            session_id: &session.session_id,
+            msisdn: &session.msisdn,
+            session: serde_json::to_string(session).map_err(|e| e.to_string())?,
+            expires_at,
+            updated_at: now(),
+        };
+
+        let mut connection = self.pool.get().map_err(|e| e.to_string())?;
+        let replaced = connection
+            .immediate_transaction::<_, diesel::result::Error, _>(|connection| {
+                let replaced = ussd_sessions::table
+                    .filter(ussd_sessions::session_id.eq(row.session_id))
+                    .filter(ussd_sessions::expires_at.le(now()))
+                    .select(ussd_sessions::session)
+                    .first::<String>(connection)
+                    .optional()?;
+                diesel::replace_into(ussd_sessions::table)
+                    .values(&row)
+                    .execute(connection)?;
+                Ok(replaced)
+            })
+            .map_err(|e| e.to_string())?;
+
+        // A new session replacing an expired one that was not swept yet
+        if let Some(replaced) = replaced {
+            report_expired(&self.expiry_hook, &replaced);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the current time in milliseconds since the Unix epoch.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn remove_expired(
+    pool: &SqlitePool,
+    expiry_hook: &Mutex<Option<ExpiryHook>>,
+) -> Result<usize, String> {
+    let mut connection = pool.get().map_err(|e| e.to_string())?;
+    let now = now();
+    let expired = connection
+        .immediate_transaction::<_, diesel::result::Error, _>(|connection| {
+            let expired = ussd_sessions::table
+                .filter(ussd_sessions::expires_at.le(now))
+                .select(ussd_sessions::session)
+                .load::<String>(connection)?;
+            diesel::delete(ussd_sessions::table.filter(ussd_sessions::expires_at.le(now)))
+                .execute(connection)?;
+            Ok(expired)
+        })
+        .map_err(|e| e.to_string())?;
+
+    for session in &expired {
+        report_expired(expiry_hook, session);
+    }
+    Ok(expired.len())
+}
+
+/// Removes expired sessions every `interval` until the store is dropped.
+fn sweep(pool: Weak<SqlitePool>, expiry_hook: Arc<Mutex<Option<ExpiryHook>>>, interval: Duration) {
+    loop {
+        thread::sleep(interval);
+
+        let pool = match pool.upgrade() {
+            Some(pool) => pool,
+            None => break,
+        };
+
+        match remove_expired(&pool, &expiry_hook) {
+            Ok(removed) => debug!("Swept {} expired sessions", removed),
+            Err(e) => error!("Failed to sweep expired sessions: {}", e),
+        }
+    }
+}
+
+impl SessionCache for SqliteSessionStore {
+    fn store_session(&self, session: &USSDSession) -> Result<(), String> {
+        self.upsert(session, None)
+    }
+
+    fn retrieve_session(&self, session_id: &str) -> Result<Option<USSDSession>, String> {
+        let mut connection = self.pool.get().map_err(|e| e.to_string())?;
+        let session = ussd_sessions::table
+            .filter(ussd_sessions::session_id.eq(session_id))
+            .filter(
+                ussd_sessions::expires_at
+                    .is_null()
+                    .or(ussd_sessions::expires_at.gt(now())),
+            )
+            .select(ussd_sessions::session)
+            .first::<String>(&mut connection)
+            .optional()
+            .map_err(|e| e.to_string())?;
+
+        session
+            .map(|session| serde_json::from_str(&session).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    fn delete_session(&self, session_id: &str) -> Result<(), String> {
+        let mut connection = self.pool.get().map_err(|e| e.to_string())?;
+        diesel::delete(ussd_sessions::table.filter(ussd_sessions::session_id.eq(session_id)))
+            .execute(&mut connection)
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn store_session_with_ttl(&self, session: &USSDSession, ttl: Duration) -> Result<(), String> {
+        self.upsert(session, Some(now() + ttl.as_millis() as i64))
+    }
+
+    fn on_expire(&self, hook: ExpiryHook) {
+        *self.expiry_hook.lock().unwrap() = Some(hook);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+
+    fn database(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "ussd-sessions-{}-{}.sqlite3",
+            std::process::id(),
+            name
+        ));
+        remove_database(&path);
+        path
+    }
+
+    fn remove_database(path: &Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    fn session(session_id: &str) -> USSDSession {
+        USSDSession::new(
+            session_id.to_string(),
+            "Main".to_string(),
+            "en".to_string(),
+            "1234567890".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_sqlite_store_persists_sessions() {
+        let path = database("persist");
+        let url = path.to_str().unwrap();
+
+        {
+            let store = SqliteSessionStore::new(url).unwrap();
+            let mut session = session("persisted");
+            session.current_screen = "Balance".to_string();
+            store.store_session(&session).unwrap();
+            store.store_session(&session).unwrap();
+        }
+
+        // A new store on the same file sees the sessions of the previous one
+        let store = SqliteSessionStore::new(url).unwrap();
+        let session = store.retrieve_session("persisted").unwrap().unwrap();
+        assert_eq!(session.current_screen, "Balance");
+        assert_eq!(store.sessions_for_msisdn("1234567890").unwrap().len(), 1);
+
+        store.delete_session("persisted").unwrap();
+        assert!(store.retrieve_session("persisted").unwrap().is_none());
+
+        drop(store);
+        remove_database(&path);
+    }
+
+    #[test]
+    fn test_sqlite_store_expiry() {
+        let path = database("expiry");
+        let store = SqliteSessionStore::new(path.to_str().unwrap()).unwrap();
+
+        store
+            .store_session_with_ttl(&session("live"), Duration::from_secs(60))
+            .unwrap();
+        store
+            .store_session_with_ttl(&session("expired"), Duration::from_secs(0))
+            .unwrap();
+
+        assert!(store.retrieve_session("live").unwrap().is_some());
+        assert!(store.retrieve_session("expired").unwrap().is_none());
+        assert_eq!(store.remove_expired().unwrap(), 1);
+
+        drop(store);
+        remove_database(&path);
+    }
+
+    #[test]
+    fn test_sqlite_store_reports_expired_sessions() {
+        let path = database("expired-hook");
+        let store = SqliteSessionStore::new(path.to_str().unwrap()).unwrap();
+        let expired = Arc::new(Mutex::new(Vec::new()));
+        let recorded = expired.clone();
+        store.on_expire(Arc::new(move |session: &USSDSession| {
+            recorded.lock().unwrap().push(session.session_id.clone())
+        }));
+
+        store
+            .store_session_with_ttl(&session("swept"), Duration::from_secs(0))
+            .unwrap();
+        store
+            .store_session_with_ttl(&session("replaced"), Duration::from_secs(0))
+            .unwrap();
+        let mut ended = session("ended");
+        ended.end_session = true;
+        store
+            .store_session_with_ttl(&ended, Duration::from_secs(0))
+            .unwrap();
+
+        // A new session with the same id replaces the expired one
+        store
+            .store_session_with_ttl(&session("replaced"), Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(*expired.lock().unwrap(), vec!["replaced"]);
+
+        assert_eq!(store.remove_expired().unwrap(), 2);
+        assert_eq!(*expired.lock().unwrap(), vec!["replaced", "swept"]);
+
+        drop(store);
+        remove_database(&path);
+    }
+
+    #[test]
+    fn test_sqlite_store_shares_database_with_builder() {
+        // The menu builder records its migrations in the same `__diesel_schema_migrations` table
+        const BUILDER_MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
+
+        for (name, builder_first) in [("builder-first", true), ("store-first", false)] {
+            let path = database(name);
+            let url = path.to_str().unwrap();
+            let pool = Pool::builder()
+                .max_size(1)
+                .build(ConnectionManager::<SqliteConnection>::new(url))
+                .unwrap();
+
+            let run_builder_migrations = || {
+                pool.get()
+                    .unwrap()
+                    .run_pending_migrations(BUILDER_MIGRATIONS)
+                    .unwrap();
+            };
+
+            if builder_first {
+                run_builder_migrations();
+            }
+            let store =
+                SqliteSessionStore::with_pool(pool.clone(), Duration::from_secs(60)).unwrap();
+            if !builder_first {
+                run_builder_migrations();
+            }
+
+            store.store_session(&session("shared")).unwrap();
+            assert!(store.retrieve_session("shared").unwrap().is_some());
+
+            // Every builder migration ran, including the latest ones
+            let mut connection = pool.get().unwrap();
+            assert!(!connection
+                .has_pending_migration(BUILDER_MIGRATIONS)
+                .unwrap());
+            connection
+                .batch_execute("SELECT timeout_ms, retries FROM services")
+                .unwrap();
+            drop(connection);
+
+            drop(store);
+            remove_database(&path);
+        }
+    }
+
+    #[test]
+    fn test_sqlite_store_concurrent_workers() {
+        let path = database("concurrent");
+        let store = Arc::new(SqliteSessionStore::new(path.to_str().unwrap()).unwrap());
+
+        let workers: Vec<_> = (0..4)
+            .map(|worker| {
+                let store = store.clone();
+                thread::spawn(move || {
+                    for request in 0..10 {
+                        let session = session(&format!("session-{}-{}", worker, request));
+                        store.store_session(&session).unwrap();
+                        assert!(store
+                            .retrieve_session(&session.session_id)
+                            .unwrap()
+                            .is_some());
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert_eq!(store.sessions_for_msisdn("1234567890").unwrap().len(), 40);
+
+        drop(store);
+        remove_database(&path);
+    }
+}
@@ -0,0 +1,967 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Display;
+
+use crate::utils::{router_condition, Expression, FunctionRegistry, Template};
+
+use super::{
+    ussd_input::compile_pattern,
+    ussd_screens::{USSDMenuItems, USSDScreen},
+    LocalizedText, ScreenType, ServiceKind, USSDMenu,
+};
+
+/// Represents a problem found while statically validating a `USSDMenu`.
+///
+/// Each variant carries enough context (screen, service, option) to point the menu author
+/// at the offending entry in the menu file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MenuValidationError {
+    /// A screen points at a screen that does not exist in the menu.
+    DanglingScreenReference {
+        screen: String,
+        field: String,
+        target: String,
+    },
+    /// The menu has no `Initial` screen.
+    MissingInitialScreen,
+    /// The menu has more than one `Initial` screen for the same service code.
+    MultipleInitialScreens(Vec<String>),
+    /// No path leads from the `Initial` screen to this screen.
+    UnreachableScreen(String),
+    /// A `Function` screen does not name a service in its `function` field.
+    MissingFunction { screen: String },
+    /// A `Function` screen names a service that is not defined in `services`.
+    MissingService { screen: String, service: String },
+    /// A service refers to a function that has not been registered.
+    UnregisteredFunction {
+        service: String,
+        function_name: String,
+    },
+    /// A service calling a synchronous function sets a timeout or retries, which `UssdApp::run`
+    /// ignores. Menus only processed with `run_async` can leave it unfixed.
+    IgnoredCallPolicy { service: String },
+    /// Two menu items on the same screen share an option.
+    DuplicateMenuOption { screen: String, option: String },
+    /// A menu item option is not a positive number.
+    NonNumericMenuOption { screen: String, option: String },
+    /// A router option's expression does not parse.
+    InvalidExpression {
+        screen: String,
+        expression: String,
+        error: String,
+    },
+    /// A screen's text does not parse as a template.
+    InvalidTemplate { screen: String, error: String },
+    /// An `Input` screen's validation pattern is not a valid regular expression.
+    InvalidPattern {
+        screen: String,
+        pattern: String,
+        error: String,
+    },
+    /// `Initial`, `Function` and `Router` screens that can route to each other in a circle
+    /// without displaying a message, so a session could run through them forever.
+    NonDisplayingCycle(Vec<String>),
+}
+
+impl Display for MenuValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MenuValidationError::DanglingScreenReference {
+                screen,
+                field,
+                target,
+            } => write!(
+                f,
+                "screen '{}' references unknown screen '{}' in {}",
+                screen, target, field
+            ),
+            MenuValidationError::MissingInitialScreen => write!(f, "no Initial screen found"),
+            MenuValidationError::MultipleInitialScreens(screens) => {
+                write!(f, "multiple Initial screens found: {}", screens.join(", "))
+            }
+            MenuValidationError::UnreachableScreen(screen) => {
+                write!(
+                    f,
+                    "screen '{}' is unreachable from the Initial screen",
+                    screen
+                )
+            }
+            MenuValidationError::MissingFunction { screen } => {
+                write!(f, "Function screen '{}' has no function set", screen)
+            }
+            MenuValidationError::MissingService { screen, service } => write!(
+                f,
+                "Function screen '{}' calls unknown service '{}'",
+                screen, service
+            ),
+            MenuValidationError::UnregisteredFunction {
+                service,
+                function_name,
+            } => write!(
+                f,
+                "service '{}' uses unregistered function '{}'",
+                service, function_name
+            ),
+            MenuValidationError::IgnoredCallPolicy { service } => write!(
+                f,
+                "service '{}' sets a timeout or retries, which run ignores",
+                service
+            ),
+            MenuValidationError::DuplicateMenuOption { screen, option } => {
+                write!(
+                    f,
+                    "screen '{}' has duplicate menu option '{}'",
+                    screen, option
+                )
+            }
+            MenuValidationError::NonNumericMenuOption { screen, option } => write!(
+                f,
+                "screen '{}' has non-numeric menu option '{}'",
+                screen, option
+            ),
+            MenuValidationError::InvalidExpression {
+                screen,
+                expression,
+                error,
+            } => write!(
+                f,
+                "screen '{}' has invalid expression '{}': {}",
+                screen, expression, error
+            ),
+            MenuValidationError::InvalidTemplate { screen, error } => {
+                write!(f, "screen '{}' has invalid text: {}", screen, error)
+            }
+            MenuValidationError::InvalidPattern {
+                screen,
+                pattern,
+                error,
+            } => write!(
+                f,
+                "screen '{}' has invalid input pattern '{}': {}",
+                screen, pattern, error
+            ),
+            MenuValidationError::NonDisplayingCycle(screens) => write!(
+                f,
+                "screens {} route in a circle without displaying a message",
+                screens.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MenuValidationError {}
+
+impl USSDMenu {
+    /// Statically validates the menu graph.
+    ///
+    /// This method checks the menu for problems that would otherwise only show up at runtime:
+    /// dangling screen references, a missing `Initial` screen or more than one for a service code,
+    /// unreachable screens, `Function` screens without a matching service, services whose function
+    /// has not been registered, services whose timeout and retries `run` ignores, duplicate or
+    /// non-numeric menu options, router options whose expression does not parse, screen texts that
+    /// do not parse as templates, input validation patterns that do not compile and screens that
+    /// route in a circle without displaying a message.
+    ///
+    /// Services are checked against the global function maps only. To check them against the
+    /// functions registered in a `UssdApp`, use `UssdApp::validate_menu` instead.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the menu is valid, or a list of every `MenuValidationError` found.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ussdframework::prelude::*;
+    ///
+    /// let menu = USSDMenu::new();
+    ///
+    /// if let Err(errors) = menu.validate() {
+    ///     for error in errors {
+    ///         println!("{}", error);
+    ///     }
+    /// }
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<MenuValidationError>> {
+        self.validate_with_functions(&FunctionRegistry::new())
+    }
+
+    /// Statically validates the menu graph, checking services against the `functions` registry.
+    ///
+    /// See `validate` for the problems reported.
+    pub fn validate_with_functions(
+        &self,
+        functions: &FunctionRegistry,
+    ) -> Result<(), Vec<MenuValidationError>> {
+        let mut errors = Vec::new();
+
+        // Sort screens by name so the reported errors are stable between runs
+        let mut screen_names: Vec<&String> = self.menus.keys().collect();
+        screen_names.sort();
+
+        // Initial screens
+        let initial_screens: Vec<String> = screen_names
+            .iter()
+            .filter(|name| self.menus[**name].screen_type == ScreenType::Initial)
+            .map(|name| name.to_string())
+            .collect();
+
+        if initial_screens.is_empty() {
+            errors.push(MenuValidationError::MissingInitialScreen);
+        }
+
+        // Each service code, and the screens without one, may have a single initial screen
+        let mut initial_screens_by_code: Vec<(Option<&String>, Vec<String>)> = Vec::new();
+        for name in initial_screens.iter() {
+            let service_code = self.menus[name].service_code.as_ref();
+            match initial_screens_by_code
+                .iter_mut()
+                .find(|(code, _)| *code == service_code)
+            {
+                Some((_, names)) => names.push(name.clone()),
+                None => initial_screens_by_code.push((service_code, vec![name.clone()])),
+            }
+        }
+
+        for (_, names) in initial_screens_by_code {
+            if names.len() > 1 {
+                errors.push(MenuValidationError::MultipleInitialScreens(names));
+            }
+        }
+
+        // Screen references, functions and menu options
+        for name in screen_names.iter() {
+            let screen = &self.menus[*name];
+
+            for (field, target) in screen_references(screen) {
+                if !self.menus.contains_key(&target) {
+                    errors.push(MenuValidationError::DanglingScreenReference {
+                        screen: name.to_string(),
+                        field,
+                        target,
+                    });
+                }
+            }
+
+            if screen.screen_type == ScreenType::Function {
+                match &screen.function {
+                    Some(service) if self.services.contains_key(service) => {}
+                    Some(service) => errors.push(MenuValidationError::MissingService {
+                        screen: name.to_string(),
+                        service: service.clone(),
+                    }),
+                    None => errors.push(MenuValidationError::MissingFunction {
+                        screen: name.to_string(),
+                    }),
+                }
+            }
+
+            let pattern = screen
+                .input_validation
+                .as_ref()
+                .and_then(|validation| validation.pattern.as_ref());
+            if let Some(pattern) = pattern {
+                if let Err(error) = compile_pattern(pattern) {
+                    errors.push(MenuValidationError::InvalidPattern {
+                        screen: name.to_string(),
+                        pattern: pattern.clone(),
+                        error,
+                    });
+                }
+            }
+
+            if let Some(menu_items) = &screen.menu_items {
+                let mut options: Vec<&String> =
+                    menu_items.values().map(|item| &item.option).collect();
+                options.sort();
+
+                let mut seen = HashSet::new();
+                for option in options {
+                    if !matches!(option.parse::<usize>(), Ok(o) if o > 0) {
+                        errors.push(MenuValidationError::NonNumericMenuOption {
+                            screen: name.to_string(),
+                            option: option.clone(),
+                        });
+                    }
+                    if !seen.insert(option) {
+                        errors.push(MenuValidationError::DuplicateMenuOption {
+                            screen: name.to_string(),
+                            option: option.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        // Error screens, displayed when a request fails
+        let error_screens = [
+            ("errors.menu.screen", &self.errors.menu.screen),
+            ("errors.session.screen", &self.errors.session.screen),
+        ];
+        for (field, target) in error_screens.iter() {
+            match target {
+                Some(target) if !self.menus.contains_key(target) => {
+                    errors.push(MenuValidationError::DanglingScreenReference {
+                        screen: "errors".to_string(),
+                        field: field.to_string(),
+                        target: target.clone(),
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        errors.extend(self.expression_errors());
+
+        for cycle in self.non_displaying_cycles() {
+            errors.push(MenuValidationError::NonDisplayingCycle(cycle));
+        }
+
+        // Unreachable screens, only meaningful when there is an initial screen to start from
+        if !initial_screens.is_empty() {
+            let mut start = initial_screens.clone();
+            start.extend(
+                error_screens
+                    .iter()
+                    .filter_map(|(_, target)| (*target).clone()),
+            );
+            let reachable = self.reachable_screens(&start);
+
+            for name in screen_names.iter() {
+                if !reachable.contains(name.as_str()) {
+                    errors.push(MenuValidationError::UnreachableScreen(name.to_string()));
+                }
+            }
+        }
+
+        // Services and registered functions, http services do not call a function
+        let mut service_names: Vec<&String> = self.services.keys().collect();
+        service_names.sort();
+
+        for name in service_names {
+            let service = &self.services[name];
+            if service.kind != ServiceKind::Http && !functions.contains(&service.function_name) {
+                errors.push(MenuValidationError::UnregisteredFunction {
+                    service: name.clone(),
+                    function_name: service.function_name.clone(),
+                });
+            }
+
+            // Http services and async functions can only be called with run_async
+            if service.kind != ServiceKind::Http
+                && !functions.is_async(&service.function_name)
+                && service.has_call_policy()
+            {
+                errors.push(MenuValidationError::IgnoredCallPolicy {
+                    service: name.clone(),
+                });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Parses the text of every screen and the expression of every router option, and returns
+    /// the ones that do not parse.
+    ///
+    /// The parsed texts, dynamic menus and router options are kept on their screens, so they are
+    /// not parsed again when a session reaches them. Menus loaded with `load_from_json` or
+    /// `_load_from_config` are checked when they load.
+    pub fn expression_errors(&self) -> Vec<MenuValidationError> {
+        let mut errors = Vec::new();
+
+        let mut screen_names: Vec<&String> = self.menus.keys().collect();
+        screen_names.sort();
+
+        for name in screen_names {
+            for text in localized_texts(&self.menus[name].text) {
+                let parsed = self.menus[name]
+                    .templates
+                    .get_or_parse(text, Template::parse);
+                if let Err(e) = parsed {
+                    errors.push(MenuValidationError::InvalidTemplate {
+                        screen: name.clone(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+
+            if let Some(dynamic_menu) = &self.menus[name].dynamic_menu {
+                let expressions = std::iter::once(&dynamic_menu.items).chain(&dynamic_menu.value);
+                for expression in expressions {
+                    let parsed = dynamic_menu
+                        .expressions
+                        .get_or_parse(expression, Expression::parse);
+                    if let Err(e) = parsed {
+                        errors.push(MenuValidationError::InvalidExpression {
+                            screen: name.clone(),
+                            expression: expression.clone(),
+                            error: e.to_string(),
+                        });
+                    }
+                }
+
+                for display in localized_texts(&dynamic_menu.display) {
+                    let parsed = dynamic_menu
+                        .templates
+                        .get_or_parse(display, Template::parse);
+                    if let Err(e) = parsed {
+                        errors.push(MenuValidationError::InvalidTemplate {
+                            screen: name.clone(),
+                            error: e.to_string(),
+                        });
+                    }
+                }
+            }
+
+            let Some(router_options) = &self.menus[name].router_options else {
+                continue;
+            };
+
+            for option in router_options {
+                let Some(expression) = router_condition(&option.router_option) else {
+                    continue;
+                };
+
+                let parsed = self.menus[name]
+                    .expressions
+                    .get_or_parse(expression, Expression::parse);
+                if let Err(e) = parsed {
+                    errors.push(MenuValidationError::InvalidExpression {
+                        screen: name.clone(),
+                        expression: expression.to_string(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Returns the groups of screens that run without displaying a message and can route to each
+    /// other in a circle, each sorted by name.
+    fn non_displaying_cycles(&self) -> Vec<Vec<String>> {
+        let runs_without_input = |name: &String| {
+            self.menus.get(name).is_some_and(|screen| {
+                matches!(
+                    screen.screen_type,
+                    ScreenType::Initial | ScreenType::Function | ScreenType::Router
+                )
+            })
+        };
+
+        let mut names: Vec<&String> = self
+            .menus
+            .keys()
+            .filter(|n| runs_without_input(n))
+            .collect();
+        names.sort();
+
+        // The screens each screen reaches through screens that run without input
+        let reached = |start: &String| {
+            let mut reached: HashSet<String> = HashSet::new();
+            let mut queue: VecDeque<&String> = VecDeque::from([start]);
+
+            while let Some(name) = queue.pop_front() {
+                for (_, target) in screen_references(&self.menus[name]) {
+                    if runs_without_input(&target) && reached.insert(target.clone()) {
+                        queue.push_back(self.menus.get_key_value(&target).unwrap().0);
+                    }
+                }
+            }
+            reached
+        };
+        let reached: Vec<HashSet<String>> = names.iter().map(|name| reached(name)).collect();
+
+        let mut cycles: Vec<Vec<String>> = Vec::new();
+        for (index, name) in names.iter().enumerate() {
+            if !reached[index].contains(*name) || cycles.iter().any(|c| c.contains(*name)) {
+                continue;
+            }
+
+            // The screens on a circle through this one reach it back
+            let cycle: Vec<String> = names
+                .iter()
+                .enumerate()
+                .filter(|(other, other_name)| {
+                    reached[index].contains(**other_name) && reached[*other].contains(*name)
+                })
+                .map(|(_, other_name)| other_name.to_string())
+                .collect();
+            cycles.push(cycle);
+        }
+
+        cycles
+    }
+
+    /// Walks the menu graph from the given screens and returns every screen that can be reached.
+    fn reachable_screens(&self, start: &[String]) -> HashSet<String> {
+        let mut reachable: HashSet<String> = HashSet::new();
+        let mut queue: VecDeque<String> = start.iter().cloned().collect();
+
+        while let Some(name) = queue.pop_front() {
+            if !reachable.insert(name.clone()) {
+                continue;
+            }
+
+            if let Some(screen) = self.menus.get(&name) {
+                for (_, target) in screen_references(screen) {
+                    if self.menus.contains_key(&target) && !reachable.contains(&target) {
+                        queue.push_back(target);
+                    }
+                }
+            }
+        }
+
+        reachable
+    }
+}
+
+/// Returns the text of every language, sorted so the reported errors are stable between runs.
+fn localized_texts(text: &LocalizedText) -> Vec<&String> {
+    let mut texts: Vec<&String> = match text {
+        LocalizedText::Text(text) => vec![text],
+        LocalizedText::Translations(translations) => translations.values().collect(),
+    };
+    texts.sort();
+    texts
+}
+
+/// Collects every screen a screen can route to, paired with the field that holds the reference.
+///
+/// An empty `default_next_screen` on a `Quit` screen is not treated as a reference, since the
+/// session has ended by the time it would be followed.
+fn screen_references(screen: &USSDScreen) -> Vec<(String, String)> {
+    let mut references = Vec::new();
+
+    if !(screen.screen_type == ScreenType::Quit && screen.default_next_screen.is_empty()) {
+        references.push((
+            "default_next_screen".to_string(),
+            screen.default_next_screen.clone(),
+        ));
+    }
+
+    if let Some(menu_items) = &screen.menu_items {
+        let mut items: Vec<(&String, &USSDMenuItems)> = menu_items.iter().collect();
+        items.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (item_name, item) in items {
+            references.push((
+                format!("menu_items.{}.next_screen", item_name),
+                item.next_screen.clone(),
+            ));
+        }
+    }
+
+    if let Some(dynamic_menu) = &screen.dynamic_menu {
+        references.push((
+            "dynamic_menu.next_screen".to_string(),
+            dynamic_menu.next_screen.clone(),
+        ));
+    }
+
+    if let Some(router_options) = &screen.router_options {
+        for (index, option) in router_options.iter().enumerate() {
+            references.push((
+                format!("router_options[{}].next_screen", index),
+                option.next_screen.clone(),
+            ));
+        }
+    }
+
+    if let Some(confirm) = &screen.confirm {
+        if let Some(accept_screen) = &confirm.accept_screen {
+            references.push(("confirm.accept_screen".to_string(), accept_screen.clone()));
+        }
+        if let Some(reject_screen) = &confirm.reject_screen {
+            references.push(("confirm.reject_screen".to_string(), reject_screen.clone()));
+        }
+    }
+
+    if let Some(on_error_screen) = &screen.on_error_screen {
+        references.push(("on_error_screen".to_string(), on_error_screen.clone()));
+    }
+
+    if let Some(on_timeout_screen) = &screen.on_timeout_screen {
+        references.push(("on_timeout_screen".to_string(), on_timeout_screen.clone()));
+    }
+
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        ussd_screens::{USSDMenuItems, USSDRouterOption, USSDScreen},
+        InputValidation, USSDConfirm, USSDDynamicMenu, USSDService,
+    };
+    use std::collections::HashMap;
+
+    fn screen(screen_type: ScreenType, default_next_screen: &str) -> USSDScreen {
+        USSDScreen {
+            text: "text".into(),
+            screen_type,
+            default_next_screen: default_next_screen.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn valid_menu() -> USSDMenu {
+        let mut menu = USSDMenu::new();
+        menu.menus
+            .insert("Start".to_string(), screen(ScreenType::Initial, "Main"));
+
+        let mut main = screen(ScreenType::Menu, "End");
+        let mut items = HashMap::new();
+        items.insert(
+            "EndOption".to_string(),
+            USSDMenuItems {
+                option: "1".to_string(),
+                display_name: "End".into(),
+                next_screen: "End".to_string(),
+                language: None,
+            },
+        );
+        main.menu_items = Some(items);
+        menu.menus.insert("Main".to_string(), main);
+        menu.menus
+            .insert("End".to_string(), screen(ScreenType::Quit, ""));
+        menu
+    }
+
+    #[test]
+    fn test_validate_valid_menu() {
+        assert_eq!(valid_menu().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_missing_initial_screen() {
+        let mut menu = valid_menu();
+        menu.menus.remove("Start");
+
+        let errors = menu.validate().unwrap_err();
+        assert!(errors.contains(&MenuValidationError::MissingInitialScreen));
+    }
+
+    #[test]
+    fn test_validate_multiple_initial_screens() {
+        let mut menu = valid_menu();
+        menu.menus
+            .insert("Other".to_string(), screen(ScreenType::Initial, "Main"));
+
+        let errors = menu.validate().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![MenuValidationError::MultipleInitialScreens(vec![
+                "Other".to_string(),
+                "Start".to_string()
+            ])]
+        );
+
+        // Initial screens for different service codes do not conflict
+        menu.menus.get_mut("Other").unwrap().service_code = Some("*456#".to_string());
+        assert_eq!(menu.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_dangling_and_unreachable_screens() {
+        let mut menu = valid_menu();
+        menu.menus
+            .insert("Orphan".to_string(), screen(ScreenType::Input, "Missing"));
+        menu.menus.get_mut("Orphan").unwrap().on_timeout_screen = Some("TimedOut".to_string());
+        menu.menus.get_mut("Orphan").unwrap().confirm = Some(USSDConfirm {
+            reject_screen: Some("Rejected".to_string()),
+            ..Default::default()
+        });
+
+        let errors = menu.validate().unwrap_err();
+        assert!(
+            errors.contains(&MenuValidationError::DanglingScreenReference {
+                screen: "Orphan".to_string(),
+                field: "default_next_screen".to_string(),
+                target: "Missing".to_string(),
+            })
+        );
+        assert!(
+            errors.contains(&MenuValidationError::DanglingScreenReference {
+                screen: "Orphan".to_string(),
+                field: "on_timeout_screen".to_string(),
+                target: "TimedOut".to_string(),
+            })
+        );
+        assert!(
+            errors.contains(&MenuValidationError::DanglingScreenReference {
+                screen: "Orphan".to_string(),
+                field: "confirm.reject_screen".to_string(),
+                target: "Rejected".to_string(),
+            })
+        );
+        assert!(errors.contains(&MenuValidationError::UnreachableScreen(
+            "Orphan".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_validate_error_screens() {
+        let mut menu = valid_menu();
+        menu.menus
+            .insert("Unavailable".to_string(), screen(ScreenType::Quit, ""));
+        menu.errors.menu.screen = Some("Unavailable".to_string());
+        assert_eq!(menu.validate(), Ok(()));
+
+        menu.errors.session.screen = Some("Missing".to_string());
+        assert_eq!(
+            menu.validate(),
+            Err(vec![MenuValidationError::DanglingScreenReference {
+                screen: "errors".to_string(),
+                field: "errors.session.screen".to_string(),
+                target: "Missing".to_string(),
+            }])
+        );
+    }
+
+    #[test]
+    fn test_validate_menu_options() {
+        let mut menu = valid_menu();
+        let items = menu
+            .menus
+            .get_mut("Main")
+            .unwrap()
+            .menu_items
+            .as_mut()
+            .unwrap();
+        items.insert(
+            "Duplicate".to_string(),
+            USSDMenuItems {
+                option: "1".to_string(),
+                display_name: "Again".into(),
+                next_screen: "End".to_string(),
+                language: None,
+            },
+        );
+        items.insert(
+            "Letter".to_string(),
+            USSDMenuItems {
+                option: "a".to_string(),
+                display_name: "Letter".into(),
+                next_screen: "End".to_string(),
+                language: None,
+            },
+        );
+
+        let errors = menu.validate().unwrap_err();
+        assert!(errors.contains(&MenuValidationError::DuplicateMenuOption {
+            screen: "Main".to_string(),
+            option: "1".to_string(),
+        }));
+        assert!(errors.contains(&MenuValidationError::NonNumericMenuOption {
+            screen: "Main".to_string(),
+            option: "a".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_validate_expressions() {
+        let mut menu = valid_menu();
+        let mut router = screen(ScreenType::Router, "Main");
+        router.router_options = Some(vec![
+            USSDRouterOption {
+                router_option: "{{amount > 900 && status == 'ok'}}".to_string(),
+                next_screen: "End".to_string(),
+            },
+            USSDRouterOption {
+                router_option: "{{amount >}}".to_string(),
+                next_screen: "End".to_string(),
+            },
+        ]);
+        menu.menus.insert("Router".to_string(), router);
+        menu.menus.get_mut("Start").unwrap().default_next_screen = "Router".to_string();
+
+        let errors = menu.validate().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![MenuValidationError::InvalidExpression {
+                screen: "Router".to_string(),
+                expression: "amount >".to_string(),
+                error: "Expected a value, found end of expression at position 8".to_string(),
+            }]
+        );
+
+        menu.menus.get_mut("Router").unwrap().router_options = None;
+        menu.menus.get_mut("Main").unwrap().text = "{{#if amount > 5}}Main".into();
+        assert_eq!(
+            menu.validate(),
+            Err(vec![MenuValidationError::InvalidTemplate {
+                screen: "Main".to_string(),
+                error: "Unclosed {{#if}} at position 0".to_string(),
+            }])
+        );
+    }
+
+    #[test]
+    fn test_validate_dynamic_menu() {
+        let mut menu = valid_menu();
+        menu.menus.get_mut("Main").unwrap().dynamic_menu = Some(USSDDynamicMenu {
+            items: "accounts[".to_string(),
+            display: "{{name | shout}}".into(),
+            value: None,
+            selection_key: "account".to_string(),
+            next_screen: "Missing".to_string(),
+            ..Default::default()
+        });
+
+        let errors = menu.validate().unwrap_err();
+        assert!(
+            errors.contains(&MenuValidationError::DanglingScreenReference {
+                screen: "Main".to_string(),
+                field: "dynamic_menu.next_screen".to_string(),
+                target: "Missing".to_string(),
+            })
+        );
+        assert!(errors.contains(&MenuValidationError::InvalidTemplate {
+            screen: "Main".to_string(),
+            error: "Unknown filter 'shout' at position 0".to_string(),
+        }));
+        assert!(errors.iter().any(|error| matches!(
+            error,
+            MenuValidationError::InvalidExpression { expression, .. } if expression == "accounts["
+        )));
+    }
+
+    #[test]
+    fn test_validate_input_pattern() {
+        let mut menu = valid_menu();
+        let mut input = screen(ScreenType::Input, "End");
+        input.input_validation = Some(InputValidation {
+            pattern: Some("^[A-Z{3}$".to_string()),
+            ..Default::default()
+        });
+        menu.menus.insert("Input".to_string(), input);
+        menu.menus.get_mut("Start").unwrap().default_next_screen = "Input".to_string();
+        menu.menus.get_mut("Input").unwrap().default_next_screen = "Main".to_string();
+
+        let errors = menu.validate().unwrap_err();
+        assert!(matches!(
+            errors.as_slice(),
+            [MenuValidationError::InvalidPattern { screen, pattern, .. }]
+                if screen == "Input" && pattern == "^[A-Z{3}$"
+        ));
+    }
+
+    #[test]
+    fn test_validate_non_displaying_cycles() {
+        let mut menu = valid_menu();
+        menu.menus.get_mut("Start").unwrap().default_next_screen = "Check".to_string();
+
+        // A router that routes back to itself
+        let mut check = screen(ScreenType::Router, "Check");
+        check.router_options = Some(vec![USSDRouterOption {
+            router_option: "{{ready}}".to_string(),
+            next_screen: "Main".to_string(),
+        }]);
+        menu.menus.insert("Check".to_string(), check);
+        assert_eq!(
+            menu.validate(),
+            Err(vec![MenuValidationError::NonDisplayingCycle(vec![
+                "Check".to_string()
+            ])])
+        );
+
+        // A router and a function screen that route to each other
+        let mut call = screen(ScreenType::Function, "Main");
+        call.function = Some("lookup".to_string());
+        call.on_error_screen = Some("Check".to_string());
+        menu.menus.insert("Call".to_string(), call);
+        menu.menus.get_mut("Check").unwrap().default_next_screen = "Call".to_string();
+        menu.services.insert(
+            "lookup".to_string(),
+            USSDService {
+                kind: crate::core::ServiceKind::Http,
+                data_key: "result".to_string(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            menu.validate(),
+            Err(vec![MenuValidationError::NonDisplayingCycle(vec![
+                "Call".to_string(),
+                "Check".to_string()
+            ])])
+        );
+
+        // A circle through a screen that displays a message waits for the user
+        menu.menus.get_mut("Call").unwrap().on_error_screen = Some("Main".to_string());
+        menu.menus.get_mut("Main").unwrap().default_next_screen = "Check".to_string();
+        assert_eq!(menu.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_functions_and_services() {
+        let mut menu = valid_menu();
+        let mut function_screen = screen(ScreenType::Function, "End");
+        function_screen.function = Some("unknown_service".to_string());
+        menu.menus.insert("Call".to_string(), function_screen);
+        menu.menus.get_mut("Start").unwrap().default_next_screen = "Call".to_string();
+        menu.services.insert(
+            "validation_service".to_string(),
+            USSDService {
+                function_name: "validation_unregistered_function".to_string(),
+                data_key: "result".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let errors = menu.validate().unwrap_err();
+        assert!(errors.contains(&MenuValidationError::MissingService {
+            screen: "Call".to_string(),
+            service: "unknown_service".to_string(),
+        }));
+        assert!(errors.contains(&MenuValidationError::UnregisteredFunction {
+            service: "validation_service".to_string(),
+            function_name: "validation_unregistered_function".to_string(),
+        }));
+
+        // Timeouts and retries of synchronous functions are ignored by run
+        let service = menu.services.get_mut("validation_service").unwrap();
+        service.retries = 2;
+        let errors = menu.validate().unwrap_err();
+        assert!(errors.contains(&MenuValidationError::IgnoredCallPolicy {
+            service: "validation_service".to_string(),
+        }));
+
+        let service = menu.services.get_mut("validation_service").unwrap();
+        service.kind = ServiceKind::Http;
+        let errors = menu.validate().unwrap_err();
+        assert!(!errors.contains(&MenuValidationError::IgnoredCallPolicy {
+            service: "validation_service".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_validate_with_functions() {
+        let mut menu = valid_menu();
+        menu.services.insert(
+            "validation_service".to_string(),
+            USSDService {
+                function_name: "validation_registry_function".to_string(),
+                data_key: "result".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let mut functions = FunctionRegistry::new();
+        functions.register(
+            "validation_registry_function",
+            |_session: &crate::core::USSDSession, _url: &str| crate::types::USSDData::None,
+        );
+
+        assert_eq!(menu.validate_with_functions(&functions), Ok(()));
+        assert!(menu.validate().is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::{USSDMenu, USSDRequest, USSDResponse},
+    UssdApp,
+};
+
+use super::GatewayAdapter;
+
+/// Adapter for the Africa's Talking USSD gateway.
+///
+/// Requests are form-encoded `AfricasTalkingRequest`s, and replies are plain text starting with
+/// `CON` or `END`.
+pub struct AfricasTalking;
+
+impl GatewayAdapter for AfricasTalking {
+    fn parse_request(&self, _request: &HttpRequest, body: &[u8]) -> Result<USSDRequest, String> {
+        let body = std::str::from_utf8(body).map_err(|e| e.to_string())?;
+        let form =
+            web::Query::<AfricasTalkingRequest>::from_query(body).map_err(|e| e.to_string())?;
+
+        Ok(form.to_ussd_request())
+    }
+
+    fn render_response(
+        &self,
+        _request: &HttpRequest,
+        _body: &[u8],
+        response: &USSDResponse,
+    ) -> HttpResponse {
+        HttpResponse::Ok()
+            .content_type("text/plain")
+            .body(format_response(response))
+    }
+}
+
+/// A request sent by the Africa's Talking USSD gateway.
+///
+/// Africa's Talking POSTs the request form-encoded. The `text` holds every input of the session
+/// so far joined by `*`, for example `1*2*500` after three inputs, and is empty on the first
+/// request of a session.
+///
+/// # Fields
+///
+/// * `session_id`: The unique identifier of the session.
+/// * `service_code`: The USSD code dialled by the user.
+/// * `phone_number`: The phone number of the user.
+/// * `text`: The inputs of the session so far, joined by `*`.
+/// * `network_code`: The code of the user's mobile network.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AfricasTalkingRequest {
+    pub session_id: String,
+    pub service_code: String,
+    pub phone_number: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub network_code: Option<String>,
+}
+
+impl AfricasTalkingRequest {
+    /// Converts the request into a `USSDRequest`, keeping only the latest input.
+    ///
+    /// The inputs of `text` are also appended to the service code, as in `*384*123*1*2#`. The first
+    /// request of a session replays them like any extended dial string, since Africa's Talking
+    /// sends the inputs dialled with the code in its `text`. Later requests continue the session
+    /// with the latest input.
+    ///
+    /// Africa's Talking does not send the user's language, so the menu's default language is used.
+    pub fn to_ussd_request(&self) -> USSDRequest {
+        USSDRequest {
+            session_id: self.session_id.clone(),
+            msisdn: self.phone_number.clone(),
+            input: latest_input(&self.text),
+            service_code: dial_string(&self.service_code, &self.text),
+            language: String::new(),
+        }
+    }
+}
+
+/// Returns the latest input from the `*`-joined inputs of a session.
+///
+/// # Example
+///
+/// ```
+/// use ussdframework::gateway::africastalking::latest_input;
+///
+/// assert_eq!(latest_input("1*2*500"), "500");
+/// assert_eq!(latest_input(""), "");
+/// ```
+pub fn latest_input(text: &str) -> String {
+    text.rsplit('*').next().unwrap_or_default().to_string()
+}
+
+/// Appends the `*`-joined inputs of a session to the dialled service code.
+///
+/// # Example
+///
+/// ```
+/// use ussdframework::gateway::africastalking::dial_string;
+///
+/// assert_eq!(dial_string("*384*123#", "1*2"), "*384*123*1*2#");
+/// assert_eq!(dial_string("*384*123#", ""), "*384*123#");
+/// ```
+pub fn dial_string(service_code: &str, text: &str) -> String {
+    match service_code.trim().strip_suffix('#') {
+        Some(code) if !text.is_empty() => format!("{}*{}#", code, text),
+        _ => service_code.to_string(),
+    }
+}
+
+/// Formats the response as Africa's Talking expects it.
+///
+/// Messages that continue the session start with `CON`, and messages that end it start with `END`.
+pub fn format_response(response: &USSDResponse) -> String {
+    let prefix = if response.end_session { "END" } else { "CON" };
+    format!("{} {}", prefix, response.message)
+}
+
+/// Actix handler for Africa's Talking USSD callbacks.
+///
+/// This is the same as mounting the `AfricasTalking` adapter with `gateway::route`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use actix_web::{web, App, HttpServer};
+/// use ussdframework::gateway::africastalking;
+/// use ussdframework::prelude::*;
+///
+/// #[actix_web::main]
+/// async fn main() -> std::io::Result<()> {
+///     HttpServer::new(move || {
+///         let app = UssdApp::new(true, None);
+///         let menus = USSDMenu::load_from_json("menu.json").unwrap();
+///
+///         App::new()
+///             .app_data(web::Data::new(app))
+///             .app_data(web::Data::new(menus))
+///             .route("/ussd/africastalking", web::post().to(africastalking::handle_ussd))
+///     })
+///     .bind("127.0.0.1:3000")?
+///     .run()
+///     .await
+/// }
+/// ```
+pub async fn handle_ussd(
+    form: web::Form<AfricasTalkingRequest>,
+    app: web::Data<UssdApp>,
+    menus: web::Data<USSDMenu>,
+) -> HttpResponse {
+    let request = form.into_inner().to_ussd_request();
+
+    let response = app.run_async(request, menus.get_ref()).await;
+
+    HttpResponse::Ok()
+        .content_type("text/plain")
+        .body(format_response(&response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_ussd_request_takes_latest_input() {
+        let request: AfricasTalkingRequest = serde_json::from_value(serde_json::json!({
+            "sessionId": "ATUid_1",
+            "serviceCode": "*384*123#",
+            "phoneNumber": "+254711000000",
+            "text": "1*2*500",
+        }))
+        .unwrap();
+
+        let ussd_request = request.to_ussd_request();
+        assert_eq!(ussd_request.session_id, "ATUid_1");
+        assert_eq!(ussd_request.msisdn, "+254711000000");
+        assert_eq!(ussd_request.service_code, "*384*123*1*2*500#");
+        assert_eq!(ussd_request.input, "500");
+
+        assert_eq!(latest_input(""), "");
+        assert_eq!(latest_input("1"), "1");
+        assert_eq!(dial_string("*384*123#", ""), "*384*123#");
+    }
+
+    #[test]
+    fn test_inputs_dialled_with_the_code() {
+        let menu: USSDMenu = serde_json::from_value(serde_json::json!({
+            "menus": {
+                "Start": {
+                    "text": "",
+                    "screen_type": "Initial",
+                    "default_next_screen": "Main",
+                    "service_code": "*384*123#"
+                },
+                "Main": {
+                    "text": "Main",
+                    "screen_type": "Menu",
+                    "default_next_screen": "Main",
+                    "menu_items": {
+                        "Buy": { "option": "1", "display_name": "Buy", "next_screen": "Amount" }
+                    }
+                },
+                "Amount": {
+                    "text": "Amount",
+                    "screen_type": "Input",
+                    "default_next_screen": "Bought",
+                    "input_identifier": "amount"
+                },
+                "Bought": {
+                    "text": "Bought {{amount}}",
+                    "screen_type": "Quit",
+                    "default_next_screen": ""
+                }
+            },
+            "services": {}
+        }))
+        .unwrap();
+        let request = |session_id: &str, text: &str| {
+            AfricasTalkingRequest {
+                session_id: session_id.to_string(),
+                service_code: "*384*123#".to_string(),
+                phone_number: "+254711000000".to_string(),
+                text: text.to_string(),
+                network_code: None,
+            }
+            .to_ussd_request()
+        };
+        let app = UssdApp::new(true, None);
+
+        // Inputs dialled with the code are all replayed on the first request
+        assert_eq!(
+            app.run(request("ATUid_1", "1*50"), &menu).message,
+            "Bought 50"
+        );
+
+        // Later requests take the latest input
+        assert_eq!(
+            app.run(request("ATUid_2", ""), &menu).message,
+            "Main\n1. Buy"
+        );
+        assert_eq!(app.run(request("ATUid_2", "1"), &menu).message, "Amount");
+        assert_eq!(
+            app.run(request("ATUid_2", "1*50"), &menu).message,
+            "Bought 50"
+        );
+    }
+
+    #[test]
+    fn test_format_response() {
+        let mut response = USSDResponse {
+            msisdn: "+254711000000".to_string(),
+            session_id: "ATUid_1".to_string(),
+            end_session: false,
+            message: "Main Menu\n1. Balance".to_string(),
+        };
+        assert_eq!(format_response(&response), "CON Main Menu\n1. Balance");
+
+        response.end_session = true;
+        response.message = "Goodbye".to_string();
+        assert_eq!(format_response(&response), "END Goodbye");
+    }
+}
@@ -0,0 +1,138 @@
+use actix_web::{HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{USSDRequest, USSDResponse};
+
+use super::{parse_json, GatewayAdapter};
+
+/// A request sent by the Arkesel USSD gateway.
+///
+/// # Fields
+///
+/// * `session_id`: The unique identifier of the session.
+/// * `user_id`: The identifier of the Arkesel account, echoed back in the reply.
+/// * `new_session`: Whether this is the first request of a session.
+/// * `msisdn`: The phone number of the user.
+/// * `user_data`: The dialled code on a new session, the user's input otherwise.
+/// * `network`: The user's mobile network.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArkeselRequest {
+    #[serde(rename = "sessionID")]
+    pub session_id: String,
+    #[serde(rename = "userID")]
+    pub user_id: String,
+    pub new_session: bool,
+    pub msisdn: String,
+    #[serde(default)]
+    pub user_data: String,
+    #[serde(default)]
+    pub network: Option<String>,
+}
+
+/// A reply to the Arkesel USSD gateway.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArkeselResponse {
+    #[serde(rename = "sessionID")]
+    pub session_id: String,
+    #[serde(rename = "userID")]
+    pub user_id: String,
+    pub msisdn: String,
+    pub message: String,
+    pub continue_session: bool,
+}
+
+/// Adapter for the Arkesel USSD gateway, which sends and expects JSON.
+pub struct Arkesel;
+
+impl GatewayAdapter for Arkesel {
+    fn parse_request(&self, _request: &HttpRequest, body: &[u8]) -> Result<USSDRequest, String> {
+        let request: ArkeselRequest = parse_json(body)?;
+
+        // The user data of a new session is the dialled code, not an input
+        let (input, service_code) = if request.new_session {
+            (String::new(), request.user_data)
+        } else {
+            // The engine uses the code the session started with
+            (request.user_data, String::new())
+        };
+
+        Ok(USSDRequest {
+            session_id: request.session_id,
+            msisdn: request.msisdn,
+            input,
+            service_code,
+            language: String::new(),
+        })
+    }
+
+    fn render_response(
+        &self,
+        _request: &HttpRequest,
+        body: &[u8],
+        response: &USSDResponse,
+    ) -> HttpResponse {
+        let request: ArkeselRequest = parse_json(body).unwrap_or_default();
+
+        HttpResponse::Ok().json(ArkeselResponse {
+            session_id: response.session_id.clone(),
+            user_id: request.user_id,
+            msisdn: response.msisdn.clone(),
+            message: response.message.clone(),
+            continue_session: !response.end_session,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn test_arkesel_parse_request() {
+        let request = TestRequest::default().to_http_request();
+        let body = br#"{"sessionID": "a1", "userID": "acme", "newSession": true, "msisdn": "233200000000", "userData": "*920*55#", "network": "MTN"}"#;
+
+        let ussd_request = Arkesel.parse_request(&request, body).unwrap();
+        assert_eq!(ussd_request.session_id, "a1");
+        assert_eq!(ussd_request.msisdn, "233200000000");
+        assert_eq!(ussd_request.service_code, "*920*55#");
+        assert_eq!(ussd_request.input, "");
+
+        let body = br#"{"sessionID": "a1", "userID": "acme", "newSession": false, "msisdn": "233200000000", "userData": "2", "network": "MTN"}"#;
+        let ussd_request = Arkesel.parse_request(&request, body).unwrap();
+        assert_eq!(ussd_request.service_code, "");
+        assert_eq!(ussd_request.input, "2");
+
+        assert!(Arkesel.parse_request(&request, b"not json").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_arkesel_render_response() {
+        let request = TestRequest::default().to_http_request();
+        let body = br#"{"sessionID": "a1", "userID": "acme", "newSession": false, "msisdn": "233200000000", "userData": "2", "network": "MTN"}"#;
+
+        let response = USSDResponse {
+            msisdn: "233200000000".to_string(),
+            session_id: "a1".to_string(),
+            end_session: true,
+            message: "Goodbye".to_string(),
+        };
+        let reply = Arkesel.render_response(&request, body, &response);
+        let bytes = actix_web::body::to_bytes(reply.into_body()).await.unwrap();
+        let reply: ArkeselResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(
+            reply,
+            ArkeselResponse {
+                session_id: "a1".to_string(),
+                user_id: "acme".to_string(),
+                msisdn: "233200000000".to_string(),
+                message: "Goodbye".to_string(),
+                continue_session: false,
+            }
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use actix_web::{HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{USSDRequest, USSDResponse};
+
+use super::{parse_json, GatewayAdapter};
+
+/// A request sent by the Hubtel USSD gateway.
+///
+/// # Fields
+///
+/// * `session_id`: The unique identifier of the session.
+/// * `mobile`: The phone number of the user.
+/// * `service_code`: The number of the USSD code dialled by the user, such as `713`.
+/// * `message_type`: `Initiation` on the first request of a session, `Response` after that, and
+///   `Release` or `Timeout` when the session was closed by the user or the network.
+/// * `message`: The full dial string on `Initiation`, such as `*713*1#`, the user's input otherwise.
+/// * `operator`: The user's mobile network.
+/// * `sequence`: The number of the request within the session.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HubtelRequest {
+    pub session_id: String,
+    pub mobile: String,
+    #[serde(default)]
+    pub service_code: String,
+    #[serde(rename = "Type")]
+    pub message_type: String,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub operator: Option<String>,
+    #[serde(default)]
+    pub sequence: Option<u32>,
+}
+
+/// A reply to the Hubtel USSD gateway.
+///
+/// The `message_type` is `response` to continue the session and `release` to end it.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HubtelResponse {
+    pub session_id: String,
+    #[serde(rename = "Type")]
+    pub message_type: String,
+    pub message: String,
+    pub label: String,
+    pub data_type: String,
+    pub field_type: String,
+}
+
+/// Adapter for the Hubtel USSD gateway, which sends and expects JSON.
+pub struct Hubtel;
+
+impl GatewayAdapter for Hubtel {
+    fn parse_request(&self, _request: &HttpRequest, body: &[u8]) -> Result<USSDRequest, String> {
+        let request: HubtelRequest = parse_json(body)?;
+
+        // The message of the first request is the dial string, not an input
+        let (input, service_code) = if request.message_type == "Initiation" {
+            (String::new(), request.message)
+        } else {
+            // The engine uses the code the session started with
+            (request.message, String::new())
+        };
+
+        Ok(USSDRequest {
+            session_id: request.session_id,
+            msisdn: request.mobile,
+            input,
+            service_code,
+            language: String::new(),
+        })
+    }
+
+    fn render_response(
+        &self,
+        _request: &HttpRequest,
+        _body: &[u8],
+        response: &USSDResponse,
+    ) -> HttpResponse {
+        let (message_type, data_type) = if response.end_session {
+            ("release", "display")
+        } else {
+            ("response", "input")
+        };
+
+        HttpResponse::Ok().json(HubtelResponse {
+            session_id: response.session_id.clone(),
+            message_type: message_type.to_string(),
+            message: response.message.clone(),
+            label: String::new(),
+            data_type: data_type.to_string(),
+            field_type: "text".to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+    use crate::{gateway::handle_request, testing::example_menu, UssdApp};
+
+    #[test]
+    fn test_hubtel_initiation_has_no_input() {
+        let request = TestRequest::default().to_http_request();
+        let body = br#"{"Type": "Initiation", "Mobile": "233200000000", "SessionId": "h1", "ServiceCode": "713", "Message": "*713#", "Sequence": 1}"#;
+
+        let ussd_request = Hubtel.parse_request(&request, body).unwrap();
+        assert_eq!(ussd_request.session_id, "h1");
+        assert_eq!(ussd_request.msisdn, "233200000000");
+        assert_eq!(ussd_request.service_code, "*713#");
+        assert_eq!(ussd_request.input, "");
+
+        let body = br#"{"Type": "Response", "Mobile": "233200000000", "SessionId": "h1", "ServiceCode": "713", "Message": "2", "Sequence": 2}"#;
+        let ussd_request = Hubtel.parse_request(&request, body).unwrap();
+        assert_eq!(ussd_request.service_code, "");
+        assert_eq!(ussd_request.input, "2");
+    }
+
+    #[actix_web::test]
+    async fn test_hubtel_dispatch_on_dial_string() {
+        let mut menu = example_menu();
+        menu.menus.get_mut("InitialScreen").unwrap().service_code = Some("*123#".to_string());
+        let mut airtime_start = menu.menus["InitialScreen"].clone();
+        airtime_start.service_code = Some("*713#".to_string());
+        airtime_start.default_next_screen = "AirtimeScreen".to_string();
+        menu.menus.insert("AirtimeStart".to_string(), airtime_start);
+
+        let app = UssdApp::new(false, None);
+        let request = TestRequest::default().to_http_request();
+        let reply = |body: &'static [u8]| {
+            let (app, menu, request) = (&app, &menu, &request);
+            async move {
+                let reply = handle_request(&Hubtel, request, body, app, menu).await;
+                let bytes = actix_web::body::to_bytes(reply.into_body()).await.unwrap();
+                serde_json::from_slice::<HubtelResponse>(&bytes).unwrap()
+            }
+        };
+
+        let body = br#"{"Type": "Initiation", "Mobile": "233200000000", "SessionId": "h1", "ServiceCode": "713", "Message": "*713#", "Sequence": 1}"#;
+        assert!(reply(body).await.message.starts_with("Select option"));
+        let body = br#"{"Type": "Response", "Mobile": "233200000000", "SessionId": "h1", "ServiceCode": "713", "Message": "1", "Sequence": 2}"#;
+        assert_eq!(reply(body).await.message, "Enter amount");
+
+        // Inputs in the dial string are replayed
+        let body = br#"{"Type": "Initiation", "Mobile": "233200000000", "SessionId": "h2", "ServiceCode": "713", "Message": "*713*1#", "Sequence": 1}"#;
+        let response = reply(body).await;
+        assert_eq!(response.message, "Enter amount");
+        assert_eq!(response.message_type, "response");
+    }
+}
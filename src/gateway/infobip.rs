@@ -0,0 +1,136 @@
+use actix_web::{HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{USSDRequest, USSDResponse};
+
+use super::{parse_json, GatewayAdapter};
+
+/// A request sent by the Infobip USSD gateway.
+///
+/// Infobip calls `/session/{sessionId}/start` when a session starts and `/session/{sessionId}/response`
+/// for every input after that. The session id is read from the path when the body does not hold it.
+///
+/// # Fields
+///
+/// * `session_id`: The unique identifier of the session.
+/// * `msisdn`: The phone number of the user.
+/// * `short_code`: The USSD code dialled by the user, sent when the session starts.
+/// * `text`: The full dial string when the session starts, the user's input otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InfobipRequest {
+    #[serde(default)]
+    pub session_id: Option<String>,
+    pub msisdn: String,
+    #[serde(default)]
+    pub short_code: Option<String>,
+    #[serde(default)]
+    pub text: String,
+}
+
+/// A reply to the Infobip USSD gateway.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InfobipResponse {
+    pub should_close: bool,
+    pub ussd_menu: String,
+    pub response_exit_code: u16,
+    pub response_message: String,
+}
+
+/// Adapter for the Infobip USSD gateway, which sends and expects JSON.
+pub struct Infobip;
+
+impl GatewayAdapter for Infobip {
+    fn parse_request(&self, request: &HttpRequest, body: &[u8]) -> Result<USSDRequest, String> {
+        let body: InfobipRequest = parse_json(body)?;
+        let is_start = request.path().ends_with("/start");
+
+        let session_id = match body.session_id {
+            Some(session_id) => session_id,
+            None => session_id_from_path(request.path())
+                .ok_or_else(|| "No session id in the request".to_string())?,
+        };
+
+        // The text of the first request is the dial string, not an input
+        let (input, service_code) = if is_start {
+            let dial_string = if body.text.is_empty() {
+                body.short_code.unwrap_or_default()
+            } else {
+                body.text
+            };
+            (String::new(), dial_string)
+        } else {
+            // The engine uses the code the session started with
+            (body.text, String::new())
+        };
+
+        Ok(USSDRequest {
+            session_id,
+            msisdn: body.msisdn,
+            input,
+            service_code,
+            language: String::new(),
+        })
+    }
+
+    fn render_response(
+        &self,
+        _request: &HttpRequest,
+        _body: &[u8],
+        response: &USSDResponse,
+    ) -> HttpResponse {
+        HttpResponse::Ok().json(InfobipResponse {
+            should_close: response.end_session,
+            ussd_menu: response.message.clone(),
+            response_exit_code: 200,
+            response_message: String::new(),
+        })
+    }
+}
+
+/// Returns the path segment following `session`, as in `/session/{sessionId}/start`.
+fn session_id_from_path(path: &str) -> Option<String> {
+    let mut segments = path.split('/');
+    segments.find(|segment| *segment == "session")?;
+    segments
+        .next()
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| segment.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn test_infobip_session_id_from_path() {
+        let request = TestRequest::post()
+            .uri("/ussd/infobip/session/ib-1/response")
+            .to_http_request();
+        let body = br#"{"msisdn": "385911234567", "text": "1"}"#;
+
+        let ussd_request = Infobip.parse_request(&request, body).unwrap();
+        assert_eq!(ussd_request.session_id, "ib-1");
+        assert_eq!(ussd_request.input, "1");
+        assert_eq!(ussd_request.service_code, "");
+    }
+
+    #[test]
+    fn test_infobip_start_keeps_the_dial_string() {
+        let request = TestRequest::post()
+            .uri("/ussd/infobip/session/ib-1/start")
+            .to_http_request();
+        let body = br#"{"msisdn": "385911234567", "shortCode": "*123#", "text": "*123*3*1#"}"#;
+
+        let ussd_request = Infobip.parse_request(&request, body).unwrap();
+        assert_eq!(ussd_request.service_code, "*123*3*1#");
+        assert_eq!(ussd_request.input, "");
+
+        let body = br#"{"msisdn": "385911234567", "shortCode": "*123#"}"#;
+        let ussd_request = Infobip.parse_request(&request, body).unwrap();
+        assert_eq!(ussd_request.service_code, "*123#");
+    }
+}
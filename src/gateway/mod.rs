@@ -0,0 +1,189 @@
+//! Adapters between USSD gateways and the USSD application.
+//!
+//! Each gateway sends requests and expects replies in its own format. An adapter turns the
+//! gateway's request into a `USSDRequest`, and the `USSDResponse` back into the gateway's reply.
+//! Adapters ship with ready-made actix handlers, which expect the `UssdApp` and `USSDMenu` to be
+//! registered as app data.
+
+pub mod africastalking;
+pub mod arkesel;
+pub mod hubtel;
+pub mod infobip;
+pub mod nalo;
+pub mod xml;
+
+use std::sync::Arc;
+
+use actix_web::{web, HttpRequest, HttpResponse, Route};
+
+use crate::{
+    core::{USSDMenu, USSDRequest, USSDResponse},
+    error, UssdApp,
+};
+
+pub use africastalking::AfricasTalking;
+pub use arkesel::Arkesel;
+pub use hubtel::Hubtel;
+pub use infobip::Infobip;
+pub use nalo::Nalo;
+pub use xml::XmlFreeflow;
+
+/// Trait for a USSD gateway adapter.
+///
+/// The `GatewayAdapter` trait defines how the requests of a gateway are read and how its replies
+/// are written. Implement it to support a gateway that is not built in, and mount it with `route`.
+///
+/// # Example
+///
+/// ```rust
+/// use actix_web::{HttpRequest, HttpResponse};
+/// use ussdframework::gateway::GatewayAdapter;
+/// use ussdframework::prelude::*;
+///
+/// struct PlainText;
+///
+/// impl GatewayAdapter for PlainText {
+///     fn parse_request(&self, request: &HttpRequest, body: &[u8]) -> Result<USSDRequest, String> {
+///         let header = |name: &str| {
+///             request
+///                 .headers()
+///                 .get(name)
+///                 .and_then(|value| value.to_str().ok())
+///                 .unwrap_or_default()
+///                 .to_string()
+///         };
+///
+///         Ok(USSDRequest {
+///             session_id: header("X-Session-Id"),
+///             msisdn: header("X-Msisdn"),
+///             service_code: header("X-Service-Code"),
+///             input: String::from_utf8_lossy(body).to_string(),
+///             language: String::new(),
+///         })
+///     }
+///
+///     fn render_response(
+///         &self,
+///         _request: &HttpRequest,
+///         _body: &[u8],
+///         response: &USSDResponse,
+///     ) -> HttpResponse {
+///         HttpResponse::Ok().body(response.message.clone())
+///     }
+/// }
+/// ```
+pub trait GatewayAdapter: Send + Sync {
+    /// Parses the gateway's HTTP request into a `USSDRequest`.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: The HTTP request, giving access to its headers, path and query string.
+    /// * `body`: The body of the HTTP request.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing either the `USSDRequest`, or a `String` describing why the request
+    /// could not be parsed.
+    ///
+    fn parse_request(&self, request: &HttpRequest, body: &[u8]) -> Result<USSDRequest, String>;
+
+    /// Renders the `USSDResponse` into the gateway's HTTP reply.
+    ///
+    /// The original request is passed along for gateways that expect fields of the request to be
+    /// echoed back.
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: The HTTP request the response answers.
+    /// * `body`: The body of the HTTP request.
+    /// * `response`: The response of the USSD application.
+    ///
+    fn render_response(
+        &self,
+        request: &HttpRequest,
+        body: &[u8],
+        response: &USSDResponse,
+    ) -> HttpResponse;
+}
+
+/// Handles a gateway request with the given adapter.
+///
+/// Requests that cannot be parsed are answered with `400 Bad Request`.
+///
+/// # Arguments
+///
+/// * `adapter` - The adapter of the gateway that sent the request.
+/// * `request` - The HTTP request.
+/// * `body` - The body of the HTTP request.
+/// * `app` - The USSD application.
+/// * `menus` - The USSD menu screens.
+///
+/// # Returns
+///
+/// The gateway's HTTP reply.
+pub async fn handle_request<A: GatewayAdapter + ?Sized>(
+    adapter: &A,
+    request: &HttpRequest,
+    body: &[u8],
+    app: &UssdApp,
+    menus: &USSDMenu,
+) -> HttpResponse {
+    let ussd_request = match adapter.parse_request(request, body) {
+        Ok(ussd_request) => ussd_request,
+        Err(e) => {
+            error!("Failed to parse gateway request: {}", e);
+            return HttpResponse::BadRequest().body(e);
+        }
+    };
+
+    let response = app.run_async(ussd_request, menus).await;
+
+    adapter.render_response(request, body, &response)
+}
+
+/// Builds an actix route that handles requests with the given adapter.
+///
+/// The route accepts any HTTP method, since some gateways send their requests with `GET`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use actix_web::{web, App, HttpServer};
+/// use ussdframework::gateway::{self, Arkesel, Hubtel};
+/// use ussdframework::prelude::*;
+///
+/// #[actix_web::main]
+/// async fn main() -> std::io::Result<()> {
+///     HttpServer::new(move || {
+///         let app = UssdApp::new(true, None);
+///         let menus = USSDMenu::load_from_json("menu.json").unwrap();
+///
+///         App::new()
+///             .app_data(web::Data::new(app))
+///             .app_data(web::Data::new(menus))
+///             .route("/ussd/hubtel", gateway::route(Hubtel))
+///             .route("/ussd/arkesel", gateway::route(Arkesel))
+///     })
+///     .bind("127.0.0.1:3000")?
+///     .run()
+///     .await
+/// }
+/// ```
+pub fn route<A: GatewayAdapter + 'static>(adapter: A) -> Route {
+    let adapter = Arc::new(adapter);
+
+    web::route().to(
+        move |request: HttpRequest,
+              body: web::Bytes,
+              app: web::Data<UssdApp>,
+              menus: web::Data<USSDMenu>| {
+            let adapter = adapter.clone();
+            async move { handle_request(adapter.as_ref(), &request, &body, &app, &menus).await }
+        },
+    )
+}
+
+/// Parses a JSON body into the gateway's request type.
+fn parse_json<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, String> {
+    serde_json::from_slice(body).map_err(|e| e.to_string())
+}
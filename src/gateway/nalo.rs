@@ -0,0 +1,138 @@
+use actix_web::{HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{USSDRequest, USSDResponse};
+
+use super::{parse_json, GatewayAdapter};
+
+/// A request sent by the Nalo Solutions USSD gateway.
+///
+/// # Fields
+///
+/// * `user_id`: The identifier of the Nalo account, echoed back in the reply.
+/// * `msisdn`: The phone number of the user.
+/// * `user_data`: The dialled code on the first request, the user's input otherwise.
+/// * `msg_type`: `true` on the first request of a session.
+/// * `network`: The user's mobile network.
+/// * `session_id`: The unique identifier of the session.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct NaloRequest {
+    #[serde(rename = "USERID")]
+    pub user_id: String,
+    pub msisdn: String,
+    #[serde(rename = "USERDATA", default)]
+    pub user_data: String,
+    #[serde(rename = "MSGTYPE")]
+    pub msg_type: bool,
+    #[serde(default)]
+    pub network: Option<String>,
+    #[serde(rename = "SESSIONID")]
+    pub session_id: String,
+}
+
+/// A reply to the Nalo Solutions USSD gateway.
+///
+/// The `msg_type` is `true` to continue the session and `false` to end it.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct NaloResponse {
+    #[serde(rename = "USERID")]
+    pub user_id: String,
+    pub msisdn: String,
+    pub msg: String,
+    #[serde(rename = "MSGTYPE")]
+    pub msg_type: bool,
+}
+
+/// Adapter for the Nalo Solutions USSD gateway, which sends and expects JSON.
+pub struct Nalo;
+
+impl GatewayAdapter for Nalo {
+    fn parse_request(&self, _request: &HttpRequest, body: &[u8]) -> Result<USSDRequest, String> {
+        let request: NaloRequest = parse_json(body)?;
+
+        // The user data of the first request is the dialled code, not an input
+        let (input, service_code) = if request.msg_type {
+            (String::new(), request.user_data)
+        } else {
+            // The engine uses the code the session started with
+            (request.user_data, String::new())
+        };
+
+        Ok(USSDRequest {
+            session_id: request.session_id,
+            msisdn: request.msisdn,
+            input,
+            service_code,
+            language: String::new(),
+        })
+    }
+
+    fn render_response(
+        &self,
+        _request: &HttpRequest,
+        body: &[u8],
+        response: &USSDResponse,
+    ) -> HttpResponse {
+        let request: NaloRequest = parse_json(body).unwrap_or_default();
+
+        HttpResponse::Ok().json(NaloResponse {
+            user_id: request.user_id,
+            msisdn: response.msisdn.clone(),
+            msg: response.message.clone(),
+            msg_type: !response.end_session,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn test_nalo_parse_request() {
+        let request = TestRequest::default().to_http_request();
+        let body = br#"{"USERID": "acme", "MSISDN": "233200000000", "USERDATA": "*920*55#", "MSGTYPE": true, "NETWORK": "MTN", "SESSIONID": "n1"}"#;
+
+        let ussd_request = Nalo.parse_request(&request, body).unwrap();
+        assert_eq!(ussd_request.session_id, "n1");
+        assert_eq!(ussd_request.msisdn, "233200000000");
+        assert_eq!(ussd_request.service_code, "*920*55#");
+        assert_eq!(ussd_request.input, "");
+
+        let body = br#"{"USERID": "acme", "MSISDN": "233200000000", "USERDATA": "2", "MSGTYPE": false, "NETWORK": "MTN", "SESSIONID": "n1"}"#;
+        let ussd_request = Nalo.parse_request(&request, body).unwrap();
+        assert_eq!(ussd_request.service_code, "");
+        assert_eq!(ussd_request.input, "2");
+
+        assert!(Nalo.parse_request(&request, b"not json").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_nalo_render_response() {
+        let request = TestRequest::default().to_http_request();
+        let body = br#"{"USERID": "acme", "MSISDN": "233200000000", "USERDATA": "2", "MSGTYPE": false, "NETWORK": "MTN", "SESSIONID": "n1"}"#;
+
+        let response = USSDResponse {
+            msisdn: "233200000000".to_string(),
+            session_id: "n1".to_string(),
+            end_session: false,
+            message: "Main Menu\n1. Balance".to_string(),
+        };
+        let reply = Nalo.render_response(&request, body, &response);
+        let bytes = actix_web::body::to_bytes(reply.into_body()).await.unwrap();
+        let reply: NaloResponse = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(
+            reply,
+            NaloResponse {
+                user_id: "acme".to_string(),
+                msisdn: "233200000000".to_string(),
+                msg: "Main Menu\n1. Balance".to_string(),
+                msg_type: true,
+            }
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use actix_web::{HttpRequest, HttpResponse};
+
+use crate::core::{USSDRequest, USSDResponse};
+
+use super::GatewayAdapter;
+
+/// Adapter for gateways that send XML over HTTP and read the `Freeflow` header of the reply.
+///
+/// Requests have the following shape, where a `type` of `1` marks the first request of a session
+/// and its `msg` is the full dial string:
+///
+/// ```xml
+/// <ussd>
+///     <msisdn>233200000000</msisdn>
+///     <sessionid>1234567890</sessionid>
+///     <servicecode>*713#</servicecode>
+///     <type>1</type>
+///     <msg>*713#</msg>
+/// </ussd>
+/// ```
+///
+/// Replies have the same shape, with a `type` of `2` to continue the session and `3` to end it. The
+/// `Freeflow` header is `FC` to continue the session and `FB` to end it.
+pub struct XmlFreeflow;
+
+impl GatewayAdapter for XmlFreeflow {
+    fn parse_request(&self, _request: &HttpRequest, body: &[u8]) -> Result<USSDRequest, String> {
+        let body = std::str::from_utf8(body).map_err(|e| e.to_string())?;
+
+        let session_id = tag_value(body, "sessionid").ok_or("No sessionid in the request")?;
+        let msisdn = tag_value(body, "msisdn").ok_or("No msisdn in the request")?;
+        let is_start = tag_value(body, "type").as_deref() == Some("1");
+
+        // The message of the first request is the dial string, not an input
+        let message = tag_value(body, "msg").unwrap_or_default();
+        let (input, service_code) = if !is_start {
+            // The engine uses the code the session started with
+            (message, String::new())
+        } else if message.is_empty() {
+            (
+                String::new(),
+                tag_value(body, "servicecode").unwrap_or_default(),
+            )
+        } else {
+            (String::new(), message)
+        };
+
+        Ok(USSDRequest {
+            session_id,
+            msisdn,
+            input,
+            service_code,
+            language: String::new(),
+        })
+    }
+
+    fn render_response(
+        &self,
+        _request: &HttpRequest,
+        _body: &[u8],
+        response: &USSDResponse,
+    ) -> HttpResponse {
+        let (message_type, freeflow) = if response.end_session {
+            ("3", "FB")
+        } else {
+            ("2", "FC")
+        };
+
+        let body = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ussd><msisdn>{}</msisdn><sessionid>{}</sessionid><type>{}</type><msg>{}</msg></ussd>",
+            escape(&response.msisdn),
+            escape(&response.session_id),
+            message_type,
+            escape(&response.message)
+        );
+
+        HttpResponse::Ok()
+            .content_type("text/xml")
+            .insert_header(("Freeflow", freeflow))
+            .body(body)
+    }
+}
+
+/// Returns the unescaped text of the first `<tag>` element in the XML.
+fn tag_value(xml: &str, tag: &str) -> Option<String> {
+    let pattern = regex::Regex::new(&format!(r"(?s)<{0}>(.*?)</{0}>", regex::escape(tag))).unwrap();
+
+    pattern
+        .captures(xml)
+        .map(|caps| unescape(caps.get(1).unwrap().as_str().trim()))
+}
+
+/// Escapes the characters that cannot appear in XML text.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Reverses `escape`.
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body::MessageBody, test::TestRequest};
+
+    use super::*;
+
+    #[test]
+    fn test_xml_freeflow_round_trip() {
+        let request = TestRequest::default().to_http_request();
+        let body = b"<ussd><msisdn>233200000000</msisdn><sessionid>x1</sessionid><type>2</type><msg>1</msg></ussd>";
+
+        let ussd_request = XmlFreeflow.parse_request(&request, body).unwrap();
+        assert_eq!(ussd_request.session_id, "x1");
+        assert_eq!(ussd_request.input, "1");
+
+        let start = b"<ussd><msisdn>233200000000</msisdn><sessionid>x1</sessionid><servicecode>*713#</servicecode><type>1</type><msg>*713*1#</msg></ussd>";
+        let ussd_request = XmlFreeflow.parse_request(&request, start).unwrap();
+        assert_eq!(ussd_request.service_code, "*713*1#");
+        assert_eq!(ussd_request.input, "");
+
+        let response = USSDResponse {
+            msisdn: "233200000000".to_string(),
+            session_id: "x1".to_string(),
+            end_session: true,
+            message: "Bye & thanks".to_string(),
+        };
+        let reply = XmlFreeflow.render_response(&request, body, &response);
+        assert_eq!(reply.headers().get("Freeflow").unwrap(), "FB");
+
+        let reply_body = reply.into_body().try_into_bytes().unwrap();
+        let reply_body = std::str::from_utf8(&reply_body).unwrap();
+        assert_eq!(tag_value(reply_body, "msg").unwrap(), "Bye & thanks");
+        assert_eq!(tag_value(reply_body, "type").unwrap(), "3");
+    }
+}
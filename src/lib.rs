@@ -1,15 +1,31 @@
+pub mod analytics;
 mod core;
+pub mod gateway;
 mod log;
+pub mod metrics;
 pub mod prelude;
+pub mod testing;
 pub mod types;
 mod utils;
 
 extern crate serde;
 
+use std::{
+    borrow::Borrow,
+    sync::{Arc, RwLock},
+};
+
+use analytics::SessionJourney;
 use core::{
-    process_request, InMemorySessionStore, SessionCache, USSDMenu, USSDRequest, USSDResponse,
+    error_response, process_request, process_request_async, record_evicted_session,
+    AsyncSessionCache, InMemorySessionStore, MenuValidationError, SessionCache, SessionSettings,
+    USSDMenu, USSDRequest, USSDResponse, USSDSession, UssdError,
 };
-use utils::{register_function, FUNCTION_MAP, REGISTERED_FUNCTIONS};
+use utils::FunctionRegistry;
+
+// Global function maps, kept for code written before each `UssdApp` owned a `FunctionRegistry`.
+// Functions registered here are found by any app whose registry has no function of the same name.
+pub use utils::{register_async_function, register_function, ASYNC_FUNCTION_MAP, FUNCTION_MAP};
 
 #[cfg(feature = "menubuilder")]
 mod builder;
@@ -20,7 +36,15 @@ mod builder;
 /// # Fields
 ///
 /// * `functions_path` - The path to the functions used by the USSD application.
-/// * `session_cache` - The session cache implementation used by the USSD application.
+/// * `session_cache` - The session cache implementation used by the USSD application. Set it with
+///   `new` or `use_session_cache`, so the app is told about the sessions it removes once they
+///   expire.
+/// * `async_session_cache` - The session cache awaited by `run_async`, instead of `session_cache`.
+/// * `session_settings` - How long sessions live and what happens when they expire.
+/// * `functions` - The functions the services of the menu can call.
+/// * `session_start_hook` - Called with the session when a session starts.
+/// * `session_end_hook` - Called with the final session when a session ends.
+/// * `journey_hook` - Called with the journey of a session when it ends or expires.
 ///
 /// # Examples
 ///
@@ -30,7 +54,13 @@ mod builder;
 /// let app = UssdApp::new(false, None);
 /// ```
 pub struct UssdApp {
-    pub session_cache: Box<dyn SessionCache>,
+    session_cache: Box<dyn SessionCache>,
+    pub session_settings: SessionSettings,
+    pub functions: FunctionRegistry,
+    session_start_hook: Option<types::SessionHook>,
+    session_end_hook: Option<types::SessionHook>,
+    journey_hook: Arc<RwLock<Option<types::JourneyHook>>>,
+    async_session_cache: Option<Box<dyn AsyncSessionCache>>,
 }
 
 impl UssdApp {
@@ -55,16 +85,155 @@ impl UssdApp {
                 session_manager.unwrap()
             };
 
-        UssdApp { session_cache }
+        let app = UssdApp {
+            session_cache,
+            session_settings: SessionSettings::default(),
+            functions: FunctionRegistry::new(),
+            session_start_hook: None,
+            session_end_hook: None,
+            journey_hook: Arc::new(RwLock::new(None)),
+            async_session_cache: None,
+        };
+        app.session_cache.on_expire(app.expiry_hook());
+        app
+    }
+
+    /// Returns the hook the session caches call with the sessions they remove once expired.
+    ///
+    /// Those sessions are recorded as abandoned in the metrics, and their journey is passed to the
+    /// journey hook.
+    fn expiry_hook(&self) -> types::ExpiryHook {
+        let journey_hook = self.journey_hook.clone();
+        Arc::new(move |session: &USSDSession| record_evicted_session(session, &journey_hook))
+    }
+
+    /// Sets the hook called when a session starts.
+    ///
+    /// A session starts with the first request for its session id, or when an expired or ended
+    /// session is restarted. The hook runs before any screen of the session.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - The function called with the new session.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ussdframework::prelude::*;
+    ///
+    /// let mut app = UssdApp::new(true, None);
+    ///
+    /// app.on_session_start(|session: &USSDSession| {
+    ///     println!("Session {} started for {}", session.session_id, session.msisdn);
+    /// });
+    /// ```
+    pub fn on_session_start<F>(&mut self, hook: F)
+    where
+        F: Fn(&USSDSession) + Send + Sync + 'static,
+    {
+        self.session_start_hook = Some(Box::new(hook));
+    }
+
+    /// Sets the hook called when a session ends.
+    ///
+    /// A session ends when a `Quit` screen is displayed. The hook receives the final session,
+    /// including its data, before the session is removed from the session cache. Sessions that
+    /// expire without being ended do not call the hook.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - The function called with the final session.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ussdframework::prelude::*;
+    ///
+    /// let mut app = UssdApp::new(true, None);
+    ///
+    /// app.on_session_end(|session: &USSDSession| {
+    ///     println!("Session {} ended at {}", session.session_id, session.current_screen);
+    /// });
+    /// ```
+    pub fn on_session_end<F>(&mut self, hook: F)
+    where
+        F: Fn(&USSDSession) + Send + Sync + 'static,
+    {
+        self.session_end_hook = Some(Box::new(hook));
+    }
+
+    /// Sets the hook called with the journey of a session when it ends or expires.
+    ///
+    /// The journey lists the screens displayed, the screen the session ended or expired on, how
+    /// long it lasted and whether it ended on a `Quit` screen. Expired sessions are reported when
+    /// the next request for them arrives, or when the session cache removes them. See the
+    /// `analytics` module to aggregate journeys into drop-off rates and funnel conversion.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - The function called with the journey.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ussdframework::prelude::*;
+    ///
+    /// let mut app = UssdApp::new(true, None);
+    ///
+    /// app.on_journey(|journey: &SessionJourney| {
+    ///     println!("{} visited {:?}", journey.session_id, journey.screens);
+    /// });
+    /// ```
+    pub fn on_journey<F>(&mut self, hook: F)
+    where
+        F: Fn(&SessionJourney) + Send + Sync + 'static,
+    {
+        *self.journey_hook.write().unwrap() = Some(Box::new(hook));
+    }
+
+    /// Returns the session cache used by `run`.
+    pub fn session_cache(&self) -> &dyn SessionCache {
+        self.session_cache.as_ref()
+    }
+
+    /// Sets the session cache used by `run`.
+    ///
+    /// The cache is given the app's expiry hook, so the sessions it removes once expired are still
+    /// recorded as abandoned and passed to the journey hook.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - The session cache.
+    pub fn use_session_cache<C>(&mut self, cache: C)
+    where
+        C: SessionCache + 'static,
+    {
+        cache.on_expire(self.expiry_hook());
+        self.session_cache = Box::new(cache);
+    }
+
+    /// Sets the session cache awaited by `run_async`.
+    ///
+    /// Requests processed with `run_async` store their sessions in this cache instead of
+    /// the session cache of `run`, so session stores that make network calls do not block the
+    /// worker. Requests processed with `run` keep using `session_cache`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - The async session cache.
+    pub fn use_async_session_cache<C>(&mut self, cache: C)
+    where
+        C: AsyncSessionCache + 'static,
+    {
+        cache.on_expire(self.expiry_hook());
+        self.async_session_cache = Some(Box::new(cache));
     }
 
     /// Registers a batch of USSD functions provided in the `functions_map`.
     ///
-    /// The `register_functions` function is responsible for registering a batch of USSD (Unstructured
-    /// Supplementary Service Data) functions provided in the `functions_map`. It iterates through
-    /// each function in the map, checks if it has already been registered, and if not, registers it
-    /// by calling the `register_function` function. Once registered, the path of the function is added
-    /// to the set of registered functions to prevent redundant registrations.
+    /// The functions are registered in the app's `functions` registry, replacing any function
+    /// already registered under the same path. Functions registered in one `UssdApp` are not
+    /// visible to other instances.
     ///
     /// # Arguments
     ///
@@ -79,7 +248,7 @@ impl UssdApp {
     /// use std::collections::HashMap;
     ///
     /// // Define your USSD function
-    /// fn my_function(request: &USSDRequest, url: &str) -> USSDData {
+    /// fn my_function(session: &USSDSession, url: &str) -> USSDData {
     ///    // Your function logic here
     ///    return USSDData::Str("Hello".to_string());
     /// }
@@ -94,58 +263,237 @@ impl UssdApp {
     /// }
     ///
     /// // Register the functions
-    /// register_functions(functions());
-    ///
+    /// let mut app = UssdApp::new(true, None);
+    /// app.register_functions(functions());
     /// ```
     ///
-    /// # Panics
+    pub fn register_functions(&mut self, functions_map: types::FunctionMap) {
+        for (path, function) in functions_map {
+            self.functions.register(&path, function);
+        }
+    }
+
+    /// Registers a batch of async USSD functions provided in the `functions_map`.
+    ///
+    /// Async functions return a boxed future, so they can await network calls without blocking the
+    /// worker. They are registered next to synchronous ones and are only called when the
+    /// request is processed with `run_async`.
     ///
-    /// This function panics if it fails to acquire the lock on either the function map or the set of
-    /// registered functions.
+    /// # Arguments
     ///
-    /// # Safety
+    /// * `functions_map`: An `AsyncFunctionMap` containing the mapping of function paths to
+    ///   async USSD functions.
     ///
-    /// This function is safe to call as long as the `FUNCTION_MAP` and `REGISTERED_FUNCTIONS` are correctly
-    /// initialized and accessible by the current thread.
+    /// # Example
     ///
-    pub fn register_functions(&self, functions_map: types::FunctionMap) {
-        let mut function_map_guard = FUNCTION_MAP.lock().expect("Failed to lock function map");
-        let mut registered_functions_guard = REGISTERED_FUNCTIONS
-            .lock()
-            .expect("Failed to lock registered functions");
-
+    /// ```rust
+    /// use ussdframework::prelude::*;
+    ///
+    /// use std::collections::HashMap;
+    ///
+    /// fn my_function(session: USSDSession, url: String) -> USSDFuture {
+    ///     Box::pin(async move { USSDData::Str("Hello".to_string()) })
+    /// }
+    ///
+    /// let mut app = UssdApp::new(true, None);
+    ///
+    /// let mut functions_map: AsyncFunctionMap = HashMap::new();
+    /// functions_map.insert("my_function".to_string(), my_function as USSDAsyncFunction);
+    ///
+    /// app.register_async_functions(functions_map);
+    /// ```
+    ///
+    pub fn register_async_functions(&mut self, functions_map: types::AsyncFunctionMap) {
         for (path, function) in functions_map {
-            if registered_functions_guard.contains(&path) {
-                // Function already registered, skip it
-                continue;
-            }
-
-            register_function(&path, function, &mut function_map_guard);
-            registered_functions_guard.insert(path);
+            self.functions.register_async(&path, function);
         }
     }
 
+    /// Registers a single USSD function, which can be a closure capturing shared state.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The name services refer to the function by, in their `function_name`.
+    /// * `function` - The function, called with the session and the service's `function_url`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use ussdframework::prelude::*;
+    ///
+    /// let greeting = Arc::new(String::from("Welcome back"));
+    ///
+    /// let mut app = UssdApp::new(true, None);
+    /// app.register_function("greet", move |session: &USSDSession, _url: &str| {
+    ///     USSDData::Str(format!("{} {}", greeting, session.msisdn))
+    /// });
+    /// ```
+    pub fn register_function<F>(&mut self, path: &str, function: F)
+    where
+        F: Fn(&USSDSession, &str) -> types::USSDData + Send + Sync + 'static,
+    {
+        self.functions.register(path, function);
+    }
+
+    /// Validates the menu against the functions registered in this app.
+    ///
+    /// This is `USSDMenu::validate`, except that services are checked against the app's
+    /// `functions` registry.
+    ///
+    /// # Arguments
+    ///
+    /// * `menu` - The menu to validate.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` if the menu is valid, or a list of every `MenuValidationError` found.
+    pub fn validate_menu(&self, menu: &USSDMenu) -> Result<(), Vec<MenuValidationError>> {
+        menu.validate_with_functions(&self.functions)
+    }
+
     /// Runs the USSD application with the given request and screens.
     ///
+    /// A request that fails, for example because the menu points at a screen that does not exist
+    /// or the session cache is unreachable, is answered with the menu's `errors` message for the
+    /// class of the error, ending the session. Use `try_run` to handle the error instead.
+    ///
     /// # Arguments
     ///
     /// * `request` - The USSD request.
-    /// * `screens` - The USSD menu screens.
+    /// * `screens` - The USSD menu screens, owned or borrowed.
     ///
     /// # Returns
     ///
     /// The USSD response.
-    pub fn run(&self, request: USSDRequest, screens: USSDMenu) -> USSDResponse {
-        process_request(&request, &self.session_cache, &screens)
+    pub fn run(&self, request: USSDRequest, screens: impl Borrow<USSDMenu>) -> USSDResponse {
+        let screens = screens.borrow();
+        process_request(&request, self, screens)
+            .unwrap_or_else(|e| error_response(&request, screens, &e))
     }
 
-    /// Displays the menu to the user.
+    /// Runs the USSD application with the given request and screens, returning the error that
+    /// stopped the request if it fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The USSD request.
+    /// * `screens` - The USSD menu screens, owned or borrowed.
+    ///
+    /// # Returns
+    ///
+    /// The USSD response, or the `UssdError` that stopped the request.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use ussdframework::prelude::*;
+    ///
+    /// let app = UssdApp::new(true, None);
+    /// let request = USSDRequest {
+    ///     session_id: "session".to_string(),
+    ///     msisdn: "1234567890".to_string(),
+    ///     input: String::new(),
+    ///     service_code: "*123#".to_string(),
+    ///     language: "en".to_string(),
+    /// };
+    ///
+    /// match app.try_run(request, USSDMenu::new()) {
+    ///     Ok(response) => println!("{}", response.message),
+    ///     Err(e) => match e.class() {
+    ///         ErrorClass::Menu => eprintln!("The menu is broken: {}", e),
+    ///         ErrorClass::Session => eprintln!("The session store failed: {}", e),
+    ///     },
+    /// }
+    /// ```
+    pub fn try_run(
+        &self,
+        request: USSDRequest,
+        screens: impl Borrow<USSDMenu>,
+    ) -> Result<USSDResponse, UssdError> {
+        process_request(&request, self, screens.borrow())
+    }
+
+    /// Runs the USSD application with the given request and screens without blocking the worker.
+    ///
+    /// Unlike `run`, this awaits async functions and calls http services. Failed requests are
+    /// answered like in `run`.
+    ///
+    /// Http services use the awc client of the current thread, so menus calling them must be run
+    /// on an actix runtime, such as an actix-web worker. The returned future is not `Send`: await
+    /// it on the thread that created it rather than spawning it with `tokio::spawn`.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The USSD request.
+    /// * `screens` - The USSD menu screens, owned or borrowed.
+    ///
+    /// # Returns
+    ///
+    /// The USSD response.
+    pub async fn run_async(
+        &self,
+        request: USSDRequest,
+        screens: impl Borrow<USSDMenu>,
+    ) -> USSDResponse {
+        let screens = screens.borrow();
+        process_request_async(&request, self, screens)
+            .await
+            .unwrap_or_else(|e| error_response(&request, screens, &e))
+    }
+
+    /// Runs the USSD application with the given request and screens without blocking the worker,
+    /// returning the error that stopped the request if it fails.
+    ///
+    /// Like `run_async`, the returned future is not `Send` and http services need an actix
+    /// runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The USSD request.
+    /// * `screens` - The USSD menu screens, owned or borrowed.
+    ///
+    /// # Returns
+    ///
+    /// The USSD response, or the `UssdError` that stopped the request.
+    pub async fn try_run_async(
+        &self,
+        request: USSDRequest,
+        screens: impl Borrow<USSDMenu>,
+    ) -> Result<USSDResponse, UssdError> {
+        process_request_async(&request, self, screens.borrow()).await
+    }
+
+    /// Displays the menu to the user, framed like a phone screen.
+    ///
+    /// The footer shows whether the user can reply or the session has ended.
     ///
     /// # Arguments
     ///
     /// * `ussd_response` - The USSD response containing the menu message.
     pub fn display_menu(&self, ussd_response: &USSDResponse) {
+        let footer = if ussd_response.end_session {
+            "Session ended"
+        } else {
+            "Reply"
+        };
+
+        let lines: Vec<&str> = ussd_response.message.lines().collect();
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .chain([footer.len(), 24])
+            .max()
+            .unwrap_or_default();
+        let border = format!("+{}+", "-".repeat(width + 2));
+
         // Display the menu to the user
-        println!("{}", ussd_response.message);
+        println!("{}", border);
+        for line in lines {
+            println!("| {:<width$} |", line, width = width);
+        }
+        println!("{}", border);
+        println!("| {:>width$} |", footer, width = width);
+        println!("{}", border);
     }
 }
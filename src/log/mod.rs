@@ -1,40 +1,29 @@
-// colorise log output
-fn log_info(message: &str) {
-    println!("\x1b[1;32m{}\x1b[0m", message);
-}
-
-fn log_error(message: &str) {
-    println!("\x1b[1;31m{}\x1b[0m", message)
-}
-
-fn log_warning(message: &str) {
-    println!("\x1b[1;33m{}\x1b[0m", message)
-}
+//! Logging for the framework.
+//!
+//! The `info!`, `error!`, `warning!`, `debug!` and `trace!` macros emit `tracing` events, so the
+//! application decides where logs go and at which level by installing a subscriber, for example
+//! `tracing-subscriber` with an `EnvFilter` and JSON output. Without a subscriber, events are
+//! forwarded to the `log` crate, and nothing is printed unless a logger is installed.
+//!
+//! The macros take the same arguments as the `tracing` macros, so structured fields can be added
+//! before the message:
+//!
+//! ```ignore
+//! debug!(screen = %current_screen, "Displaying message");
+//! ```
+//!
+//! Each request runs in a `ussd_request` span carrying its `session_id`, `msisdn` and
+//! `service_code`, so every event logged while processing it carries them too. Services run in a
+//! `ussd_service` span carrying the `service` and its `kind`.
 
-fn log_debug(message: &str) {
-    println!("\x1b[1;34m{}\x1b[0m", message)
-}
-
-fn log_trace(message: &str) {
-    println!("\x1b[1;35m{}\x1b[0m", message)
-}
-
-pub fn logger(level: &str, message: &str) {
-    match level {
-        "info" => log_info(message),
-        "error" => log_error(message),
-        "warning" => log_warning(message),
-        "debug" => log_debug(message),
-        "trace" => log_trace(message),
-        _ => log_info(message),
-    }
-}
+#[doc(hidden)]
+pub use tracing;
 
 #[macro_export]
 /// Macro for logging an info message.
 ///
-/// This macro takes a format string and arguments, and logs a warning message using the `logger` function from the `log` module.
-/// The format string and arguments are passed to the `format!` macro to create the final log message.
+/// This macro takes optional structured fields, a format string and arguments, and emits an info
+/// event with the `tracing` crate.
 ///
 /// # Examples
 ///
@@ -43,15 +32,15 @@ pub fn logger(level: &str, message: &str) {
 /// ```
 macro_rules! info {
     ($($arg:tt)*) => ({
-        $crate::log::logger("info", &format!($($arg)*));
+        $crate::log::tracing::info!($($arg)*);
     })
 }
 
 #[macro_export]
 /// Macro for logging an error message.
 ///
-/// This macro takes a format string and arguments, and logs a warning message using the `logger` function from the `log` module.
-/// The format string and arguments are passed to the `format!` macro to create the final log message.
+/// This macro takes optional structured fields, a format string and arguments, and emits an error
+/// event with the `tracing` crate.
 ///
 /// # Examples
 ///
@@ -60,15 +49,15 @@ macro_rules! info {
 /// ```
 macro_rules! error {
     ($($arg:tt)*) => ({
-        $crate::log::logger("error", &format!($($arg)*));
+        $crate::log::tracing::error!($($arg)*);
     })
 }
 
 #[macro_export]
 /// Macro for logging a warning message.
 ///
-/// This macro takes a format string and arguments, and logs a warning message using the `logger` function from the `log` module.
-/// The format string and arguments are passed to the `format!` macro to create the final log message.
+/// This macro takes optional structured fields, a format string and arguments, and emits a warn
+/// event with the `tracing` crate.
 ///
 /// # Examples
 ///
@@ -77,15 +66,15 @@ macro_rules! error {
 /// ```
 macro_rules! warning {
     ($($arg:tt)*) => ({
-        $crate::log::logger("warning", &format!($($arg)*));
+        $crate::log::tracing::warn!($($arg)*);
     })
 }
 
 #[macro_export]
 /// Macro for logging a debug message.
 ///
-/// This macro takes a format string and arguments, and logs a warning message using the `logger` function from the `log` module.
-/// The format string and arguments are passed to the `format!` macro to create the final log message.
+/// This macro takes optional structured fields, a format string and arguments, and emits a debug
+/// event with the `tracing` crate.
 ///
 /// # Examples
 ///
@@ -94,15 +83,15 @@ macro_rules! warning {
 /// ```
 macro_rules! debug {
     ($($arg:tt)*) => ({
-        $crate::log::logger("debug", &format!($($arg)*));
+        $crate::log::tracing::debug!($($arg)*);
     })
 }
 
 #[macro_export]
-/// Macro for logging a warning message.
+/// Macro for logging a trace message.
 ///
-/// This macro takes a format string and arguments, and logs a warning message using the `logger` function from the `log` module.
-/// The format string and arguments are passed to the `format!` macro to create the final log message.
+/// This macro takes optional structured fields, a format string and arguments, and emits a trace
+/// event with the `tracing` crate.
 ///
 /// # Examples
 ///
@@ -111,23 +100,12 @@ macro_rules! debug {
 /// ```
 macro_rules! trace {
     ($($arg:tt)*) => ({
-        $crate::log::logger("trace", &format!($($arg)*));
+        $crate::log::tracing::trace!($($arg)*);
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
-    #[test]
-    fn test_logger() {
-        logger("info", "test");
-        logger("error", "test");
-        logger("warning", "test");
-        logger("debug", "test");
-        logger("trace", "test");
-    }
-
     #[test]
     fn test_logger_macro() {
         info!("test");
@@ -136,4 +114,11 @@ mod tests {
         debug!("test");
         trace!("test");
     }
+
+    #[test]
+    fn test_logger_macro_fields() {
+        let screen = "MainScreen";
+        info!(screen, "test");
+        debug!(session_id = %"session", screen = ?screen, "test {}", 1);
+    }
 }
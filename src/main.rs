@@ -0,0 +1,279 @@
+//! Interactive terminal simulator for USSD menus.
+//!
+//! Loads a menu JSON file and runs it like a phone would: dial a service code, type replies and
+//! inspect the session at any point, without an HTTP server or a gateway.
+//!
+//! ```bash
+//! cargo run --bin main -- examples/data/menu.json --stubs stubs.json
+//! ```
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    process,
+};
+
+use serde_json::Value;
+use ussdframework::{prelude::*, testing::ConversationTest, types::hash_str_any_to_json};
+
+const USAGE: &str = "Usage: main <menu.json> [options]
+
+Options:
+  --stubs <file>         JSON object of the data returned by services, by service name
+  --msisdn <number>      Phone number to dial from (default 1234567890)
+  --language <code>      Language of the session (default: the menu's default language)
+  --service-code <code>  Service code dialled by :dial (default *123#)
+  -h, --help             Show this message";
+
+const HELP: &str = "Dial a service code such as *123#, then type your replies.
+
+Commands:
+  :dial      Dial the last service code again
+  :session   Show the session state
+  :screen    Show the current screen
+  :visited   Show the screens visited
+  :data      Show the session data
+  :help      Show this message
+  :quit      Exit the simulator";
+
+/// Options of the simulator, read from the command line.
+struct Options {
+    menu: String,
+    stubs: Option<String>,
+    msisdn: String,
+    language: String,
+    service_code: String,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options {
+            menu: String::new(),
+            stubs: None,
+            msisdn: "1234567890".to_string(),
+            language: String::new(),
+            service_code: "*123#".to_string(),
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("Missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--stubs" => options.stubs = Some(value()?),
+                "--msisdn" => options.msisdn = value()?,
+                "--language" => options.language = value()?,
+                "--service-code" => options.service_code = value()?,
+                "-h" | "--help" => return Err(String::new()),
+                _ if arg.starts_with('-') => return Err(format!("Unknown option: {}", arg)),
+                _ if options.menu.is_empty() => options.menu = arg,
+                _ => return Err(format!("Unexpected argument: {}", arg)),
+            }
+        }
+
+        if options.menu.is_empty() {
+            return Err("Missing menu file".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+/// The simulated phone, holding the conversation of the latest dial.
+struct Simulator {
+    menu: USSDMenu,
+    stubs: HashMap<String, Value>,
+    options: Options,
+    conversation: Option<ConversationTest>,
+    in_session: bool,
+}
+
+impl Simulator {
+    /// Starts a new session on the service code.
+    fn dial(&mut self, service_code: &str) {
+        self.options.service_code = service_code.to_string();
+
+        let mut conversation = ConversationTest::new(self.menu.clone())
+            .msisdn(&self.options.msisdn)
+            .language(&self.options.language)
+            .service_code(service_code);
+        for (service, data) in self.stubs.iter() {
+            conversation = conversation.mock_data(service, data.clone());
+        }
+
+        conversation.dial();
+        self.show(conversation);
+    }
+
+    /// Sends a reply in the current session.
+    fn reply(&mut self, input: &str) {
+        match self.conversation.take() {
+            Some(mut conversation) if self.in_session => {
+                conversation.input(input);
+                self.show(conversation);
+            }
+            conversation => {
+                self.conversation = conversation;
+                println!(
+                    "No session, dial a service code such as {}",
+                    self.options.service_code
+                );
+            }
+        }
+    }
+
+    /// Displays the latest screen of the conversation and keeps it.
+    fn show(&mut self, conversation: ConversationTest) {
+        let response = conversation.response();
+        conversation.app().display_menu(response);
+
+        self.in_session = !response.end_session;
+        self.conversation = Some(conversation);
+    }
+
+    fn session(&self) -> Option<USSDSession> {
+        let session = self
+            .conversation
+            .as_ref()
+            .and_then(|conversation| conversation.session());
+
+        if session.is_none() {
+            println!("No session yet, dial a service code first");
+        }
+        session
+    }
+
+    fn show_session(&self) {
+        if let Some(session) = self.session() {
+            println!("Session:  {}", session.session_id);
+            println!("MSISDN:   {}", session.msisdn);
+            println!("Language: {}", session.language);
+            println!("Screen:   {}", session.current_screen);
+            println!("Visited:  {}", session.visited_screens.join(" > "));
+            println!("Ended:    {}", session.end_session);
+            if let Some(error_message) = &session.error_message {
+                println!("Error:    {}", error_message);
+            }
+            println!("Data:     {}", format_data(&session));
+        }
+    }
+
+    fn show_screen(&self) {
+        if let Some(session) = self.session() {
+            match self.menu.menus.get(&session.current_screen) {
+                Some(screen) => println!(
+                    "{} ({})",
+                    session.current_screen,
+                    screen.screen_type.to_string()
+                ),
+                None => println!("{}", session.current_screen),
+            }
+        }
+    }
+
+    fn show_visited(&self) {
+        if let Some(session) = self.session() {
+            for (index, screen) in session.visited_screens.iter().enumerate() {
+                println!("{}. {}", index + 1, screen);
+            }
+        }
+    }
+
+    fn show_data(&self) {
+        if let Some(session) = self.session() {
+            println!("{}", format_data(&session));
+        }
+    }
+}
+
+/// Formats the session data as pretty JSON.
+fn format_data(session: &USSDSession) -> String {
+    let data: serde_json::Map<String, Value> = session
+        .data
+        .iter()
+        .map(|(key, value)| (key.clone(), hash_str_any_to_json(value.clone())))
+        .collect();
+
+    serde_json::to_string_pretty(&data).unwrap_or_default()
+}
+
+/// Loads the data returned by stubbed services.
+fn load_stubs(path: &str, menu: &USSDMenu) -> Result<HashMap<String, Value>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let stubs: HashMap<String, Value> =
+        serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path, e))?;
+
+    for service in stubs.keys() {
+        if !menu.services.contains_key(service) {
+            return Err(format!("{}: unknown service {:?}", path, service));
+        }
+    }
+
+    Ok(stubs)
+}
+
+fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{}\n", e);
+            }
+            eprintln!("{}", USAGE);
+            process::exit(if e.is_empty() { 0 } else { 2 });
+        }
+    };
+
+    let menu = USSDMenu::load_from_json(&options.menu).unwrap_or_else(|e| {
+        eprintln!("Failed to load menu {}: {}", options.menu, e);
+        process::exit(1);
+    });
+
+    let stubs = match &options.stubs {
+        Some(path) => load_stubs(path, &menu).unwrap_or_else(|e| {
+            eprintln!("Failed to load stubs {}", e);
+            process::exit(1);
+        }),
+        None => HashMap::new(),
+    };
+
+    let mut simulator = Simulator {
+        menu,
+        stubs,
+        options,
+        conversation: None,
+        in_session: false,
+    };
+
+    println!("USSD simulator, type :help for commands");
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        let line = line.trim();
+
+        match line {
+            ":quit" | ":q" => break,
+            ":help" => println!("{}", HELP),
+            ":dial" => {
+                let service_code = simulator.options.service_code.clone();
+                simulator.dial(&service_code);
+            }
+            ":session" => simulator.show_session(),
+            ":screen" => simulator.show_screen(),
+            ":visited" => simulator.show_visited(),
+            ":data" => simulator.show_data(),
+            _ if line.starts_with(':') => println!("Unknown command {}, type :help", line),
+            _ if line.starts_with('*') && line.ends_with('#') => simulator.dial(line),
+            _ => simulator.reply(line),
+        }
+    }
+}
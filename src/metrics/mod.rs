@@ -0,0 +1,441 @@
+//! Metrics of the USSD traffic, exposed in the Prometheus text format.
+//!
+//! The engine records its metrics in a process-wide registry returned by `metrics()`, so every
+//! `UssdApp` of the process reports to the same endpoint. Mount `route()` on the actix app to
+//! expose them:
+//!
+//! ```rust,no_run
+//! use actix_web::{App, HttpServer};
+//! use ussdframework::metrics;
+//!
+//! #[actix_web::main]
+//! async fn main() -> std::io::Result<()> {
+//!     HttpServer::new(|| App::new().route("/metrics", metrics::route()))
+//!         .bind("127.0.0.1:3000")?
+//!         .run()
+//!         .await
+//! }
+//! ```
+//!
+//! # Metrics
+//!
+//! * `ussd_requests_total{service_code}`: Requests processed, by service code. Service codes no
+//!   screen serves are counted as `unknown`.
+//! * `ussd_request_duration_seconds`: End-to-end latency of processing a request.
+//! * `ussd_screen_views_total{screen}`: Messages displayed, by screen.
+//! * `ussd_screen_exits_total{screen,reason}`: Sessions that `ended` or were `abandoned` on a
+//!   screen.
+//! * `ussd_service_calls_total{service}`: Service calls, by function name.
+//! * `ussd_service_failures_total{service}`: Service calls whose result has an `error` field.
+//! * `ussd_service_duration_seconds{service}`: Latency of service calls.
+//! * `ussd_sessions_started_total`, `ussd_sessions_ended_total`, `ussd_sessions_abandoned_total`:
+//!   Sessions started, ended by a `Quit` screen, and expired without ending.
+
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex, time::Duration};
+
+use actix_web::{web, HttpResponse, Route};
+
+lazy_static::lazy_static! {
+    // Registry the engine records its metrics in
+    static ref METRICS: Metrics = Metrics::new();
+}
+
+/// Returns the registry the engine records its metrics in.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Why a session left a screen.
+///
+/// * `Ended`: A `Quit` screen ended the session.
+/// * `Abandoned`: The session expired while on the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ScreenExit {
+    Ended,
+    Abandoned,
+}
+
+impl ScreenExit {
+    fn label(&self) -> &'static str {
+        match self {
+            ScreenExit::Ended => "ended",
+            ScreenExit::Abandoned => "abandoned",
+        }
+    }
+}
+
+/// Latency histogram with the `BUCKETS` upper bounds.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        for (count, bound) in self.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, separator, bound, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{}{}le=\"+Inf\"}} {}",
+            name, labels, separator, self.count
+        );
+        let _ = writeln!(out, "{}_sum{} {}", name, braces(labels), self.sum);
+        let _ = writeln!(out, "{}_count{} {}", name, braces(labels), self.count);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    requests: BTreeMap<String, u64>,
+    request_duration: Histogram,
+    screen_views: BTreeMap<String, u64>,
+    screen_exits: BTreeMap<(String, ScreenExit), u64>,
+    service_calls: BTreeMap<String, u64>,
+    service_failures: BTreeMap<String, u64>,
+    service_duration: BTreeMap<String, Histogram>,
+    sessions_started: u64,
+    sessions_ended: u64,
+    sessions_abandoned: u64,
+}
+
+/// Counters and latency histograms of the USSD traffic.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ussdframework::metrics::Metrics;
+///
+/// let metrics = Metrics::new();
+/// metrics.record_request("*123#", Duration::from_millis(12));
+///
+/// assert!(metrics.render().contains("ussd_requests_total{service_code=\"*123#\"} 1"));
+/// ```
+#[derive(Debug, Default)]
+pub struct Metrics {
+    registry: Mutex<Registry>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a processed request and how long it took.
+    pub fn record_request(&self, service_code: &str, duration: Duration) {
+        let mut registry = self.registry.lock().unwrap();
+        *registry
+            .requests
+            .entry(service_code.to_string())
+            .or_default() += 1;
+        registry.request_duration.observe(duration);
+    }
+
+    /// Records a message displayed by `screen`.
+    pub fn record_screen_view(&self, screen: &str) {
+        let mut registry = self.registry.lock().unwrap();
+        *registry.screen_views.entry(screen.to_string()).or_default() += 1;
+    }
+
+    /// Records a service call, how long it took and whether it failed.
+    pub fn record_service_call(&self, service: &str, duration: Duration, failed: bool) {
+        let mut registry = self.registry.lock().unwrap();
+        *registry
+            .service_calls
+            .entry(service.to_string())
+            .or_default() += 1;
+        if failed {
+            *registry
+                .service_failures
+                .entry(service.to_string())
+                .or_default() += 1;
+        }
+        registry
+            .service_duration
+            .entry(service.to_string())
+            .or_default()
+            .observe(duration);
+    }
+
+    /// Records a session started.
+    pub fn record_session_start(&self) {
+        self.registry.lock().unwrap().sessions_started += 1;
+    }
+
+    /// Records a session that left `screen`, either ended or abandoned.
+    pub fn record_session_exit(&self, screen: &str, exit: ScreenExit) {
+        let mut registry = self.registry.lock().unwrap();
+        match exit {
+            ScreenExit::Ended => registry.sessions_ended += 1,
+            ScreenExit::Abandoned => registry.sessions_abandoned += 1,
+        }
+        *registry
+            .screen_exits
+            .entry((screen.to_string(), exit))
+            .or_default() += 1;
+    }
+
+    /// Renders the metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let registry = self.registry.lock().unwrap();
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "ussd_requests_total",
+            "counter",
+            "Requests processed.",
+        );
+        for (service_code, count) in &registry.requests {
+            sample(
+                &mut out,
+                "ussd_requests_total",
+                &[("service_code", service_code)],
+                *count,
+            );
+        }
+
+        header(
+            &mut out,
+            "ussd_request_duration_seconds",
+            "histogram",
+            "Latency of processing a request.",
+        );
+        registry
+            .request_duration
+            .render(&mut out, "ussd_request_duration_seconds", "");
+
+        header(
+            &mut out,
+            "ussd_screen_views_total",
+            "counter",
+            "Messages displayed by screen.",
+        );
+        for (screen, count) in &registry.screen_views {
+            sample(
+                &mut out,
+                "ussd_screen_views_total",
+                &[("screen", screen)],
+                *count,
+            );
+        }
+
+        header(
+            &mut out,
+            "ussd_screen_exits_total",
+            "counter",
+            "Sessions that ended or were abandoned on a screen.",
+        );
+        for ((screen, exit), count) in &registry.screen_exits {
+            sample(
+                &mut out,
+                "ussd_screen_exits_total",
+                &[("screen", screen), ("reason", exit.label())],
+                *count,
+            );
+        }
+
+        header(
+            &mut out,
+            "ussd_service_calls_total",
+            "counter",
+            "Service calls.",
+        );
+        for (service, count) in &registry.service_calls {
+            sample(
+                &mut out,
+                "ussd_service_calls_total",
+                &[("service", service)],
+                *count,
+            );
+        }
+
+        header(
+            &mut out,
+            "ussd_service_failures_total",
+            "counter",
+            "Service calls that returned an error.",
+        );
+        for (service, count) in &registry.service_failures {
+            sample(
+                &mut out,
+                "ussd_service_failures_total",
+                &[("service", service)],
+                *count,
+            );
+        }
+
+        header(
+            &mut out,
+            "ussd_service_duration_seconds",
+            "histogram",
+            "Latency of service calls.",
+        );
+        for (service, histogram) in &registry.service_duration {
+            histogram.render(
+                &mut out,
+                "ussd_service_duration_seconds",
+                &labels(&[("service", service)]),
+            );
+        }
+
+        for (name, help, count) in [
+            (
+                "ussd_sessions_started_total",
+                "Sessions started.",
+                registry.sessions_started,
+            ),
+            (
+                "ussd_sessions_ended_total",
+                "Sessions ended by a Quit screen.",
+                registry.sessions_ended,
+            ),
+            (
+                "ussd_sessions_abandoned_total",
+                "Sessions that expired without ending.",
+                registry.sessions_abandoned,
+            ),
+        ] {
+            header(&mut out, name, "counter", help);
+            sample(&mut out, name, &[], count);
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: u64) {
+    let _ = writeln!(out, "{}{} {}", name, braces(&self::labels(labels)), value);
+}
+
+/// Formats label pairs as `name="value",...`, escaping the values.
+fn labels(labels: &[(&str, &str)]) -> String {
+    labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn braces(labels: &str) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels)
+    }
+}
+
+/// Answers with the metrics of `metrics()` in the Prometheus text format.
+pub async fn handler() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics().render())
+}
+
+/// Builds an actix route that serves the metrics, to be scraped by Prometheus.
+pub fn route() -> Route {
+    web::get().to(handler)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{example_menu, ConversationTest};
+
+    #[test]
+    fn test_render_metrics() {
+        let metrics = Metrics::new();
+        metrics.record_request("*123#", Duration::from_millis(30));
+        metrics.record_request("*123#", Duration::from_secs(20));
+        metrics.record_screen_view("MainScreen");
+        metrics.record_service_call("buy_airtime", Duration::from_millis(200), true);
+        metrics.record_session_start();
+        metrics.record_session_exit("Success \"Screen\"", ScreenExit::Ended);
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE ussd_requests_total counter\n"));
+        assert!(text.contains("ussd_requests_total{service_code=\"*123#\"} 2\n"));
+        assert!(text.contains("ussd_request_duration_seconds_bucket{le=\"0.05\"} 1\n"));
+        assert!(text.contains("ussd_request_duration_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(text.contains("ussd_request_duration_seconds_count 2\n"));
+        assert!(text.contains("ussd_screen_views_total{screen=\"MainScreen\"} 1\n"));
+        assert!(text.contains(
+            "ussd_screen_exits_total{screen=\"Success \\\"Screen\\\"\",reason=\"ended\"} 1\n"
+        ));
+        assert!(text.contains("ussd_service_failures_total{service=\"buy_airtime\"} 1\n"));
+        assert!(text.contains(
+            "ussd_service_duration_seconds_bucket{service=\"buy_airtime\",le=\"0.25\"} 1\n"
+        ));
+        assert!(text.contains("ussd_sessions_started_total 1\n"));
+        assert!(text.contains("ussd_sessions_ended_total 1\n"));
+        assert!(text.contains("ussd_sessions_abandoned_total 0\n"));
+    }
+
+    #[test]
+    fn test_conversation_metrics() {
+        // The registry is shared with the other tests, so only increases are checked
+        let sample = |name: &str| -> u64 {
+            metrics()
+                .render()
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.trim().parse().ok())
+                .unwrap_or(0)
+        };
+        let views = "ussd_screen_views_total{screen=\"SuccessScreen\"}";
+        let calls = "ussd_service_calls_total{service=\"mock:buy_airtime\"}";
+        let exits = "ussd_screen_exits_total{screen=\"SuccessScreen\",reason=\"ended\"}";
+        let (views_before, calls_before, exits_before) =
+            (sample(views), sample(calls), sample(exits));
+
+        let mut test = ConversationTest::new(example_menu())
+            .service_code("*123#")
+            .mock_data(
+                "buy_airtime",
+                json!({"status": "success", "message": "Airtime sent"}),
+            );
+        test.dial().input("3").input("1").input("50");
+        test.expect_screen("SuccessScreen");
+
+        assert!(sample(views) > views_before);
+        assert!(sample(calls) > calls_before);
+        assert!(sample(exits) > exits_before);
+        assert!(sample("ussd_requests_total{service_code=\"*123#\"}") >= 4);
+    }
+}
@@ -1,9 +1,20 @@
-pub use crate::core::USSDMenu;
-pub use crate::core::USSDRequest;
-pub use crate::core::USSDResponse;
-pub use crate::core::USSDService;
-pub use crate::core::{SessionCache, USSDSession};
-pub use crate::types::{FunctionMap, Stack, USSDData, USSDFunction};
+pub use crate::analytics::SessionJourney;
+pub use crate::core::{
+    AsyncSessionCache, SessionCache, SessionError, SessionExpiry, SessionSettings,
+    SqliteSessionStore, SyncSessionCache, USSDSession,
+};
+pub use crate::core::{ConfirmChoice, USSDConfirm, USSDDynamicMenu, USSDMenu, USSDShortcut};
+pub use crate::core::{ErrorClass, ErrorResponse, ErrorSettings, MenuValidationError, UssdError};
+pub use crate::core::{InputType, InputValidation, InputValidationError};
+pub use crate::core::{LocalizedText, PaginationSettings};
+pub use crate::core::{ServiceErrorKind, ServiceKind, ServiceOutcome, USSDService};
+pub use crate::core::{USSDRequest, USSDResponse};
+pub use crate::types::{
+    AsyncFunctionHandler, AsyncFunctionMap, ExpiryHook, FunctionHandler, FunctionMap, JourneyHook,
+    SessionFuture, SessionHook, Stack, USSDAsyncFunction, USSDData, USSDFunction, USSDFuture,
+};
+pub use crate::utils::{Expression, ExpressionError, ExpressionValue};
+pub use crate::utils::{FunctionRegistry, Template, TemplateError};
 pub use crate::UssdApp;
 
 #[cfg(feature = "menubuilder")]
@@ -0,0 +1,452 @@
+//! Helpers for testing menus with scripted conversations.
+//!
+//! A `ConversationTest` drives a `USSDMenu` the way a user would, by dialling and then sending
+//! inputs, and checks the messages displayed and the state of the session along the way. The same
+//! conversations can be written as YAML or JSON `Scenario` files.
+
+pub mod scenario;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    core::{ServiceKind, USSDMenu, USSDRequest, USSDResponse, USSDSession},
+    types::{json_to_hash_str_any, FunctionMap, USSDData},
+    UssdApp,
+};
+
+pub use scenario::{Scenario, ScenarioStep};
+
+/// What to check after a request. Checks that are not set are skipped.
+///
+/// # Fields
+///
+/// * `message`: The exact message displayed.
+/// * `contains`: Text the message must contain.
+/// * `end_session`: Whether the response ends the session.
+/// * `screen`: The screen the session is on.
+/// * `data`: Values of the session data, by key.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Expectation {
+    pub message: Option<String>,
+    pub contains: Vec<String>,
+    pub end_session: Option<bool>,
+    pub screen: Option<String>,
+    pub data: HashMap<String, Value>,
+}
+
+/// Drives a USSD menu through a scripted conversation.
+///
+/// Each conversation uses its own `UssdApp`, with its own function registry and in-memory session
+/// store. Services can be mocked for the duration of the test, either with fixed data or with
+/// another function, without affecting other tests running in parallel. The
+/// `expect_*` methods panic with a description of the mismatch, so they can be used in tests
+/// like `assert!`.
+///
+/// # Example
+///
+/// ```rust
+/// use serde_json::json;
+/// use ussdframework::prelude::*;
+/// use ussdframework::testing::ConversationTest;
+///
+/// let menus = USSDMenu::load_from_json("examples/data/menu.json").unwrap();
+///
+/// let mut test = ConversationTest::new(menus)
+///     .mock_data("buy_airtime", json!({"status": "success", "message": "Airtime sent"}));
+///
+/// test.dial().expect_contains("Main Menu").expect_end_session(false);
+/// test.input("3").input("1").input("50");
+/// test.expect_screen("SuccessScreen")
+///     .expect_end_session(true)
+///     .expect_data("amount", json!("50"));
+/// ```
+pub struct ConversationTest {
+    app: UssdApp,
+    menu: USSDMenu,
+    request: USSDRequest,
+    response: Option<USSDResponse>,
+    ended_session: Arc<Mutex<Option<USSDSession>>>,
+}
+
+impl ConversationTest {
+    /// Creates a conversation test for the menu.
+    pub fn new(menu: USSDMenu) -> Self {
+        let mut app = UssdApp::new(true, None);
+
+        // Keep the final session so it can be checked once the session has ended
+        let ended_session = Arc::new(Mutex::new(None));
+        let hook_session = ended_session.clone();
+        app.on_session_end(move |session: &USSDSession| {
+            *hook_session.lock().unwrap() = Some(session.clone());
+        });
+
+        ConversationTest {
+            app,
+            menu,
+            request: USSDRequest {
+                msisdn: "1234567890".to_string(),
+                session_id: "test-session".to_string(),
+                input: String::new(),
+                service_code: String::new(),
+                language: String::new(),
+            },
+            response: None,
+            ended_session,
+        }
+    }
+
+    /// Registers the functions used by the menu's services.
+    pub fn with_functions(mut self, functions: FunctionMap) -> Self {
+        self.app.register_functions(functions);
+        self
+    }
+
+    /// Sets the phone number the conversation is held from.
+    pub fn msisdn(mut self, msisdn: &str) -> Self {
+        self.request.msisdn = msisdn.to_string();
+        self
+    }
+
+    /// Sets the language of the conversation.
+    pub fn language(mut self, language: &str) -> Self {
+        self.request.language = language.to_string();
+        self
+    }
+
+    /// Sets the USSD code dialled.
+    pub fn service_code(mut self, service_code: &str) -> Self {
+        self.request.service_code = service_code.to_string();
+        self
+    }
+
+    /// Replaces the function of a service with another function for this test.
+    ///
+    /// # Arguments
+    ///
+    /// * `service` - The name of the service in the menu's `services`.
+    /// * `function` - The function called instead.
+    pub fn mock_function<F>(mut self, service: &str, function: F) -> Self
+    where
+        F: Fn(&USSDSession, &str) -> USSDData + Send + Sync + 'static,
+    {
+        let name = format!("mock:{}", service);
+
+        self.app.functions.register(&name, function);
+        self.replace_service(service, &name);
+        self
+    }
+
+    /// Replaces a service with fixed data for this test.
+    ///
+    /// # Arguments
+    ///
+    /// * `service` - The name of the service in the menu's `services`.
+    /// * `data` - The data the service returns, converted to `USSDData`.
+    pub fn mock_data(self, service: &str, data: Value) -> Self {
+        let data = json_to_hash_str_any(data);
+
+        self.mock_function(service, move |_session: &USSDSession, _url: &str| {
+            data.clone()
+        })
+    }
+
+    /// Points a service of the test's menu at another function.
+    fn replace_service(&mut self, service: &str, function_name: &str) {
+        match self.menu.services.get_mut(service) {
+            Some(service) => {
+                service.kind = ServiceKind::Function;
+                service.function_name = function_name.to_string();
+            }
+            None => panic!("Cannot mock unknown service: {}", service),
+        }
+    }
+
+    /// Dials the service code, starting a new session.
+    pub fn dial(&mut self) -> &mut Self {
+        self.app
+            .session_cache
+            .delete_session(&self.request.session_id)
+            .unwrap();
+        *self.ended_session.lock().unwrap() = None;
+
+        self.input("")
+    }
+
+    /// Sends an input in the current session.
+    pub fn input(&mut self, input: &str) -> &mut Self {
+        self.request.input = input.to_string();
+        self.response = Some(self.app.run(self.request.clone(), &self.menu));
+        self
+    }
+
+    /// Returns the USSD application the conversation runs on.
+    pub fn app(&self) -> &UssdApp {
+        &self.app
+    }
+
+    /// Returns the USSD application the conversation runs on, to change its settings or hooks.
+    pub fn app_mut(&mut self) -> &mut UssdApp {
+        &mut self.app
+    }
+
+    /// Returns the latest response.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if nothing has been dialled yet.
+    pub fn response(&self) -> &USSDResponse {
+        self.response.as_ref().expect("No response yet, dial first")
+    }
+
+    /// Returns the session of the conversation, or the final session if it has ended.
+    pub fn session(&self) -> Option<USSDSession> {
+        if let Some(session) = self.ended_session.lock().unwrap().clone() {
+            return Some(session);
+        }
+
+        self.app
+            .session_cache
+            .retrieve_session(&self.request.session_id)
+            .unwrap()
+    }
+
+    /// Checks the latest response and the session against the expectation.
+    ///
+    /// # Returns
+    ///
+    /// A description of every mismatch, if any.
+    pub fn check(&self, expectation: &Expectation) -> Result<(), String> {
+        let response = self.response();
+        let session = self.session();
+        let mut failures = Vec::new();
+
+        if let Some(message) = &expectation.message {
+            if &response.message != message {
+                failures.push(format!(
+                    "expected message {:?}, got {:?}",
+                    message, response.message
+                ));
+            }
+        }
+
+        for text in expectation.contains.iter() {
+            if !response.message.contains(text.as_str()) {
+                failures.push(format!(
+                    "expected message to contain {:?}, got {:?}",
+                    text, response.message
+                ));
+            }
+        }
+
+        if let Some(end_session) = expectation.end_session {
+            if response.end_session != end_session {
+                failures.push(format!(
+                    "expected end_session {}, got {}",
+                    end_session, response.end_session
+                ));
+            }
+        }
+
+        if let Some(screen) = &expectation.screen {
+            let current_screen = session.as_ref().map(|session| &session.current_screen);
+            if current_screen != Some(screen) {
+                failures.push(format!(
+                    "expected screen {:?}, got {:?}",
+                    screen, current_screen
+                ));
+            }
+        }
+
+        for (key, value) in expectation.data.iter() {
+            let expected = json_to_hash_str_any(value.clone());
+            let actual = session.as_ref().and_then(|session| session.data.get(key));
+            if actual != Some(&expected) {
+                failures.push(format!(
+                    "expected data {:?} to be {:?}, got {:?}",
+                    key, expected, actual
+                ));
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures.join("\n"))
+        }
+    }
+
+    /// Panics if the latest response and session do not meet the expectation.
+    pub fn expect(&mut self, expectation: &Expectation) -> &mut Self {
+        if let Err(failures) = self.check(expectation) {
+            panic!("{}", failures);
+        }
+        self
+    }
+
+    /// Panics if the latest message is not exactly `message`.
+    pub fn expect_message(&mut self, message: &str) -> &mut Self {
+        self.expect(&Expectation {
+            message: Some(message.to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// Panics if the latest message does not contain `text`.
+    pub fn expect_contains(&mut self, text: &str) -> &mut Self {
+        self.expect(&Expectation {
+            contains: vec![text.to_string()],
+            ..Default::default()
+        })
+    }
+
+    /// Panics if the latest response does not end the session, or ends it when it should not.
+    pub fn expect_end_session(&mut self, end_session: bool) -> &mut Self {
+        self.expect(&Expectation {
+            end_session: Some(end_session),
+            ..Default::default()
+        })
+    }
+
+    /// Panics if the session is not on `screen`.
+    pub fn expect_screen(&mut self, screen: &str) -> &mut Self {
+        self.expect(&Expectation {
+            screen: Some(screen.to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// Panics if the session data under `key` is not `value`.
+    pub fn expect_data(&mut self, key: &str, value: Value) -> &mut Self {
+        let mut data = HashMap::new();
+        data.insert(key.to_string(), value);
+
+        self.expect(&Expectation {
+            data,
+            ..Default::default()
+        })
+    }
+}
+
+/// Loads the example menu the tests run conversations on.
+#[cfg(test)]
+pub(crate) fn example_menu() -> USSDMenu {
+    USSDMenu::load_from_json("examples/data/menu.json").unwrap()
+}
+
+/// A session cache whose store is unreachable. Deleting succeeds, so conversations can still dial.
+#[cfg(test)]
+pub(crate) struct UnreachableCache;
+
+#[cfg(test)]
+impl crate::core::SessionCache for UnreachableCache {
+    fn store_session(&self, _session: &USSDSession) -> Result<(), String> {
+        Err("connection refused".to_string())
+    }
+
+    fn retrieve_session(&self, _session_id: &str) -> Result<Option<USSDSession>, String> {
+        Err("connection refused".to_string())
+    }
+
+    fn delete_session(&self, _session_id: &str) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn failed_airtime(_session: &USSDSession, _url: &str) -> USSDData {
+        json_to_hash_str_any(json!({"status": "failed", "message": "Insufficient balance"}))
+    }
+
+    #[test]
+    fn test_conversation_with_mock_data() {
+        let mut test = ConversationTest::new(example_menu()).mock_data(
+            "buy_airtime",
+            json!({"status": "success", "message": "Airtime sent"}),
+        );
+
+        test.dial()
+            .expect_contains("Main Menu")
+            .expect_end_session(false)
+            .expect_screen("MainScreen");
+
+        test.input("3").input("1").input("50");
+        test.expect_message("Transaction Successful Airtime sent")
+            .expect_end_session(true)
+            .expect_screen("SuccessScreen")
+            .expect_data("amount", json!("50"));
+
+        assert!(test
+            .check(&Expectation {
+                end_session: Some(false),
+                ..Default::default()
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_conversation_with_mock_function() {
+        let mut test =
+            ConversationTest::new(example_menu()).mock_function("buy_airtime", failed_airtime);
+
+        test.dial().input("3").input("1").input("50");
+        test.expect_screen("FailureScreen")
+            .expect_contains("Insufficient balance");
+    }
+
+    #[test]
+    fn test_scenario_from_yaml() {
+        let scenario = Scenario::from_yaml(
+            r#"
+name: Buy airtime for own number
+mocks:
+  buy_airtime:
+    status: success
+    message: Airtime sent
+dial:
+  contains: ["Main Menu"]
+  end_session: false
+steps:
+  - input: "3"
+    contains: ["Own Number"]
+  - input: "1"
+  - input: "50"
+    end_session: true
+    screen: SuccessScreen
+    data:
+      amount: "50"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(scenario.steps.len(), 3);
+        assert_eq!(scenario.run(&example_menu()), Ok(()));
+
+        let mut failing = scenario.clone();
+        failing.steps[2].expect.screen = Some("FailureScreen".to_string());
+        let error = failing.run(&example_menu()).unwrap_err();
+        assert!(error.contains("step 3"), "{}", error);
+    }
+
+    #[test]
+    fn test_example_scenarios() {
+        for path in [
+            "examples/data/scenarios/buy_airtime.yaml",
+            "examples/data/scenarios/buy_airtime_failed.json",
+        ] {
+            let scenario = Scenario::from_file(path).unwrap();
+            assert_eq!(scenario.run(&example_menu()), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::{collections::HashMap, error::Error, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::core::USSDMenu;
+
+use super::{ConversationTest, Expectation};
+
+/// A scripted conversation, written as a YAML or JSON file.
+///
+/// The scenario dials the service code, checks the `dial` expectation, then sends the input of
+/// each step in turn and checks the step's expectation. Services listed in `mocks` return the
+/// given data instead of being called.
+///
+/// # Fields
+///
+/// * `name`: The name of the scenario, used in failure messages.
+/// * `msisdn`: The phone number the conversation is held from.
+/// * `service_code`: The USSD code dialled.
+/// * `language`: The language of the conversation.
+/// * `mocks`: The data returned by mocked services, by service name.
+/// * `dial`: What to check after dialling.
+/// * `steps`: The inputs sent after dialling, with what to check after each.
+///
+/// # Example
+///
+/// ```yaml
+/// name: Buy airtime for own number
+/// mocks:
+///   buy_airtime:
+///     status: success
+///     message: Airtime sent
+/// dial:
+///   contains: ["Main Menu"]
+/// steps:
+///   - input: "3"
+///   - input: "1"
+///   - input: "50"
+///     end_session: true
+///     screen: SuccessScreen
+///     data:
+///       amount: "50"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Scenario {
+    pub name: String,
+    pub msisdn: String,
+    pub service_code: String,
+    pub language: String,
+    pub mocks: HashMap<String, Value>,
+    pub dial: Expectation,
+    pub steps: Vec<ScenarioStep>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario {
+            name: String::new(),
+            msisdn: "1234567890".to_string(),
+            service_code: String::new(),
+            language: String::new(),
+            mocks: HashMap::new(),
+            dial: Expectation::default(),
+            steps: Vec::new(),
+        }
+    }
+}
+
+/// An input of a scenario, with what to check after it is sent.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ScenarioStep {
+    pub input: String,
+    #[serde(flatten)]
+    pub expect: Expectation,
+}
+
+impl Scenario {
+    /// Loads a scenario from a YAML or JSON file, depending on its extension.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => Scenario::from_yaml(&contents),
+            _ => Scenario::from_json(&contents),
+        }
+    }
+
+    /// Parses a scenario from YAML.
+    pub fn from_yaml(contents: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_yaml::from_str(contents)?)
+    }
+
+    /// Parses a scenario from JSON.
+    pub fn from_json(contents: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(contents)?)
+    }
+
+    /// Runs the scenario against the menu.
+    ///
+    /// The scenario stops at the first step whose expectation is not met.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` that is `Err` with a description of the failed step and its mismatches.
+    pub fn run(&self, menu: &USSDMenu) -> Result<(), String> {
+        let mut test = ConversationTest::new(menu.clone())
+            .msisdn(&self.msisdn)
+            .service_code(&self.service_code)
+            .language(&self.language);
+
+        for (service, data) in self.mocks.iter() {
+            if !menu.services.contains_key(service) {
+                return Err(format!(
+                    "{}: cannot mock unknown service {:?}",
+                    self.name, service
+                ));
+            }
+            test = test.mock_data(service, data.clone());
+        }
+
+        test.dial();
+        test.check(&self.dial)
+            .map_err(|failures| format!("{}: dial\n{}", self.name, failures))?;
+
+        for (index, step) in self.steps.iter().enumerate() {
+            test.input(&step.input);
+            test.check(&step.expect).map_err(|failures| {
+                format!(
+                    "{}: step {} (input {:?})\n{}",
+                    self.name,
+                    index + 1,
+                    step.input,
+                    failures
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,9 +1,12 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, future::Future, pin::Pin, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::core::USSDSession;
+use crate::{
+    analytics::SessionJourney,
+    core::{SessionError, USSDSession},
+};
 
 /// Function signature for USSD functions
 /// The function signature is a function that takes a USSDRequest and a string as arguments
@@ -30,6 +33,96 @@ pub type USSDFunction = fn(&USSDSession, &str) -> USSDData;
 /// Key-value map of USSD functions
 pub type FunctionMap = HashMap<String, USSDFunction>;
 
+/// Boxed future returned by async USSD functions
+/// The future is `Send`, so it can be spawned on a multi-threaded runtime.
+pub type USSDFuture = Pin<Box<dyn Future<Output = USSDData> + Send>>;
+
+/// Boxed future returned by the methods of an `AsyncSessionCache`
+pub type SessionFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, SessionError>> + Send + 'a>>;
+
+/// Function signature for async USSD functions
+/// The function takes an owned copy of the session and the function url, and returns a boxed future
+/// so it can await network calls without blocking the worker it runs on.
+///
+/// # Arguments
+///
+/// * `session` - The USSD session
+/// * `url` - The function url set on the service
+///
+/// # Returns
+///
+/// A future resolving to a USSDData value
+///
+/// # Example
+///
+/// ```
+/// use ussdframework::prelude::*;
+///
+/// fn fetch_balance(session: USSDSession, url: String) -> USSDFuture {
+///     Box::pin(async move {
+///         USSDData::Str(format!("Balance for {} from {}", session.msisdn, url))
+///     })
+/// }
+/// ```
+pub type USSDAsyncFunction = fn(USSDSession, String) -> USSDFuture;
+
+/// Hook called when a session starts or ends
+/// The hook receives the session, so it can write audit records or release resources held for it.
+///
+/// # Example
+///
+/// ```
+/// use ussdframework::prelude::*;
+///
+/// let hook: SessionHook = Box::new(|session: &USSDSession| {
+///     println!("Session {} ended on {}", session.session_id, session.current_screen);
+/// });
+/// ```
+pub type SessionHook = Box<dyn Fn(&USSDSession) + Send + Sync>;
+
+/// Hook called with the journey of a session when it ends or expires
+///
+/// # Example
+///
+/// ```
+/// use ussdframework::prelude::*;
+///
+/// let hook: JourneyHook = Box::new(|journey: &SessionJourney| {
+///     println!("{} left on {}", journey.session_id, journey.last_screen);
+/// });
+/// ```
+pub type JourneyHook = Box<dyn Fn(&SessionJourney) + Send + Sync>;
+
+/// Hook called by a session cache with each session it removes after the session expired
+/// `UssdApp` passes one to its session cache, so sessions that are never dialled again are still
+/// reported as abandoned.
+pub type ExpiryHook = Arc<dyn Fn(&USSDSession) + Send + Sync>;
+
+/// Key-value map of async USSD functions
+pub type AsyncFunctionMap = HashMap<String, USSDAsyncFunction>;
+
+/// Shared handler for USSD functions
+/// Unlike `USSDFunction`, the handler can be a closure capturing state such as a database pool,
+/// an HTTP client or configuration.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use ussdframework::prelude::*;
+///
+/// let greeting = String::from("Hello");
+///
+/// let handler: FunctionHandler = Arc::new(move |session: &USSDSession, _url: &str| {
+///     USSDData::Str(format!("{} {}", greeting, session.msisdn))
+/// });
+/// ```
+pub type FunctionHandler = Arc<dyn Fn(&USSDSession, &str) -> USSDData + Send + Sync>;
+
+/// Shared handler for async USSD functions
+/// The async counterpart of `FunctionHandler`, which can capture state like it.
+pub type AsyncFunctionHandler = Arc<dyn Fn(USSDSession, String) -> USSDFuture + Send + Sync>;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RouterOptions {
     List(Vec<RouterOptions>),
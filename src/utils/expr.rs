@@ -1,214 +1,116 @@
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display};
+use std::sync::{Arc, RwLock};
+
 use crate::core::USSDSession;
-use crate::types::USSDData;
-use crate::{debug, info};
-
-fn get_nested_value(data: &USSDData, field: &[&str]) -> Option<String> {
-    match data {
-        USSDData::Str(value) => Some(value.clone()),
-        USSDData::Dict(inner_data) => {
-            if field.is_empty() {
-                return None;
-            }
+use crate::{error, info};
 
-            if let Some(inner_value) = inner_data.get(field[0]) {
-                get_nested_value(inner_value, &field[1..])
-            } else {
-                None
-            }
-        }
-        USSDData::ListStr(value) => Some(value.join(", ")),
-        _ => None,
-    }
+use super::{Expression, Template};
+
+/// Returns the expression between the first `{{` and `}}` of a router option, if any.
+pub fn router_condition(text: &str) -> Option<&str> {
+    let start = text.find("{{")? + 2;
+    let end = text[start..].find("}}")? + start;
+    Some(&text[start..end])
 }
 
-/// Function to evaluate an expression
-///
-/// This function takes a string and a session and evaluates the string as an expression
-///
-/// # Arguments
-///
-/// * `text` - A string to be evaluated
-/// * `session` - A reference to a USSD session
+/// The parsed forms of the templates or expressions of a menu entry, by their source text.
 ///
-/// # Returns
-///
-/// * A string with the evaluated expression
-///
-/// # Example
-///
-/// ```rust
-/// use crate::utils::evaluate_expression;
-/// use crate::ussd_session::USSDSession;
-/// use std::collections::HashMap;
-///
-/// let mut session = USSDSession::new();
-/// let mut data = HashMap::new();
-/// data.insert("name".to_string(), "John".to_string());
-/// session.data = data;
-///
-/// let text = "Hello {{name}}";
-/// let evaluated_text = evaluate_expression(text, &session);
-/// assert_eq!(evaluated_text, "Hello John");
-/// ```
-pub fn evaluate_expression(text: &str, session: &USSDSession) -> String {
-    // let pattern = regex::Regex::new(r"\{\{(\w+)(?:\.(\w+))?\}\}").unwrap(); // Updated regex pattern
-    let pattern = regex::Regex::new(r"\{\{([\w.]+)\}\}").unwrap();
-
-    let evaluated_text = pattern.replace_all(text, |caps: &regex::Captures| {
-        // Closure to handle expression evaluation
-        let _field = caps.get(1).unwrap().as_str(); // Extract the entire field string including dots
-
-        let _field_parts: Vec<&str> = _field.split('.').collect();
-
-        let object = _field_parts[0]; // Extract the object name
+/// Menu texts are parsed the first time they are used, or when the menu is validated, and the
+/// parsed form is reused after that. Since entries are looked up by their source, text changed
+/// after it was parsed is parsed again.
+pub(crate) struct Parsed<T> {
+    parsed: RwLock<HashMap<String, Result<Arc<T>, String>>>,
+}
 
-        let fields = &_field_parts[1..]; // Extract the field name if exists
+impl<T> Parsed<T> {
+    /// Returns the parsed form of `source`, parsing it with `parse` the first time.
+    ///
+    /// Sources that do not parse return the parse error as text.
+    pub(crate) fn get_or_parse<E: Display>(
+        &self,
+        source: &str,
+        parse: impl FnOnce(&str) -> Result<T, E>,
+    ) -> Result<Arc<T>, String> {
+        if let Some(parsed) = self.parsed.read().unwrap().get(source) {
+            return parsed.clone();
+        }
 
-        info!(
-            "Evaluating expression: field: {} object: {} nested fields: {:?}",
-            _field, object, fields
-        );
+        let parsed = parse(source).map(Arc::new).map_err(|e| e.to_string());
+        self.parsed
+            .write()
+            .unwrap()
+            .insert(source.to_string(), parsed.clone());
+        parsed
+    }
+}
 
-        if let Some(data_object) = session.data.get(object) {
-            match data_object {
-                USSDData::Dict(inner_data) => {
-                    if fields.is_empty() {
-                        // If field is empty, directly look up the object key
-                        if let Some(inner_value) = inner_data.get(object) {
-                            return get_nested_value(inner_value, &[object])
-                                .unwrap_or_else(|| caps.get(0).unwrap().as_str().to_string());
-                        }
-                    } else {
-                        return get_nested_value(data_object, fields)
-                            .unwrap_or_else(|| caps.get(0).unwrap().as_str().to_string());
-                    }
-                }
-                USSDData::Str(value) => {
-                    if fields.is_empty() {
-                        return value.to_string(); // Replace with value if found
-                    }
-                }
-                _ => {}
+impl Parsed<Template> {
+    /// Renders a string as a template, parsing it once.
+    ///
+    /// See `Template` for the syntax. Strings that do not parse are returned as they are.
+    pub(crate) fn render(&self, text: &str, session: &USSDSession) -> String {
+        match self.get_or_parse(text, Template::parse) {
+            Ok(template) => template.render(session),
+            Err(e) => {
+                error!("Invalid template {}: {}", text, e);
+                text.to_string()
             }
         }
-        caps.get(0).unwrap().as_str().to_string() // Keep original if not found
-    });
-    evaluated_text.to_string()
+    }
 }
 
-/// Function to evaluate an expression with an operator
-///
-/// This function takes a string and a session and evaluates the string as an expression with an operator
-///
-/// # Arguments
-///
-/// * `session` - A reference to a USSD session
-/// * `text` - A string to be evaluated
-///
-/// # Returns
-///
-/// * A boolean representing the result of the evaluation
-///
-/// # Example
-///
-/// ```rust
-/// use crate::utils::evaluate_expression_op;
-/// use crate::ussd_session::USSDSession;
-///
-/// let session = USSDSession::new();
-/// let text = "Is John? {{name == 'John'}}";
-/// let result = evaluate_expression_op(&session, text);
-/// assert_eq!(result, true);
-/// ```
-pub fn evaluate_expression_op(session: &USSDSession, text: &str) -> bool {
-    // let pattern_str = r"\{\{(\w+)(?:\.(\w+))?(?:\s*(==|>|>=|<|<=)\s*\'?(\w+)\'?)?\}\}";
-    let pattern_str = r"\{\{([\w.]+)(?:\s*(==|>|>=|<|<=)\s*\'?(\w+)\'?)?\}\}";
-    let pattern = regex::Regex::new(pattern_str).unwrap();
-
-    let matched = pattern.captures(text);
-
-    if let Some(caps) = matched {
-        // let object = caps.get(1).unwrap().as_str();
-        // let field = caps.get(2).map_or("", |m| m.as_str());
-        // let operator = caps.get(3).map_or("", |m| m.as_str());
-        // let value = caps.get(4).map_or("", |m| m.as_str());
-
-        let field = caps.get(1).unwrap().as_str(); // Extract the entire field string including dots
-
-        let _field_parts: Vec<&str> = field.split('.').collect();
-
-        let object = _field_parts[0]; // Extract the object name
-
-        let fields = &_field_parts[1..]; // Extract the field name if exists
-
-        let operator = caps.get(2).map_or("", |m| m.as_str());
-        let value = caps.get(3).map_or("", |m| m.as_str());
-
-        debug!(
-            "Field: {}, object: {}, fields: {:?}, operator: {}, value: {}",
-            field, object, fields, operator, value
-        );
+impl Parsed<Expression> {
+    /// Evaluates the first `{{ }}` expression of a router option, parsing the expression once.
+    ///
+    /// See `Expression` for the language. Strings without an expression, and expressions that do
+    /// not parse, evaluate to false.
+    pub(crate) fn is_true(&self, session: &USSDSession, text: &str) -> bool {
+        let Some(condition) = router_condition(text) else {
+            return false;
+        };
+
+        match self.get_or_parse(condition, Expression::parse) {
+            Ok(expression) => {
+                let result = expression.is_true(session);
+                info!("Evaluating expression: {} = {}", condition, result);
+                result
+            }
+            Err(e) => {
+                error!("Invalid expression {}: {}", condition, e);
+                false
+            }
+        }
+    }
+}
 
-        info!(
-            "Evaluating expression: object: {}, field: {}, operator: {}, value: {}",
-            object, field, operator, value
-        );
+impl<T> Default for Parsed<T> {
+    fn default() -> Self {
+        Parsed {
+            parsed: RwLock::new(HashMap::new()),
+        }
+    }
+}
 
-        if let Some(data_object) = session.data.get(object) {
-            match data_object {
-                USSDData::Dict(inner_data) => {
-                    if fields.is_empty() {
-                        debug!("Field is empty");
-                        if let Some(inner_value) = inner_data.get(object) {
-                            let val = get_nested_value(inner_value, &[object]);
-                            if let Some(val) = val {
-                                return compare_strings(operator, &val, value);
-                            }
-                        }
-                    } else {
-                        debug!("Field is not empty");
-                        let val = get_nested_value(data_object, fields);
-                        if let Some(val) = val {
-                            return compare_strings(operator, &val, value);
-                        }
-                    }
-                }
-                USSDData::Str(data_value) => {
-                    return compare_strings(operator, data_value, value);
-                }
-                _ => {}
-            }
+impl<T> Clone for Parsed<T> {
+    fn clone(&self) -> Self {
+        Parsed {
+            parsed: RwLock::new(self.parsed.read().unwrap().clone()),
         }
     }
-    false
 }
 
-/// Function to compare strings
-/// This function takes an operator and two strings and compares them
-/// # Arguments
-/// * `operator` - A string representing the operator
-/// * `left` - A string representing the left operand
-/// * `right` - A string representing the right operand
-/// # Returns
-/// * A boolean representing the result of the comparison
-/// # Example
-/// ```rust
-/// use crate::utils::compare_strings;
-/// let result = compare_strings("==", "John", "John");
-/// assert_eq!(result, true);
-/// ```
-fn compare_strings(operator: &str, left: &str, right: &str) -> bool {
-    info!(
-        "Comparing strings: left: {}, right: {}, operator: {}",
-        left, right, operator
-    );
-    match operator {
-        "==" => left == right,
-        ">" => left > right,
-        ">=" => left >= right,
-        "<" => left < right,
-        "<=" => left <= right,
-        _ => false,
+impl<T> Debug for Parsed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Parsed").finish_non_exhaustive()
+    }
+}
+
+/// The parsed forms are derived from the entry they belong to, so they never make two entries
+/// differ.
+impl<T> PartialEq for Parsed<T> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
     }
 }
 
@@ -218,6 +120,14 @@ mod tests {
     use crate::types::USSDData;
     use std::collections::HashMap;
 
+    fn evaluate_expression(text: &str, session: &USSDSession) -> String {
+        Parsed::default().render(text, session)
+    }
+
+    fn evaluate_expression_op(session: &USSDSession, text: &str) -> bool {
+        Parsed::default().is_true(session, text)
+    }
+
     fn new_session() -> USSDSession {
         USSDSession::new(
             "1234".to_string(),
@@ -495,4 +405,40 @@ mod tests {
         let evaluated_text = evaluate_expression_op(&session, text);
         assert_eq!(evaluated_text, false);
     }
+
+    #[test]
+    fn test_parsed_expression_parses_once() {
+        let parsed: Parsed<Expression> = Parsed::default();
+        let mut parses = 0;
+        let mut parse = |source: &str| {
+            parses += 1;
+            Expression::parse(source)
+        };
+
+        assert!(parsed.get_or_parse("age > '20'", &mut parse).is_ok());
+        assert!(parsed.get_or_parse("age > '20'", &mut parse).is_ok());
+        assert!(parsed.get_or_parse("age >", &mut parse).is_err());
+        assert_eq!(parses, 2);
+
+        let mut session = new_session();
+        session
+            .data
+            .insert("age".to_string(), USSDData::Str("30".to_string()));
+        assert!(parsed.is_true(&session, "Over 20? {{age > '20'}}"));
+        assert!(!parsed.is_true(&session, "Broken {{age >}}"));
+    }
+
+    #[test]
+    fn test_parsed_template_renders_changed_text() {
+        let parsed: Parsed<Template> = Parsed::default();
+        let mut session = new_session();
+        session
+            .data
+            .insert("name".to_string(), USSDData::Str("John".to_string()));
+
+        assert_eq!(parsed.render("Hello {{name}}", &session), "Hello John");
+        assert_eq!(parsed.render("Hello {{name}}", &session), "Hello John");
+        assert_eq!(parsed.render("Bye {{name}}", &session), "Bye John");
+        assert_eq!(parsed.render("Bye {{name", &session), "Bye {{name");
+    }
 }
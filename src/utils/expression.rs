@@ -0,0 +1,814 @@
+use std::{cmp::Ordering, collections::BTreeMap, fmt::Display};
+
+use crate::{core::USSDSession, types::USSDData};
+
+/// A parsed expression, as written between `{{` and `}}` in router options.
+///
+/// Expressions are parsed once and can be evaluated against any session. Names refer to the
+/// session data, with `.field` for nested fields and `[index]` for list items. A name that is not
+/// in the session data evaluates to `null`.
+///
+/// The language supports, from the loosest to the tightest binding:
+///
+/// * `||` and `&&`: boolean logic, evaluated left to right and stopping as soon as the result is known.
+/// * `==` and `!=`: equality. Values are compared as numbers when either side is a number.
+/// * `<`, `<=`, `>`, `>=`, `in` and `contains`: ordering, and membership in lists, dictionaries and strings.
+///   Strings that both hold numbers are ordered as numbers.
+/// * `+`, `-`, `*`, `/` and `%`: arithmetic. `+` joins strings that are not numbers.
+/// * `!` and `-`: negation.
+/// * Literals: numbers such as `900` or `2.5`, strings in single or double quotes, `true`,
+///   `false`, `null` and lists such as `['MTN', 'Vodafone']`.
+///
+/// # Example
+///
+/// ```
+/// use ussdframework::prelude::*;
+///
+/// let mut session = USSDSession::new(
+///     "session".to_string(),
+///     "MainScreen".to_string(),
+///     "en".to_string(),
+///     "1234567890".to_string(),
+/// );
+/// session.data.insert("amount".to_string(), USSDData::Str("95".to_string()));
+///
+/// let expression = Expression::parse("amount > 900 || (amount >= 50 && amount != null)").unwrap();
+/// assert!(expression.is_true(&session));
+///
+/// assert!(Expression::parse("amount >").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    source: String,
+    node: Node,
+}
+
+/// The value of an evaluated expression.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ExpressionValue {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+    List(Vec<ExpressionValue>),
+    Dict(BTreeMap<String, ExpressionValue>),
+}
+
+/// An error found while parsing an expression.
+///
+/// # Fields
+///
+/// * `message`: What is wrong with the expression.
+/// * `position`: The character offset in the expression where the error was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpressionError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl Display for ExpressionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Literal(ExpressionValue),
+    Variable(String),
+    Field(Box<Node>, String),
+    Index(Box<Node>, Box<Node>),
+    List(Vec<Node>),
+    Not(Box<Node>),
+    Negate(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    In,
+    Contains,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Name(String),
+    Symbol(&'static str),
+    End,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "'{}'", number),
+            Token::Str(value) => write!(f, "string '{}'", value),
+            Token::Name(name) => write!(f, "'{}'", name),
+            Token::Symbol(symbol) => write!(f, "'{}'", symbol),
+            Token::End => write!(f, "end of expression"),
+        }
+    }
+}
+
+const SYMBOLS: [&str; 20] = [
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "+", "-", "*", "/", "%", "(", ")", "[", "]",
+    ",", ".",
+];
+
+impl Expression {
+    /// Parses an expression.
+    ///
+    /// # Returns
+    ///
+    /// The parsed expression, or an `ExpressionError` pointing at the first problem found.
+    pub fn parse(source: &str) -> Result<Self, ExpressionError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, current: 0 };
+
+        let node = parser.parse_or()?;
+        let (token, position) = parser.peek();
+        if *token != Token::End {
+            return Err(ExpressionError {
+                message: format!("Unexpected {}", token),
+                position: *position,
+            });
+        }
+
+        Ok(Expression {
+            source: source.to_string(),
+            node,
+        })
+    }
+
+    /// Returns the source the expression was parsed from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluates the expression against the session data.
+    pub fn evaluate(&self, session: &USSDSession) -> ExpressionValue {
+        self.evaluate_with(&|name: &str| {
+            session
+                .data
+                .get(name)
+                .map(ExpressionValue::from)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Evaluates the expression, looking up the value of each name with `resolve`.
+    pub fn evaluate_with(&self, resolve: &dyn Fn(&str) -> ExpressionValue) -> ExpressionValue {
+        evaluate(&self.node, resolve)
+    }
+
+    /// Evaluates the expression and returns whether the result is truthy.
+    ///
+    /// `null`, `false`, `0`, empty strings, empty lists and empty dictionaries are false, every
+    /// other value is true.
+    pub fn is_true(&self, session: &USSDSession) -> bool {
+        self.evaluate(session).is_truthy()
+    }
+}
+
+impl ExpressionValue {
+    /// Returns whether the value counts as true in conditions.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            ExpressionValue::Null => false,
+            ExpressionValue::Bool(value) => *value,
+            ExpressionValue::Number(number) => *number != 0.0,
+            ExpressionValue::Str(value) => !value.is_empty(),
+            ExpressionValue::List(items) => !items.is_empty(),
+            ExpressionValue::Dict(fields) => !fields.is_empty(),
+        }
+    }
+
+    /// Returns the value as a number, if it is a number or a string holding one.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            ExpressionValue::Number(number) => Some(*number),
+            ExpressionValue::Str(value) => value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite()),
+            _ => None,
+        }
+    }
+
+    fn equals(&self, other: &ExpressionValue) -> bool {
+        match (self, other) {
+            (ExpressionValue::Null, ExpressionValue::Null) => true,
+            (ExpressionValue::Null, _) | (_, ExpressionValue::Null) => false,
+            (ExpressionValue::Number(_), _) | (_, ExpressionValue::Number(_)) => {
+                match (self.as_number(), other.as_number()) {
+                    (Some(left), Some(right)) => left == right,
+                    _ => false,
+                }
+            }
+            (ExpressionValue::List(left), ExpressionValue::List(right)) => {
+                left.len() == right.len() && left.iter().zip(right.iter()).all(|(l, r)| l.equals(r))
+            }
+            (ExpressionValue::Dict(left), ExpressionValue::Dict(right)) => {
+                left.len() == right.len()
+                    && left
+                        .iter()
+                        .all(|(key, value)| right.get(key).is_some_and(|r| value.equals(r)))
+            }
+            _ => self == other,
+        }
+    }
+
+    fn compare(&self, other: &ExpressionValue) -> Option<Ordering> {
+        match (self, other) {
+            (ExpressionValue::Str(left), ExpressionValue::Str(right)) => {
+                match (self.as_number(), other.as_number()) {
+                    (Some(left), Some(right)) => left.partial_cmp(&right),
+                    _ => Some(left.cmp(right)),
+                }
+            }
+            (ExpressionValue::Number(_), _) | (_, ExpressionValue::Number(_)) => {
+                self.as_number()?.partial_cmp(&other.as_number()?)
+            }
+            _ => None,
+        }
+    }
+
+    fn contains(&self, item: &ExpressionValue) -> bool {
+        match self {
+            ExpressionValue::List(items) => items.iter().any(|value| value.equals(item)),
+            ExpressionValue::Dict(fields) => fields.contains_key(&item.to_string()),
+            ExpressionValue::Str(value) => !item.is_null() && value.contains(&item.to_string()),
+            _ => false,
+        }
+    }
+
+    fn is_null(&self) -> bool {
+        *self == ExpressionValue::Null
+    }
+}
+
+impl From<&USSDData> for ExpressionValue {
+    fn from(data: &USSDData) -> Self {
+        match data {
+            USSDData::Str(value) => ExpressionValue::Str(value.clone()),
+            USSDData::Int(value) => ExpressionValue::Number(*value as f64),
+            USSDData::Float(value) => ExpressionValue::Number(*value),
+            USSDData::List(items) => {
+                ExpressionValue::List(items.iter().map(ExpressionValue::from).collect())
+            }
+            USSDData::ListStr(items) => ExpressionValue::List(
+                items
+                    .iter()
+                    .map(|item| ExpressionValue::Str(item.clone()))
+                    .collect(),
+            ),
+            USSDData::Dict(fields) => ExpressionValue::Dict(
+                fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), ExpressionValue::from(value)))
+                    .collect(),
+            ),
+            USSDData::None => ExpressionValue::Null,
+        }
+    }
+}
+
+impl From<ExpressionValue> for USSDData {
+    fn from(value: ExpressionValue) -> Self {
+        match value {
+            ExpressionValue::Null => USSDData::None,
+            ExpressionValue::Bool(value) => USSDData::Str(value.to_string()),
+            ExpressionValue::Number(number)
+                if number.fract() == 0.0 && number.abs() < i64::MAX as f64 =>
+            {
+                USSDData::Int(number as i64)
+            }
+            ExpressionValue::Number(number) => USSDData::Float(number),
+            ExpressionValue::Str(value) => USSDData::Str(value),
+            ExpressionValue::List(items) => {
+                USSDData::List(items.into_iter().map(USSDData::from).collect())
+            }
+            ExpressionValue::Dict(fields) => USSDData::Dict(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (key, USSDData::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Display for ExpressionValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpressionValue::Null => Ok(()),
+            ExpressionValue::Bool(value) => write!(f, "{}", value),
+            ExpressionValue::Number(number) => write!(f, "{}", number),
+            ExpressionValue::Str(value) => write!(f, "{}", value),
+            ExpressionValue::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(f, "{}", items.join(", "))
+            }
+            ExpressionValue::Dict(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect();
+                write!(f, "{{{}}}", fields.join(", "))
+            }
+        }
+    }
+}
+
+/// Splits an expression into tokens, each with its character offset.
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, ExpressionError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut position = 0;
+
+    while position < chars.len() {
+        let c = chars[position];
+        let start = position;
+
+        if c.is_whitespace() {
+            position += 1;
+        } else if c.is_ascii_digit() {
+            while position < chars.len()
+                && (chars[position].is_ascii_digit()
+                    || (chars[position] == '.'
+                        && chars.get(position + 1).is_some_and(|c| c.is_ascii_digit())))
+            {
+                position += 1;
+            }
+            let number: String = chars[start..position].iter().collect();
+            tokens.push((Token::Number(number.parse().unwrap_or_default()), start));
+        } else if c.is_alphabetic() || c == '_' || c == '@' {
+            position += 1;
+            while position < chars.len()
+                && (chars[position].is_alphanumeric() || chars[position] == '_')
+            {
+                position += 1;
+            }
+            tokens.push((Token::Name(chars[start..position].iter().collect()), start));
+        } else if c == '\'' || c == '"' {
+            let mut value = String::new();
+            position += 1;
+            loop {
+                match chars.get(position) {
+                    None => {
+                        return Err(ExpressionError {
+                            message: "Unterminated string".to_string(),
+                            position: start,
+                        })
+                    }
+                    Some('\\') if position + 1 < chars.len() => {
+                        value.push(chars[position + 1]);
+                        position += 2;
+                    }
+                    Some(quote) if *quote == c => {
+                        position += 1;
+                        break;
+                    }
+                    Some(other) => {
+                        value.push(*other);
+                        position += 1;
+                    }
+                }
+            }
+            tokens.push((Token::Str(value), start));
+        } else {
+            let rest: String = chars[position..chars.len().min(position + 2)]
+                .iter()
+                .collect();
+            match SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+                Some(symbol) => {
+                    tokens.push((Token::Symbol(symbol), start));
+                    position += symbol.len();
+                }
+                None if c == '=' => {
+                    return Err(ExpressionError {
+                        message: "Unexpected '=', use '==' to compare".to_string(),
+                        position: start,
+                    })
+                }
+                None => {
+                    return Err(ExpressionError {
+                        message: format!("Unexpected character '{}'", c),
+                        position: start,
+                    })
+                }
+            }
+        }
+    }
+
+    tokens.push((Token::End, chars.len()));
+    Ok(tokens)
+}
+
+/// A recursive descent parser over the tokens of an expression.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    current: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &(Token, usize) {
+        &self.tokens[self.current.min(self.tokens.len() - 1)]
+    }
+
+    fn advance(&mut self) -> (Token, usize) {
+        let token = self.peek().clone();
+        if self.current < self.tokens.len() - 1 {
+            self.current += 1;
+        }
+        token
+    }
+
+    /// Consumes the next token if it is the symbol or keyword.
+    fn accept(&mut self, expected: &str) -> bool {
+        let matches = match &self.peek().0 {
+            Token::Symbol(symbol) => *symbol == expected,
+            Token::Name(name) => name == expected,
+            _ => false,
+        };
+        if matches {
+            self.advance();
+        }
+        matches
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), ExpressionError> {
+        if self.accept(expected) {
+            return Ok(());
+        }
+
+        let (token, position) = self.peek();
+        Err(ExpressionError {
+            message: format!("Expected '{}', found {}", expected, token),
+            position: *position,
+        })
+    }
+
+    /// Parses a chain of left-associative binary operators, with operands parsed by `operand`.
+    fn parse_binary(
+        &mut self,
+        operators: &[(&str, Operator)],
+        operand: fn(&mut Parser) -> Result<Node, ExpressionError>,
+    ) -> Result<Node, ExpressionError> {
+        let mut node = operand(self)?;
+
+        'chain: loop {
+            for (symbol, operator) in operators {
+                if self.accept(symbol) {
+                    let right = operand(self)?;
+                    node = Node::Binary(*operator, Box::new(node), Box::new(right));
+                    continue 'chain;
+                }
+            }
+            return Ok(node);
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Node, ExpressionError> {
+        self.parse_binary(&[("||", Operator::Or)], Parser::parse_and)
+    }
+
+    fn parse_and(&mut self) -> Result<Node, ExpressionError> {
+        self.parse_binary(&[("&&", Operator::And)], Parser::parse_equality)
+    }
+
+    fn parse_equality(&mut self) -> Result<Node, ExpressionError> {
+        self.parse_binary(
+            &[("==", Operator::Equal), ("!=", Operator::NotEqual)],
+            Parser::parse_comparison,
+        )
+    }
+
+    fn parse_comparison(&mut self) -> Result<Node, ExpressionError> {
+        self.parse_binary(
+            &[
+                ("<=", Operator::LessEqual),
+                (">=", Operator::GreaterEqual),
+                ("<", Operator::Less),
+                (">", Operator::Greater),
+                ("in", Operator::In),
+                ("contains", Operator::Contains),
+            ],
+            Parser::parse_additive,
+        )
+    }
+
+    fn parse_additive(&mut self) -> Result<Node, ExpressionError> {
+        self.parse_binary(
+            &[("+", Operator::Add), ("-", Operator::Subtract)],
+            Parser::parse_multiplicative,
+        )
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Node, ExpressionError> {
+        self.parse_binary(
+            &[
+                ("*", Operator::Multiply),
+                ("/", Operator::Divide),
+                ("%", Operator::Remainder),
+            ],
+            Parser::parse_unary,
+        )
+    }
+
+    fn parse_unary(&mut self) -> Result<Node, ExpressionError> {
+        if self.accept("!") {
+            return Ok(Node::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.accept("-") {
+            return Ok(Node::Negate(Box::new(self.parse_unary()?)));
+        }
+        self.parse_postfix()
+    }
+
+    fn parse_postfix(&mut self) -> Result<Node, ExpressionError> {
+        let mut node = self.parse_primary()?;
+
+        loop {
+            if self.accept(".") {
+                match self.advance() {
+                    (Token::Name(field), _) => node = Node::Field(Box::new(node), field),
+                    (token, position) => {
+                        return Err(ExpressionError {
+                            message: format!("Expected a field name, found {}", token),
+                            position,
+                        })
+                    }
+                }
+            } else if self.accept("[") {
+                let index = self.parse_or()?;
+                self.expect("]")?;
+                node = Node::Index(Box::new(node), Box::new(index));
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Node, ExpressionError> {
+        match self.advance() {
+            (Token::Number(number), _) => Ok(Node::Literal(ExpressionValue::Number(number))),
+            (Token::Str(value), _) => Ok(Node::Literal(ExpressionValue::Str(value))),
+            (Token::Name(name), position) => match name.as_str() {
+                "true" => Ok(Node::Literal(ExpressionValue::Bool(true))),
+                "false" => Ok(Node::Literal(ExpressionValue::Bool(false))),
+                "null" => Ok(Node::Literal(ExpressionValue::Null)),
+                "in" | "contains" => Err(ExpressionError {
+                    message: format!("Expected a value, found '{}'", name),
+                    position,
+                }),
+                _ => Ok(Node::Variable(name)),
+            },
+            (Token::Symbol("("), _) => {
+                let node = self.parse_or()?;
+                self.expect(")")?;
+                Ok(node)
+            }
+            (Token::Symbol("["), _) => {
+                let mut items = Vec::new();
+                if !self.accept("]") {
+                    loop {
+                        items.push(self.parse_or()?);
+                        if self.accept("]") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                Ok(Node::List(items))
+            }
+            (token, position) => Err(ExpressionError {
+                message: format!("Expected a value, found {}", token),
+                position,
+            }),
+        }
+    }
+}
+
+fn evaluate(node: &Node, resolve: &dyn Fn(&str) -> ExpressionValue) -> ExpressionValue {
+    match node {
+        Node::Literal(value) => value.clone(),
+        Node::Variable(name) => resolve(name),
+        Node::Field(object, field) => match evaluate(object, resolve) {
+            ExpressionValue::Dict(mut fields) => fields.remove(field).unwrap_or_default(),
+            _ => ExpressionValue::Null,
+        },
+        Node::Index(object, index) => match (evaluate(object, resolve), evaluate(index, resolve)) {
+            (ExpressionValue::List(mut items), index) => match index.as_number() {
+                Some(index) if index >= 0.0 && (index as usize) < items.len() => {
+                    items.swap_remove(index as usize)
+                }
+                _ => ExpressionValue::Null,
+            },
+            (ExpressionValue::Dict(mut fields), key) => {
+                fields.remove(&key.to_string()).unwrap_or_default()
+            }
+            _ => ExpressionValue::Null,
+        },
+        Node::List(items) => {
+            ExpressionValue::List(items.iter().map(|item| evaluate(item, resolve)).collect())
+        }
+        Node::Not(operand) => ExpressionValue::Bool(!evaluate(operand, resolve).is_truthy()),
+        Node::Negate(operand) => match evaluate(operand, resolve).as_number() {
+            Some(number) => ExpressionValue::Number(-number),
+            None => ExpressionValue::Null,
+        },
+        Node::Binary(Operator::Or, left, right) => ExpressionValue::Bool(
+            evaluate(left, resolve).is_truthy() || evaluate(right, resolve).is_truthy(),
+        ),
+        Node::Binary(Operator::And, left, right) => ExpressionValue::Bool(
+            evaluate(left, resolve).is_truthy() && evaluate(right, resolve).is_truthy(),
+        ),
+        Node::Binary(operator, left, right) => {
+            apply(*operator, evaluate(left, resolve), evaluate(right, resolve))
+        }
+    }
+}
+
+fn apply(operator: Operator, left: ExpressionValue, right: ExpressionValue) -> ExpressionValue {
+    let ordering = |matches: fn(Ordering) -> bool| {
+        ExpressionValue::Bool(left.compare(&right).is_some_and(matches))
+    };
+
+    match operator {
+        Operator::Equal => ExpressionValue::Bool(left.equals(&right)),
+        Operator::NotEqual => ExpressionValue::Bool(!left.equals(&right)),
+        Operator::Less => ordering(|o| o == Ordering::Less),
+        Operator::LessEqual => ordering(|o| o != Ordering::Greater),
+        Operator::Greater => ordering(|o| o == Ordering::Greater),
+        Operator::GreaterEqual => ordering(|o| o != Ordering::Less),
+        Operator::In => ExpressionValue::Bool(right.contains(&left)),
+        Operator::Contains => ExpressionValue::Bool(left.contains(&right)),
+        Operator::Add => match (&left, &right, left.as_number(), right.as_number()) {
+            (_, _, Some(l), Some(r)) => ExpressionValue::Number(l + r),
+            (ExpressionValue::List(l), ExpressionValue::List(r), _, _) => {
+                ExpressionValue::List(l.iter().chain(r.iter()).cloned().collect())
+            }
+            (ExpressionValue::Str(_), _, _, _) | (_, ExpressionValue::Str(_), _, _) => {
+                ExpressionValue::Str(format!("{}{}", left, right))
+            }
+            _ => ExpressionValue::Null,
+        },
+        Operator::Subtract | Operator::Multiply | Operator::Divide | Operator::Remainder => {
+            let (Some(l), Some(r)) = (left.as_number(), right.as_number()) else {
+                return ExpressionValue::Null;
+            };
+            match operator {
+                Operator::Subtract => ExpressionValue::Number(l - r),
+                Operator::Multiply => ExpressionValue::Number(l * r),
+                _ if r == 0.0 => ExpressionValue::Null,
+                Operator::Divide => ExpressionValue::Number(l / r),
+                _ => ExpressionValue::Number(l % r),
+            }
+        }
+        Operator::Or | Operator::And => unreachable!("boolean operators are evaluated lazily"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn session() -> USSDSession {
+        let mut session = USSDSession::new(
+            "expression-session".to_string(),
+            "MainScreen".to_string(),
+            "en".to_string(),
+            "1234567890".to_string(),
+        );
+        session
+            .data
+            .insert("amount".to_string(), USSDData::Str("95".to_string()));
+        session
+            .data
+            .insert("balance".to_string(), USSDData::Int(1000));
+        session
+            .data
+            .insert("name".to_string(), USSDData::Str("Ama Serwaa".to_string()));
+        session.data.insert(
+            "account".to_string(),
+            USSDData::new_dict(HashMap::from([
+                ("status".to_string(), USSDData::Str("active".to_string())),
+                ("rate".to_string(), USSDData::Float(2.5)),
+                (
+                    "cards".to_string(),
+                    USSDData::List(vec![
+                        USSDData::new_dict(HashMap::from([(
+                            "last4".to_string(),
+                            USSDData::Str("1234".to_string()),
+                        )])),
+                        USSDData::new_dict(HashMap::from([(
+                            "last4".to_string(),
+                            USSDData::Str("9876".to_string()),
+                        )])),
+                    ]),
+                ),
+            ])),
+        );
+        session.data.insert(
+            "networks".to_string(),
+            USSDData::ListStr(vec!["MTN".to_string(), "Vodafone".to_string()]),
+        );
+        session
+    }
+
+    fn eval(source: &str) -> ExpressionValue {
+        Expression::parse(source).unwrap().evaluate(&session())
+    }
+
+    fn is_true(source: &str) -> bool {
+        Expression::parse(source).unwrap().is_true(&session())
+    }
+
+    #[test]
+    fn test_typed_comparison() {
+        assert!(!is_true("amount > 900"));
+        assert!(is_true("amount < 900"));
+        assert!(is_true("amount == 95.0"));
+        assert!(is_true("amount > '900' == false"));
+        assert!(is_true("name == 'Ama Serwaa'"));
+        assert!(is_true("name > 'Ama'"));
+        assert!(is_true("account.rate >= 2.5"));
+        assert!(is_true("balance / 4 == 250"));
+    }
+
+    #[test]
+    fn test_boolean_logic() {
+        assert!(is_true("amount > 50 && account.status == 'active'"));
+        assert!(is_true("amount > 900 || balance > 900"));
+        assert!(is_true("!(amount > 900)"));
+        assert!(!is_true("amount > 900 || !account.status"));
+        assert!(is_true("true && !false"));
+    }
+
+    #[test]
+    fn test_membership_and_null() {
+        assert!(is_true("'MTN' in networks"));
+        assert!(is_true("networks contains 'Vodafone'"));
+        assert!(!is_true("'Glo' in networks"));
+        assert!(is_true("account.status in ['active', 'pending']"));
+        assert!(is_true("name contains 'Serwaa'"));
+        assert!(is_true("'status' in account"));
+        assert!(is_true("missing == null && account.missing == null"));
+        assert!(is_true("account != null"));
+    }
+
+    #[test]
+    fn test_arithmetic_and_indexing() {
+        assert_eq!(eval("amount * 2 + 10"), ExpressionValue::Number(200.0));
+        assert_eq!(eval("-(balance % 300)"), ExpressionValue::Number(-100.0));
+        assert_eq!(eval("balance / 0"), ExpressionValue::Null);
+        assert_eq!(
+            eval("'Hello ' + name"),
+            ExpressionValue::Str("Hello Ama Serwaa".to_string())
+        );
+        assert_eq!(
+            eval("account.cards[1].last4"),
+            ExpressionValue::Str("9876".to_string())
+        );
+        assert_eq!(
+            eval("account['cards'][0].last4"),
+            ExpressionValue::Str("1234".to_string())
+        );
+        assert_eq!(eval("account.cards[5]"), ExpressionValue::Null);
+        assert_eq!(eval("networks[0]").to_string(), "MTN");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = Expression::parse("amount >").unwrap_err();
+        assert_eq!(error.position, 8);
+
+        assert_eq!(Expression::parse("amount = 5").unwrap_err().position, 7);
+        assert_eq!(Expression::parse("name == 'Ama").unwrap_err().position, 8);
+        assert_eq!(Expression::parse("(amount > 5").unwrap_err().position, 11);
+        assert_eq!(Expression::parse("amount 5").unwrap_err().position, 7);
+        assert_eq!(Expression::parse("account.").unwrap_err().position, 8);
+        assert_eq!(Expression::parse("amount # 5").unwrap_err().position, 7);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::info;
-use crate::types::{FunctionMap, USSDFunction};
+use crate::types::{AsyncFunctionMap, FunctionMap, USSDAsyncFunction, USSDFunction};
 use std::collections::HashSet;
 use std::sync::{Arc, Mutex, MutexGuard};
 
@@ -7,6 +7,7 @@ lazy_static::lazy_static! {
     // Define a lazy static variable to store registered functions
     pub static ref FUNCTION_MAP: Arc<Mutex<FunctionMap>> = Arc::new(Mutex::new(FunctionMap::new()));
     pub static ref REGISTERED_FUNCTIONS: Arc<Mutex<HashSet<String>>> = Arc::new(Mutex::new(HashSet::new()));
+    pub static ref ASYNC_FUNCTION_MAP: Arc<Mutex<AsyncFunctionMap>> = Arc::new(Mutex::new(AsyncFunctionMap::new()));
 
 }
 
@@ -62,6 +63,27 @@ pub fn register_function(
     function_map_guard.insert(function_name, function_ptr);
 }
 
+/// Registers an async USSD function with the provided path.
+///
+/// Async functions are stored separately from synchronous ones and are only called when the
+/// request is processed with `UssdApp::run_async`.
+///
+/// # Arguments
+///
+/// * `path`: A string representing the unique path for the USSD function.
+/// * `function_ptr`: A function pointer with the signature `fn(USSDSession, String) -> USSDFuture`.
+/// * `function_map_guard`: A mutable reference to the guarded map of async USSD functions.
+///
+pub fn register_async_function(
+    path: &str,
+    function_ptr: USSDAsyncFunction,
+    function_map_guard: &mut MutexGuard<AsyncFunctionMap>,
+) {
+    info!("Registering async function: {}", path);
+
+    function_map_guard.insert(path.to_string(), function_ptr);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,6 +96,23 @@ mod tests {
         unimplemented!()
     }
 
+    fn test_async_function(_session: USSDSession, _input: String) -> crate::types::USSDFuture {
+        Box::pin(async { USSDData::Str("async".to_string()) })
+    }
+
+    #[test]
+    fn test_register_async_function() {
+        let mut function_map_guard = ASYNC_FUNCTION_MAP.lock().unwrap();
+
+        register_async_function(
+            "/test-async-path",
+            test_async_function,
+            &mut function_map_guard,
+        );
+
+        assert!(function_map_guard.contains_key("/test-async-path"));
+    }
+
     #[test]
     fn test_register_function() {
         // Create a new function map
@@ -90,15 +129,15 @@ mod tests {
     fn test_register_function_no_panic() {
         // Create a new function map
         let mut function_map_guard = FUNCTION_MAP.lock().unwrap();
-    
+
         let mut registered_function_set = REGISTERED_FUNCTIONS.lock().unwrap();
-    
+
         // Register the test function
         register_function("/test-path", test_function, &mut function_map_guard);
-    
+
         // Add the function path to the registered functions set
         registered_function_set.insert("/test-path".to_string());
-    
+
         // Attempt to register the same function again and check if it already exists
         let path = "/test-path";
         if registered_function_set.contains(path) {
@@ -107,7 +146,7 @@ mod tests {
             register_function(path, test_function, &mut function_map_guard);
             registered_function_set.insert(path.to_string());
         }
-    
+
         // Verify that only one instance of '/test-path' exists
         assert!(registered_function_set.contains(path));
         assert_eq!(registered_function_set.len(), 1);
@@ -1,5 +1,12 @@
 pub mod expr;
+pub mod expression;
 pub mod func;
+pub mod registry;
+pub mod template;
 
-pub use expr::{evaluate_expression, evaluate_expression_op};
-pub use func::{register_function, FUNCTION_MAP, REGISTERED_FUNCTIONS};
+pub use expr::router_condition;
+pub(crate) use expr::Parsed;
+pub use expression::{Expression, ExpressionError, ExpressionValue};
+pub use func::{register_async_function, register_function, ASYNC_FUNCTION_MAP, FUNCTION_MAP};
+pub use registry::FunctionRegistry;
+pub use template::{Template, TemplateError};
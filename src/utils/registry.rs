@@ -0,0 +1,238 @@
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use crate::{
+    core::USSDSession,
+    info,
+    types::{AsyncFunctionHandler, FunctionHandler, USSDData, USSDFuture},
+};
+
+use super::{ASYNC_FUNCTION_MAP, FUNCTION_MAP};
+
+/// The functions a `UssdApp` can call from its services.
+///
+/// Each `UssdApp` owns its registry, so two apps in one process do not share or overwrite each
+/// other's functions, and calling a function takes no lock. Functions can be closures capturing
+/// shared state, or take typed state with `register_with_state`.
+///
+/// Functions registered in the global `FUNCTION_MAP` and `ASYNC_FUNCTION_MAP` are still found
+/// when a name is not in the registry, for code written before the registry existed.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use ussdframework::prelude::*;
+///
+/// struct Rates {
+///     airtime_bonus: f64,
+/// }
+///
+/// let mut app = UssdApp::new(true, None);
+///
+/// app.functions.register("greet", |session: &USSDSession, _url: &str| {
+///     USSDData::Str(format!("Hello {}", session.msisdn))
+/// });
+///
+/// let rates = Arc::new(Rates { airtime_bonus: 0.1 });
+/// app.functions
+///     .register_with_state("bonus", rates, |rates: &Rates, _session: &USSDSession, _url: &str| {
+///         USSDData::Float(rates.airtime_bonus)
+///     });
+/// ```
+#[derive(Clone, Default)]
+pub struct FunctionRegistry {
+    functions: HashMap<String, FunctionHandler>,
+    async_functions: HashMap<String, AsyncFunctionHandler>,
+}
+
+impl Debug for FunctionRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FunctionRegistry")
+            .field("functions", &self.functions.keys().collect::<Vec<_>>())
+            .field(
+                "async_functions",
+                &self.async_functions.keys().collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl FunctionRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        FunctionRegistry::default()
+    }
+
+    /// Registers a function under `name`, replacing any function already registered under it.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name services refer to the function by, in their `function_name`.
+    /// * `function` - The function, called with the session and the service's `function_url`.
+    pub fn register<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(&USSDSession, &str) -> USSDData + Send + Sync + 'static,
+    {
+        info!("Registering function: {}", name);
+
+        self.functions.insert(name.to_string(), Arc::new(function));
+    }
+
+    /// Registers an async function under `name`, replacing any function already registered under it.
+    ///
+    /// Async functions are only called when the request is processed with `UssdApp::run_async`.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name services refer to the function by, in their `function_name`.
+    /// * `function` - The function, called with the session and the service's `function_url`.
+    pub fn register_async<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(USSDSession, String) -> USSDFuture + Send + Sync + 'static,
+    {
+        info!("Registering async function: {}", name);
+
+        self.async_functions
+            .insert(name.to_string(), Arc::new(function));
+    }
+
+    /// Registers a function that is called with shared application state.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name services refer to the function by, in their `function_name`.
+    /// * `state` - The state passed to every call, such as a database pool or configuration.
+    /// * `function` - The function, called with the state, the session and the service's `function_url`.
+    pub fn register_with_state<S, F>(&mut self, name: &str, state: Arc<S>, function: F)
+    where
+        S: Send + Sync + 'static,
+        F: Fn(&S, &USSDSession, &str) -> USSDData + Send + Sync + 'static,
+    {
+        self.register(name, move |session: &USSDSession, url: &str| {
+            function(&state, session, url)
+        });
+    }
+
+    /// Registers an async function that is called with shared application state.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name services refer to the function by, in their `function_name`.
+    /// * `state` - The state passed to every call, such as a database pool or an HTTP client.
+    /// * `function` - The function, called with the state, the session and the service's `function_url`.
+    pub fn register_async_with_state<S, F>(&mut self, name: &str, state: Arc<S>, function: F)
+    where
+        S: Send + Sync + 'static,
+        F: Fn(Arc<S>, USSDSession, String) -> USSDFuture + Send + Sync + 'static,
+    {
+        self.register_async(name, move |session: USSDSession, url: String| {
+            function(state.clone(), session, url)
+        });
+    }
+
+    /// Returns the function registered under `name`.
+    pub fn get(&self, name: &str) -> Option<FunctionHandler> {
+        if let Some(function) = self.functions.get(name) {
+            return Some(function.clone());
+        }
+
+        // Fall back to the global function map
+        FUNCTION_MAP
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|function| -> FunctionHandler { Arc::new(*function) })
+    }
+
+    /// Returns the async function registered under `name`.
+    pub fn get_async(&self, name: &str) -> Option<AsyncFunctionHandler> {
+        if let Some(function) = self.async_functions.get(name) {
+            return Some(function.clone());
+        }
+
+        // Fall back to the global async function map
+        ASYNC_FUNCTION_MAP
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|function| -> AsyncFunctionHandler { Arc::new(*function) })
+    }
+
+    /// Returns true if an async function is registered under `name`.
+    pub fn is_async(&self, name: &str) -> bool {
+        self.async_functions.contains_key(name)
+            || (!self.functions.contains_key(name)
+                && ASYNC_FUNCTION_MAP.lock().unwrap().contains_key(name))
+    }
+
+    /// Returns true if a function, synchronous or async, is registered under `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+            || self.async_functions.contains_key(name)
+            || FUNCTION_MAP.lock().unwrap().contains_key(name)
+            || ASYNC_FUNCTION_MAP.lock().unwrap().contains_key(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> USSDSession {
+        USSDSession::new(
+            "registry-session".to_string(),
+            "Start".to_string(),
+            "en".to_string(),
+            "1234567890".to_string(),
+        )
+    }
+
+    #[test]
+    fn test_registries_are_separate() {
+        let mut first = FunctionRegistry::new();
+        let mut second = FunctionRegistry::new();
+
+        let prefix = String::from("first");
+        first.register(
+            "registry_greet",
+            move |session: &USSDSession, _url: &str| {
+                USSDData::Str(format!("{} {}", prefix, session.msisdn))
+            },
+        );
+        second.register("registry_greet", |_session: &USSDSession, url: &str| {
+            USSDData::Str(url.to_string())
+        });
+
+        let session = session();
+        assert_eq!(
+            first.get("registry_greet").unwrap()(&session, "url"),
+            USSDData::Str("first 1234567890".to_string())
+        );
+        assert_eq!(
+            second.get("registry_greet").unwrap()(&session, "url"),
+            USSDData::Str("url".to_string())
+        );
+        assert!(FunctionRegistry::new().get("registry_greet").is_none());
+    }
+
+    #[test]
+    fn test_register_with_state() {
+        struct Rates {
+            bonus: i64,
+        }
+
+        let mut registry = FunctionRegistry::new();
+        registry.register_with_state(
+            "registry_bonus",
+            Arc::new(Rates { bonus: 10 }),
+            |rates: &Rates, _session: &USSDSession, _url: &str| USSDData::Int(rates.bonus),
+        );
+
+        assert!(registry.contains("registry_bonus"));
+        assert!(!registry.is_async("registry_bonus"));
+        assert_eq!(
+            registry.get("registry_bonus").unwrap()(&session(), ""),
+            USSDData::Int(10)
+        );
+    }
+}
@@ -0,0 +1,726 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write},
+};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+use crate::core::USSDSession;
+
+use super::{Expression, ExpressionValue};
+
+/// A parsed screen text template.
+///
+/// Templates are plain text with tags between `{{` and `}}`:
+///
+/// * `{{expression}}` renders the value of an expression, see `Expression` for the language.
+///   Values that are not set are left as they are written, so missing data shows up while testing.
+/// * `{{expression | filter: argument}}` passes the value through one or more filters.
+/// * `{{#if expression}} ... {{else if expression}} ... {{else}} ... {{/if}}` renders the first
+///   branch whose expression is true.
+/// * `{{#each list}} ... {{else}} ... {{/each}}` renders its body once per item of a list, or the
+///   `else` branch when the list is empty. In the body, `this` is the item, `@index` its position
+///   from 0 and `@number` its position from 1. Fields of dictionary items can be used by name.
+///
+/// The filters are:
+///
+/// * `default: value`: the value to render when the value is not set or empty.
+/// * `upper` and `lower`: change the case of the text.
+/// * `truncate: length`: shortens the text to at most `length` characters, ending with `...`.
+/// * `number` or `number: decimals`: groups the thousands, as in `1,234,567`.
+/// * `currency` or `currency: 'GHS'`: groups the thousands with two decimals, after the currency.
+/// * `date` or `date: '%d %b %Y'`: formats a date, a date and time or a Unix timestamp.
+///   The default format is `%d/%m/%Y`.
+///
+/// # Example
+///
+/// ```
+/// use ussdframework::prelude::*;
+///
+/// let mut session = USSDSession::new(
+///     "session".to_string(),
+///     "MainScreen".to_string(),
+///     "en".to_string(),
+///     "1234567890".to_string(),
+/// );
+/// session.data.insert("balance".to_string(), USSDData::Float(1234.5));
+/// session.data.insert(
+///     "bundles".to_string(),
+///     USSDData::ListStr(vec!["Daily".to_string(), "Weekly".to_string()]),
+/// );
+///
+/// let template = Template::parse(
+///     "Hi {{name | default: 'customer'}}, you have {{balance | currency: 'GHS'}}\n\
+///      {{#each bundles}}{{@number}}. {{this | upper}}\n{{/each}}",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(
+///     template.render(&session),
+///     "Hi customer, you have GHS 1,234.50\n1. DAILY\n2. WEEKLY\n"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+/// An error found while parsing a template.
+///
+/// # Fields
+///
+/// * `message`: What is wrong with the template.
+/// * `position`: The character offset in the template of the tag where the error was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Value {
+        source: String,
+        expression: Expression,
+        filters: Vec<Filter>,
+    },
+    If {
+        branches: Vec<(Expression, Vec<Part>)>,
+        otherwise: Vec<Part>,
+    },
+    Each {
+        expression: Expression,
+        body: Vec<Part>,
+        otherwise: Vec<Part>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    name: String,
+    arguments: Vec<Expression>,
+}
+
+/// The arguments each filter accepts, as the minimum and maximum count.
+const FILTERS: [(&str, usize, usize); 7] = [
+    ("default", 1, 1),
+    ("upper", 0, 0),
+    ("lower", 0, 0),
+    ("truncate", 1, 1),
+    ("number", 0, 1),
+    ("currency", 0, 1),
+    ("date", 0, 1),
+];
+
+/// A piece of the template source, either text or the content of a tag.
+enum Piece {
+    Text(String),
+    Tag { content: String, position: usize },
+}
+
+/// Parsed parts, with the tag that ended them and its position.
+type Block = (Vec<Part>, Option<(String, usize)>);
+
+/// Variables set by the `#each` loops being rendered, innermost last.
+type Scope = Vec<HashMap<String, ExpressionValue>>;
+
+impl Template {
+    /// Parses a template.
+    ///
+    /// # Returns
+    ///
+    /// The parsed template, or a `TemplateError` for the first tag that does not parse.
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut pieces = split_tags(source)?.into_iter().peekable();
+        let (parts, end) = parse_parts(&mut pieces, &[])?;
+
+        match end {
+            None => Ok(Template { parts }),
+            Some((tag, position)) => Err(TemplateError {
+                message: format!("Unexpected {{{{{}}}}}", tag),
+                position,
+            }),
+        }
+    }
+
+    /// Renders the template with the session data.
+    pub fn render(&self, session: &USSDSession) -> String {
+        self.render_with(session, HashMap::new())
+    }
+
+    /// Renders the template with the session data and `variables`, which take precedence over it.
+    pub fn render_with(
+        &self,
+        session: &USSDSession,
+        variables: HashMap<String, ExpressionValue>,
+    ) -> String {
+        let mut output = String::new();
+        render_parts(&self.parts, session, &mut vec![variables], &mut output);
+        output
+    }
+}
+
+/// Returns the variables of a list item: `this`, `@index`, `@number` and, for dictionaries,
+/// its fields.
+pub(crate) fn item_variables(
+    item: ExpressionValue,
+    index: usize,
+) -> HashMap<String, ExpressionValue> {
+    let mut variables: HashMap<String, ExpressionValue> = match &item {
+        ExpressionValue::Dict(fields) => fields.clone().into_iter().collect(),
+        _ => HashMap::new(),
+    };
+    variables.insert("this".to_string(), item);
+    variables.insert("@index".to_string(), ExpressionValue::Number(index as f64));
+    variables.insert(
+        "@number".to_string(),
+        ExpressionValue::Number((index + 1) as f64),
+    );
+    variables
+}
+
+/// Splits the source into text and the content of `{{ }}` tags.
+fn split_tags(source: &str) -> Result<Vec<Piece>, TemplateError> {
+    let mut pieces = Vec::new();
+    let mut rest = source;
+    let mut position = 0;
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            pieces.push(Piece::Text(rest[..start].to_string()));
+        }
+        position += rest[..start].chars().count();
+
+        let Some(end) = rest[start..].find("}}") else {
+            return Err(TemplateError {
+                message: "Unclosed tag".to_string(),
+                position,
+            });
+        };
+        let end = start + end + 2;
+
+        pieces.push(Piece::Tag {
+            content: rest[start + 2..end - 2].trim().to_string(),
+            position,
+        });
+
+        position += rest[start..end].chars().count();
+        rest = &rest[end..];
+    }
+
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest.to_string()));
+    }
+    Ok(pieces)
+}
+
+/// Parses parts until one of the `ends` tags, which is returned with its position.
+///
+/// Block tags that do not belong to the block being parsed are returned too, so the caller can
+/// report them.
+fn parse_parts(
+    pieces: &mut std::iter::Peekable<std::vec::IntoIter<Piece>>,
+    ends: &[&str],
+) -> Result<Block, TemplateError> {
+    let mut parts = Vec::new();
+
+    while let Some(piece) = pieces.next() {
+        let (content, position) = match piece {
+            Piece::Text(text) => {
+                parts.push(Part::Text(text));
+                continue;
+            }
+            Piece::Tag { content, position } => (content, position),
+        };
+
+        if content == "else" || content.starts_with("else ") || content.starts_with('/') {
+            if ends
+                .iter()
+                .any(|end| content == *end || content.starts_with(&format!("{} ", end)))
+            {
+                return Ok((parts, Some((content, position))));
+            }
+            return Err(TemplateError {
+                message: format!("Unexpected {{{{{}}}}}", content),
+                position,
+            });
+        }
+
+        if let Some(condition) = content.strip_prefix("#if ") {
+            let mut branches = Vec::new();
+            let mut otherwise = Vec::new();
+            let mut condition = parse_expression(condition, position)?;
+
+            loop {
+                let (body, end) = parse_parts(pieces, &["else", "/if"])?;
+                let (end, end_position) = end.ok_or_else(|| unclosed("#if", position))?;
+                branches.push((condition, body));
+
+                if end == "/if" {
+                    break;
+                }
+                if let Some(next) = end.strip_prefix("else if ") {
+                    condition = parse_expression(next, end_position)?;
+                    continue;
+                }
+                if end != "else" {
+                    return Err(TemplateError {
+                        message: format!("Unexpected {{{{{}}}}}", end),
+                        position: end_position,
+                    });
+                }
+
+                let (body, end) = parse_parts(pieces, &["/if"])?;
+                end.ok_or_else(|| unclosed("#if", position))?;
+                otherwise = body;
+                break;
+            }
+
+            parts.push(Part::If {
+                branches,
+                otherwise,
+            });
+        } else if let Some(list) = content.strip_prefix("#each ") {
+            let expression = parse_expression(list, position)?;
+
+            let (body, end) = parse_parts(pieces, &["else", "/each"])?;
+            let (end, end_position) = end.ok_or_else(|| unclosed("#each", position))?;
+            let otherwise = match end.as_str() {
+                "/each" => Vec::new(),
+                "else" => {
+                    let (otherwise, end) = parse_parts(pieces, &["/each"])?;
+                    end.ok_or_else(|| unclosed("#each", position))?;
+                    otherwise
+                }
+                _ => {
+                    return Err(TemplateError {
+                        message: format!("Unexpected {{{{{}}}}}", end),
+                        position: end_position,
+                    })
+                }
+            };
+
+            parts.push(Part::Each {
+                expression,
+                body,
+                otherwise,
+            });
+        } else if content.starts_with('#') {
+            return Err(TemplateError {
+                message: format!("Unknown block {{{{{}}}}}", content),
+                position,
+            });
+        } else {
+            parts.push(parse_value(&content, position)?);
+        }
+    }
+
+    Ok((parts, None))
+}
+
+fn unclosed(block: &str, position: usize) -> TemplateError {
+    TemplateError {
+        message: format!("Unclosed {{{{{}}}}}", block),
+        position,
+    }
+}
+
+fn parse_expression(source: &str, position: usize) -> Result<Expression, TemplateError> {
+    Expression::parse(source.trim()).map_err(|e| TemplateError {
+        message: format!("Invalid expression '{}': {}", source.trim(), e),
+        position,
+    })
+}
+
+/// Parses a value tag, an expression followed by filters.
+fn parse_value(content: &str, position: usize) -> Result<Part, TemplateError> {
+    let mut sections = split_outside_quotes(content, '|').into_iter();
+    let expression = parse_expression(&sections.next().unwrap_or_default(), position)?;
+
+    let mut filters = Vec::new();
+    for section in sections {
+        let (name, arguments) = match section.split_once(':') {
+            Some((name, arguments)) => (name.trim(), split_outside_quotes(arguments, ',')),
+            None => (section.trim(), Vec::new()),
+        };
+
+        let Some((_, min, max)) = FILTERS.iter().find(|(filter, _, _)| *filter == name) else {
+            return Err(TemplateError {
+                message: format!("Unknown filter '{}'", name),
+                position,
+            });
+        };
+        if arguments.len() < *min || arguments.len() > *max {
+            return Err(TemplateError {
+                message: format!("Filter '{}' takes {} to {} arguments", name, min, max),
+                position,
+            });
+        }
+
+        filters.push(Filter {
+            name: name.to_string(),
+            arguments: arguments
+                .iter()
+                .map(|argument| parse_expression(argument, position))
+                .collect::<Result<_, _>>()?,
+        });
+    }
+
+    Ok(Part::Value {
+        source: format!("{{{{{}}}}}", content),
+        expression,
+        filters,
+    })
+}
+
+/// Splits on `separator` where it is not quoted or bracketed, and is not part of `||`.
+fn split_outside_quotes(source: &str, separator: char) -> Vec<String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut sections = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut depth = 0;
+
+    for (index, c) in chars.iter().enumerate() {
+        match quote {
+            Some(q) if *c == q && chars.get(index.wrapping_sub(1)) != Some(&'\\') => quote = None,
+            Some(_) => {}
+            None if *c == '\'' || *c == '"' => quote = Some(*c),
+            None if *c == '(' || *c == '[' => depth += 1,
+            None if *c == ')' || *c == ']' => depth -= 1,
+            None if *c == separator
+                && depth == 0
+                && !(separator == '|'
+                    && (chars.get(index + 1) == Some(&'|')
+                        || chars.get(index.wrapping_sub(1)) == Some(&'|'))) =>
+            {
+                sections.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            None => {}
+        }
+        current.push(*c);
+    }
+
+    if !current.trim().is_empty() || !sections.is_empty() {
+        sections.push(current.trim().to_string());
+    }
+    sections
+}
+
+fn render_parts(parts: &[Part], session: &USSDSession, scope: &mut Scope, output: &mut String) {
+    for part in parts {
+        match part {
+            Part::Text(text) => output.push_str(text),
+            Part::Value {
+                source,
+                expression,
+                filters,
+            } => {
+                let value = evaluate(expression, session, scope);
+                if value == ExpressionValue::Null && filters.is_empty() {
+                    output.push_str(source);
+                    continue;
+                }
+
+                let text = filters.iter().fold(value.to_string(), |text, filter| {
+                    apply_filter(filter, text, session, scope)
+                });
+                output.push_str(&text);
+            }
+            Part::If {
+                branches,
+                otherwise,
+            } => {
+                let branch = branches
+                    .iter()
+                    .find(|(condition, _)| evaluate(condition, session, scope).is_truthy())
+                    .map(|(_, body)| body)
+                    .unwrap_or(otherwise);
+                render_parts(branch, session, scope, output);
+            }
+            Part::Each {
+                expression,
+                body,
+                otherwise,
+            } => {
+                let items = match evaluate(expression, session, scope) {
+                    ExpressionValue::List(items) => items,
+                    ExpressionValue::Null => Vec::new(),
+                    value => vec![value],
+                };
+
+                if items.is_empty() {
+                    render_parts(otherwise, session, scope, output);
+                }
+
+                for (index, item) in items.into_iter().enumerate() {
+                    scope.push(item_variables(item, index));
+                    render_parts(body, session, scope, output);
+                    scope.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Evaluates an expression, looking names up in the loops being rendered before the session.
+pub(crate) fn evaluate(
+    expression: &Expression,
+    session: &USSDSession,
+    scope: &[HashMap<String, ExpressionValue>],
+) -> ExpressionValue {
+    expression.evaluate_with(&|name: &str| {
+        scope
+            .iter()
+            .rev()
+            .find_map(|variables| variables.get(name).cloned())
+            .or_else(|| session.data.get(name).map(ExpressionValue::from))
+            .unwrap_or_default()
+    })
+}
+
+fn apply_filter(
+    filter: &Filter,
+    text: String,
+    session: &USSDSession,
+    scope: &[HashMap<String, ExpressionValue>],
+) -> String {
+    let argument = filter
+        .arguments
+        .first()
+        .map(|argument| evaluate(argument, session, scope));
+
+    match filter.name.as_str() {
+        "default" if text.is_empty() => argument.unwrap_or_default().to_string(),
+        "default" => text,
+        "upper" => text.to_uppercase(),
+        "lower" => text.to_lowercase(),
+        "truncate" => match argument.and_then(|length| length.as_number()) {
+            Some(length) => truncate(&text, length.max(0.0) as usize),
+            None => text,
+        },
+        "number" => match ExpressionValue::Str(text.clone()).as_number() {
+            Some(number) => match argument.and_then(|decimals| decimals.as_number()) {
+                Some(decimals) => group_thousands(&format!("{:.*}", decimals as usize, number)),
+                None => group_thousands(&number.to_string()),
+            },
+            None => text,
+        },
+        "currency" => match ExpressionValue::Str(text.clone()).as_number() {
+            Some(number) => {
+                let amount = group_thousands(&format!("{:.2}", number));
+                match argument {
+                    Some(currency) if currency != ExpressionValue::Null => {
+                        format!("{} {}", currency, amount)
+                    }
+                    _ => amount,
+                }
+            }
+            None => text,
+        },
+        "date" => {
+            let format = argument
+                .map(|format| format.to_string())
+                .unwrap_or_else(|| "%d/%m/%Y".to_string());
+            // An invalid format fails to display, leave the date as it is instead of panicking
+            let mut formatted = String::new();
+            match parse_date(&text) {
+                Some(date) if write!(formatted, "{}", date.format(&format)).is_ok() => formatted,
+                _ => text,
+            }
+        }
+        _ => text,
+    }
+}
+
+/// Shortens the text to at most `length` characters, ending with `...` when it is shortened.
+fn truncate(text: &str, length: usize) -> String {
+    if text.chars().count() <= length {
+        return text.to_string();
+    }
+    if length <= 3 {
+        return text.chars().take(length).collect();
+    }
+    format!("{}...", text.chars().take(length - 3).collect::<String>())
+}
+
+/// Inserts a comma between each group of three digits in the whole part of a number.
+fn group_thousands(number: &str) -> String {
+    let (sign, number) = match number.strip_prefix('-') {
+        Some(number) => ("-", number),
+        None => ("", number),
+    };
+    let (whole, fraction) = match number.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (number, None),
+    };
+
+    let mut grouped = String::new();
+    for (index, digit) in whole.chars().enumerate() {
+        if index > 0 && (whole.len() - index) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    match fraction {
+        Some(fraction) => format!("{}{}.{}", sign, grouped, fraction),
+        None => format!("{}{}", sign, grouped),
+    }
+}
+
+/// Reads a date, a date and time, or a Unix timestamp in seconds.
+fn parse_date(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim();
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Some(date.naive_local());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(text, format) {
+            return Some(date);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0);
+    }
+
+    text.parse::<i64>()
+        .ok()
+        .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+        .map(|date| date.naive_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::USSDData;
+
+    fn session() -> USSDSession {
+        let mut session = USSDSession::new(
+            "template-session".to_string(),
+            "MainScreen".to_string(),
+            "en".to_string(),
+            "1234567890".to_string(),
+        );
+        session
+            .data
+            .insert("name".to_string(), USSDData::Str("Kofi Mensah".to_string()));
+        session
+            .data
+            .insert("points".to_string(), USSDData::Int(1234567));
+        session
+            .data
+            .insert("balance".to_string(), USSDData::Float(-1500.5));
+        session.data.insert(
+            "joined".to_string(),
+            USSDData::Str("2024-03-09T10:30:00Z".to_string()),
+        );
+        session.data.insert(
+            "accounts".to_string(),
+            USSDData::List(vec![
+                USSDData::new_dict(HashMap::from([
+                    ("name".to_string(), USSDData::Str("Savings".to_string())),
+                    ("balance".to_string(), USSDData::Int(2500)),
+                ])),
+                USSDData::new_dict(HashMap::from([
+                    ("name".to_string(), USSDData::Str("Current".to_string())),
+                    ("balance".to_string(), USSDData::Float(10.25)),
+                ])),
+            ]),
+        );
+        session
+    }
+
+    fn render(source: &str) -> String {
+        Template::parse(source).unwrap().render(&session())
+    }
+
+    #[test]
+    fn test_render_values() {
+        assert_eq!(render("Points: {{points}}"), "Points: 1234567");
+        assert_eq!(render("Balance: {{balance}}"), "Balance: -1500.5");
+        assert_eq!(render("{{accounts[1].name}}"), "Current");
+        assert_eq!(render("{{points + 1}}"), "1234568");
+        assert_eq!(render("Hello {{missing}}"), "Hello {{missing}}");
+        assert_eq!(render("No tags"), "No tags");
+    }
+
+    #[test]
+    fn test_render_filters() {
+        assert_eq!(render("{{missing | default: 'customer'}}"), "customer");
+        assert_eq!(render("{{name | default: 'customer'}}"), "Kofi Mensah");
+        assert_eq!(
+            render("{{name | upper}} {{name | lower}}"),
+            "KOFI MENSAH kofi mensah"
+        );
+        assert_eq!(render("{{name | truncate: 7}}"), "Kofi...");
+        assert_eq!(render("{{points | number}}"), "1,234,567");
+        assert_eq!(render("{{balance | number: 0}}"), "-1,500");
+        assert_eq!(render("{{balance | currency: 'GHS'}}"), "GHS -1,500.50");
+        assert_eq!(render("{{points | currency}}"), "1,234,567.00");
+        assert_eq!(render("{{joined | date}}"), "09/03/2024");
+        assert_eq!(
+            render("{{joined | date: '%d %b %Y %H:%M'}}"),
+            "09 Mar 2024 10:30"
+        );
+        assert_eq!(render("{{name | date}}"), "Kofi Mensah");
+        assert_eq!(render("{{joined | date: '%Q'}}"), "2024-03-09T10:30:00Z");
+        assert_eq!(
+            render("{{missing | default: name | upper | truncate: 4}}"),
+            "K..."
+        );
+    }
+
+    #[test]
+    fn test_render_blocks() {
+        assert_eq!(
+            render("{{#if balance < 0}}Overdrawn{{else if balance == 0}}Empty{{else}}OK{{/if}}"),
+            "Overdrawn"
+        );
+        assert_eq!(render("{{#if missing || points > 10}}yes{{/if}}"), "yes");
+        assert_eq!(render("{{#if missing}}yes{{/if}}"), "");
+        assert_eq!(
+            render("{{#each accounts}}{{@number}}. {{name}} {{balance | currency}}\n{{/each}}"),
+            "1. Savings 2,500.00\n2. Current 10.25\n"
+        );
+        assert_eq!(
+            render("{{#each missing}}{{this}}{{else}}No accounts{{/each}}"),
+            "No accounts"
+        );
+        assert_eq!(
+            render("{{#each accounts}}{{#if @index > 0}}, {{/if}}{{this.name | upper}}{{/each}}"),
+            "SAVINGS, CURRENT"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |source: &str| Template::parse(source).unwrap_err();
+
+        assert_eq!(error("Hi {{name").position, 3);
+        assert_eq!(error("{{#if points > 1}}yes").message, "Unclosed {{#if}}");
+        assert_eq!(error("yes{{/if}}").message, "Unexpected {{/if}}");
+        assert_eq!(error("{{name | shout}}").message, "Unknown filter 'shout'");
+        assert_eq!(error("{{#loop items}}{{/loop}}").position, 0);
+        assert!(error("{{points >}}")
+            .message
+            .starts_with("Invalid expression"));
+        assert!(error("{{name | truncate}}").message.contains("truncate"));
+        assert_eq!(
+            error("{{#each accounts}}{{/if}}").message,
+            "Unexpected {{/if}}"
+        );
+    }
+}